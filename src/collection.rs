@@ -0,0 +1,385 @@
+use std::{collections::BTreeMap, fmt::Display, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::reqx::ReqxRequest;
+
+//a collection is saved as a directory: `collection.toml` holds the metadata and the list of
+//files it wrote, every folder is a subdirectory and every request a `<name>.toml` file
+const META_FILE: &str = "collection.toml";
+const REQUEST_EXT: &str = "toml";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Folder {
+    pub folders: BTreeMap<String, Folder>,
+    pub requests: BTreeMap<String, ReqxRequest>,
+}
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Collection {
+    pub name: String,
+    pub root: Folder,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Folder,
+    Request,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub kind: EntryKind,
+}
+#[derive(Debug)]
+pub enum CollectionError {
+    InvalidName(String),
+    NotFound(String),
+    AlreadyExists(String),
+    //saving would mix the collection into a directory that holds something else
+    NotACollection(String),
+    Io(io::Error),
+    Parse(String, toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+enum Node {
+    Folder(Folder),
+    Request(Box<ReqxRequest>),
+}
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    name: String,
+    //folders (ending in `/`) and request files written by the last save, relative to the
+    //collection directory; only these are removed when they leave the collection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<String>,
+}
+impl Display for CollectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidName(name) => write!(f, "`{name}` is not a valid entry name"),
+            Self::NotFound(path) => write!(f, "no entry at `{path}`"),
+            Self::AlreadyExists(path) => write!(f, "an entry already exists at `{path}`"),
+            Self::NotACollection(dir) => write!(
+                f,
+                "`{dir}` is not empty and has no {META_FILE}, refusing to save a collection there"
+            ),
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Parse(file, e) => write!(f, "could not parse `{file}`: {e}"),
+            Self::Serialize(e) => write!(f, "could not serialize: {e}"),
+        }
+    }
+}
+impl std::error::Error for CollectionError {}
+impl From<io::Error> for CollectionError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<toml::ser::Error> for CollectionError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Serialize(e)
+    }
+}
+impl Folder {
+    //a folder `a.toml` and a request `a` would both be saved as `a.toml`, so either takes
+    //the name of the other
+    fn contains(&self, name: &str) -> bool {
+        let as_request = name
+            .strip_suffix(REQUEST_EXT)
+            .and_then(|stem| stem.strip_suffix('.'))
+            .is_some_and(|stem| self.requests.contains_key(stem));
+        self.folders.contains_key(name)
+            || self.requests.contains_key(name)
+            || self.folders.contains_key(&format!("{name}.{REQUEST_EXT}"))
+            || as_request
+    }
+    //turns any name into a valid one that is not taken in this folder, used by importers
+    pub fn free_name(&self, name: &str) -> String {
+        let base = sanitize_name(name);
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.contains(&name) {
+            name = format!("{base} ({n})");
+            n += 1;
+        }
+        name
+    }
+    fn load(dir: &Path) -> Result<Self, CollectionError> {
+        let mut folder = Self::default();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if name.starts_with('.') || name == META_FILE {
+                continue;
+            }
+            if path.is_dir() {
+                folder.folders.insert(name.to_string(), Self::load(&path)?);
+            } else if path.extension().and_then(|e| e.to_str()) == Some(REQUEST_EXT) {
+                let text = fs::read_to_string(&path)?;
+                let request = toml::from_str(&text)
+                    .map_err(|e| CollectionError::Parse(path.display().to_string(), e))?;
+                let name = &name[..name.len() - REQUEST_EXT.len() - 1];
+                folder.requests.insert(name.to_string(), request);
+            }
+        }
+        Ok(folder)
+    }
+    //`prefix` is the path of `dir` in the collection, every written path goes to `files`
+    fn save(
+        &self,
+        dir: &Path,
+        prefix: &str,
+        files: &mut Vec<String>,
+    ) -> Result<(), CollectionError> {
+        fs::create_dir_all(dir)?;
+        for (name, request) in &self.requests {
+            let file = format!("{name}.{REQUEST_EXT}");
+            //the maps are public, so a clash can get here without add_folder seeing it
+            if self.folders.contains_key(&file) {
+                return Err(CollectionError::AlreadyExists(format!("{prefix}{file}")));
+            }
+            write_if_changed(&dir.join(&file), &toml::to_string_pretty(request)?)?;
+            files.push(format!("{prefix}{file}"));
+        }
+        for (name, folder) in &self.folders {
+            let path = format!("{prefix}{name}/");
+            files.push(path.clone());
+            folder.save(&dir.join(name), &path, files)?;
+        }
+        Ok(())
+    }
+}
+impl Collection {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            root: Folder::default(),
+        }
+    }
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, CollectionError> {
+        let dir = dir.as_ref();
+        let meta_path = dir.join(META_FILE);
+        let meta: Meta = toml::from_str(&fs::read_to_string(&meta_path)?)
+            .map_err(|e| CollectionError::Parse(meta_path.display().to_string(), e))?;
+        Ok(Self {
+            name: meta.name,
+            root: Folder::load(dir)?,
+        })
+    }
+    //entries removed from the collection are deleted from disk when an earlier save wrote
+    //them, anything else in the directory is left alone
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<(), CollectionError> {
+        let dir = dir.as_ref();
+        let meta_path = dir.join(META_FILE);
+        let previous = match fs::read_to_string(&meta_path) {
+            Ok(text) => {
+                toml::from_str::<Meta>(&text)
+                    .map_err(|e| CollectionError::Parse(meta_path.display().to_string(), e))?
+                    .files
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+                    return Err(CollectionError::NotACollection(dir.display().to_string()));
+                }
+                Vec::new()
+            }
+            Err(e) => return Err(e.into()),
+        };
+        fs::create_dir_all(dir)?;
+        let mut files = Vec::new();
+        self.root.save(dir, "", &mut files)?;
+        let (mut folders, requests): (Vec<_>, Vec<_>) = previous
+            .iter()
+            .filter(|path| !files.contains(path) && is_relative(path))
+            .partition(|path| path.ends_with('/'));
+        for path in requests {
+            match fs::remove_file(dir.join(path)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        //deepest first, a folder that still holds files nobody saved here stays
+        folders.sort_by_key(|path| std::cmp::Reverse(path.len()));
+        for path in folders {
+            let _ = fs::remove_dir(dir.join(path));
+        }
+        let meta = Meta {
+            name: self.name.clone(),
+            files,
+        };
+        write_if_changed(&meta_path, &toml::to_string_pretty(&meta)?)?;
+        Ok(())
+    }
+    //paths are `/` separated names, the empty path is the root folder
+    pub fn folder(&self, path: &str) -> Result<&Folder, CollectionError> {
+        let mut folder = &self.root;
+        for name in split(path) {
+            folder = folder
+                .folders
+                .get(name)
+                .ok_or_else(|| CollectionError::NotFound(path.into()))?;
+        }
+        Ok(folder)
+    }
+    pub fn folder_mut(&mut self, path: &str) -> Result<&mut Folder, CollectionError> {
+        let mut folder = &mut self.root;
+        for name in split(path) {
+            folder = folder
+                .folders
+                .get_mut(name)
+                .ok_or_else(|| CollectionError::NotFound(path.into()))?;
+        }
+        Ok(folder)
+    }
+    pub fn list(&self, path: &str) -> Result<Vec<Entry>, CollectionError> {
+        let folder = self.folder(path)?;
+        let folders = folder.folders.keys().map(|name| Entry {
+            name: name.clone(),
+            kind: EntryKind::Folder,
+        });
+        let requests = folder.requests.keys().map(|name| Entry {
+            name: name.clone(),
+            kind: EntryKind::Request,
+        });
+        Ok(folders.chain(requests).collect())
+    }
+    pub fn request(&self, path: &str) -> Option<&ReqxRequest> {
+        let (parent, name) = split_last(path);
+        self.folder(parent).ok()?.requests.get(name)
+    }
+    pub fn request_mut(&mut self, path: &str) -> Option<&mut ReqxRequest> {
+        let (parent, name) = split_last(path);
+        self.folder_mut(parent).ok()?.requests.get_mut(name)
+    }
+    pub fn add_request(
+        &mut self,
+        folder: &str,
+        name: &str,
+        request: ReqxRequest,
+    ) -> Result<(), CollectionError> {
+        validate_name(name)?;
+        let parent = self.folder_mut(folder)?;
+        if parent.contains(name) {
+            return Err(CollectionError::AlreadyExists(join(folder, name)));
+        }
+        parent.requests.insert(name.to_string(), request);
+        Ok(())
+    }
+    pub fn add_folder(&mut self, folder: &str, name: &str) -> Result<(), CollectionError> {
+        validate_name(name)?;
+        let parent = self.folder_mut(folder)?;
+        if parent.contains(name) {
+            return Err(CollectionError::AlreadyExists(join(folder, name)));
+        }
+        parent.folders.insert(name.to_string(), Folder::default());
+        Ok(())
+    }
+    pub fn remove(&mut self, path: &str) -> Result<(), CollectionError> {
+        self.take(path).map(|_| ())
+    }
+    pub fn rename(&mut self, path: &str, new_name: &str) -> Result<(), CollectionError> {
+        validate_name(new_name)?;
+        let (parent, _) = split_last(path);
+        if self.folder(parent)?.contains(new_name) {
+            return Err(CollectionError::AlreadyExists(join(parent, new_name)));
+        }
+        let entry = self.take(path)?;
+        self.put(parent, new_name, entry);
+        Ok(())
+    }
+    //moves the entry at `path` into the folder at `to`, keeping its name
+    pub fn move_entry(&mut self, path: &str, to: &str) -> Result<(), CollectionError> {
+        let (_, name) = split_last(path);
+        let to_path = join(to, name);
+        if to_path == path || to_path.starts_with(&format!("{path}/")) {
+            return Err(CollectionError::InvalidName(to.into()));
+        }
+        if self.folder(to)?.contains(name) {
+            return Err(CollectionError::AlreadyExists(to_path));
+        }
+        let entry = self.take(path)?;
+        self.put(to, name, entry);
+        Ok(())
+    }
+    fn take(&mut self, path: &str) -> Result<Node, CollectionError> {
+        let (parent, name) = split_last(path);
+        let parent = self.folder_mut(parent)?;
+        if let Some(folder) = parent.folders.remove(name) {
+            Ok(Node::Folder(folder))
+        } else if let Some(request) = parent.requests.remove(name) {
+            Ok(Node::Request(Box::new(request)))
+        } else {
+            Err(CollectionError::NotFound(path.into()))
+        }
+    }
+    //callers check that `folder` exists before taking the entry out
+    fn put(&mut self, folder: &str, name: &str, node: Node) {
+        let parent = self.folder_mut(folder).unwrap();
+        match node {
+            Node::Folder(folder) => {
+                parent.folders.insert(name.to_string(), folder);
+            }
+            Node::Request(request) => {
+                parent.requests.insert(name.to_string(), *request);
+            }
+        }
+    }
+}
+//a hand edited manifest must not reach outside the collection directory
+fn is_relative(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+fn split_last(path: &str) -> (&str, &str) {
+    let path = path.trim_matches('/');
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+fn join(folder: &str, name: &str) -> String {
+    let folder = folder.trim_matches('/');
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{folder}/{name}")
+    }
+}
+//names end up as file names, so anything a filesystem could interpret is refused
+fn validate_name(name: &str) -> Result<(), CollectionError> {
+    let invalid = name.is_empty()
+        || name.starts_with('.')
+        || name == META_FILE.trim_end_matches(".toml")
+        || name.chars().any(is_reserved);
+    if invalid {
+        Err(CollectionError::InvalidName(name.into()))
+    } else {
+        Ok(())
+    }
+}
+//replaces whatever validate_name would refuse
+pub fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| if is_reserved(c) { '_' } else { c })
+        .collect();
+    if validate_name(&name).is_err() {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+fn is_reserved(c: char) -> bool {
+    matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control()
+}
+//rewriting unchanged files would churn their mtime and confuse tools watching the directory
+fn write_if_changed(path: &Path, contents: &str) -> io::Result<()> {
+    if fs::read_to_string(path).is_ok_and(|old| old == contents) {
+        return Ok(());
+    }
+    fs::write(path, contents)
+}
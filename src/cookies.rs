@@ -0,0 +1,487 @@
+use std::{collections::BTreeMap, fmt::Display, fs, io, net::IpAddr, path::Path};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use http::Method;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+//cookies.txt marks http only cookies with this prefix on the domain column, as curl does
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    //lowercase, without a leading dot
+    pub domain: String,
+    //set without a Domain attribute, only sent back to exactly `domain`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub host_only: bool,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secure: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub http_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<SameSite>,
+    //None is a session cookie, kept until the jar is cleared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CookieJar {
+    #[serde(default)]
+    cookies: Vec<Cookie>,
+}
+//one jar per environment, so logging in on staging does not leak into production
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CookieStore {
+    //used by requests sent outside of any environment
+    #[serde(default)]
+    pub default: CookieJar,
+    #[serde(default)]
+    pub environments: BTreeMap<String, CookieJar>,
+}
+#[derive(Debug)]
+pub enum CookieError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    //line number, 1 based, of a cookies.txt line that is not 7 tab separated fields
+    Netscape(usize),
+}
+impl Display for CookieError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Parse(e) => write!(f, "could not parse cookie store: {e}"),
+            Self::Serialize(e) => write!(f, "could not serialize cookie store: {e}"),
+            Self::Netscape(line) => write!(f, "invalid cookies.txt entry on line {line}"),
+        }
+    }
+}
+impl std::error::Error for CookieError {}
+impl From<io::Error> for CookieError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<toml::de::Error> for CookieError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Parse(e)
+    }
+}
+impl From<toml::ser::Error> for CookieError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Serialize(e)
+    }
+}
+impl Cookie {
+    pub fn new(
+        name: impl Into<String>,
+        value: impl Into<String>,
+        domain: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            domain: normalize_domain(&domain.into()),
+            host_only: false,
+            path: String::from("/"),
+            secure: false,
+            http_only: false,
+            same_site: None,
+            expires: None,
+            created: Utc::now(),
+        }
+    }
+    //a Set-Cookie header received from `url`, None when it is malformed or not allowed to set
+    //a cookie for that url, rfc 6265 section 5.2 and 5.3
+    pub fn parse(set_cookie: &str, url: &Url, now: DateTime<Utc>) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut attributes = set_cookie.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            secure: false,
+            http_only: false,
+            same_site: None,
+            expires: None,
+            created: now,
+        };
+        let mut max_age = None;
+        for attribute in attributes {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    cookie.domain = normalize_domain(value);
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => Some(SameSite::Strict),
+                        "lax" => Some(SameSite::Lax),
+                        "none" => Some(SameSite::None),
+                        _ => None,
+                    }
+                }
+                "max-age" => {
+                    max_age = value
+                        .parse::<i64>()
+                        .ok()
+                        .map(|secs| now + chrono::Duration::seconds(secs.clamp(-1, 1 << 40)))
+                }
+                "expires" => {
+                    if let Some(expires) = parse_date(value) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                _ => {}
+            }
+        }
+        //max-age wins over expires when both are given
+        if max_age.is_some() {
+            cookie.expires = max_age;
+        }
+        if !cookie.host_only && !domain_match(&host, &cookie.domain) {
+            return None;
+        }
+        //no public suffix list, but a bare top level domain is never accepted
+        if !cookie.host_only && !cookie.domain.contains('.') && cookie.domain != host {
+            return None;
+        }
+        let secure_origin = is_secure(url);
+        if cookie.secure && !secure_origin {
+            return None;
+        }
+        //browsers reject SameSite=None without Secure
+        if cookie.same_site == Some(SameSite::None) && !cookie.secure {
+            return None;
+        }
+        if cookie.name.starts_with("__Secure-") && !cookie.secure {
+            return None;
+        }
+        if cookie.name.starts_with("__Host-")
+            && !(cookie.secure && cookie.host_only && cookie.path == "/")
+        {
+            return None;
+        }
+        Some(cookie)
+    }
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+    //whether this cookie is sent with a request to `url`; a cross site request is one
+    //that another site caused, like a redirect, and leaves out Strict and, unless it is
+    //a safe method, Lax cookies
+    pub fn matches(
+        &self,
+        url: &Url,
+        method: &Method,
+        cross_site: bool,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let Some(host) = url.host_str().map(|host| host.to_ascii_lowercase()) else {
+            return false;
+        };
+        let domain = match self.host_only {
+            true => host == self.domain,
+            false => domain_match(&host, &self.domain),
+        };
+        let same_site = match (cross_site, self.same_site) {
+            (false, _) | (true, Some(SameSite::None)) => true,
+            (true, Some(SameSite::Strict)) => false,
+            //browsers treat a cookie without the attribute as Lax
+            (true, _) => matches!(*method, Method::GET | Method::HEAD),
+        };
+        domain
+            && same_site
+            && path_match(url.path(), &self.path)
+            && (!self.secure || is_secure(url))
+            && !self.is_expired(now)
+    }
+    fn same_key(&self, other: &Self) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+impl CookieJar {
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.iter()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+    pub fn get(&self, domain: &str, path: &str, name: &str) -> Option<&Cookie> {
+        let domain = normalize_domain(domain);
+        self.cookies
+            .iter()
+            .find(|c| c.domain == domain && c.path == path && c.name == name)
+    }
+    //adds or replaces the cookie with the same name, domain and path; an expired cookie
+    //only removes the one it replaces, which is how servers delete cookies
+    pub fn insert(&mut self, mut cookie: Cookie) {
+        let now = Utc::now();
+        cookie.domain = normalize_domain(&cookie.domain);
+        if let Some(i) = self.cookies.iter().position(|c| c.same_key(&cookie)) {
+            let old = self.cookies.remove(i);
+            cookie.created = old.created;
+        }
+        if !cookie.is_expired(now) {
+            self.cookies.push(cookie);
+        }
+    }
+    pub fn remove(&mut self, domain: &str, path: &str, name: &str) -> Option<Cookie> {
+        let domain = normalize_domain(domain);
+        let i = self
+            .cookies
+            .iter()
+            .position(|c| c.domain == domain && c.path == path && c.name == name)?;
+        Some(self.cookies.remove(i))
+    }
+    //removes every cookie for `domain` and its subdomains
+    pub fn remove_domain(&mut self, domain: &str) {
+        let domain = normalize_domain(domain);
+        self.cookies.retain(|c| !domain_match(&c.domain, &domain));
+    }
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+    pub fn clear_expired(&mut self) {
+        let now = Utc::now();
+        self.cookies.retain(|c| !c.is_expired(now));
+    }
+    //stores every Set-Cookie header of a response to `url`, the ones that are invalid
+    //or not allowed for that url are ignored the way a browser would
+    pub fn store<'a>(&mut self, url: &Url, set_cookies: impl IntoIterator<Item = &'a str>) {
+        let now = Utc::now();
+        for set_cookie in set_cookies {
+            if let Some(cookie) = Cookie::parse(set_cookie, url, now) {
+                //an insecure origin cannot shadow a secure cookie of the same name
+                let shadows_secure = !is_secure(url)
+                    && self.cookies.iter().any(|c| {
+                        c.secure
+                            && c.name == cookie.name
+                            && (domain_match(&c.domain, &cookie.domain)
+                                || domain_match(&cookie.domain, &c.domain))
+                            && path_match(&cookie.path, &c.path)
+                    });
+                if !shadows_secure {
+                    self.insert(cookie);
+                }
+            }
+        }
+    }
+    //value of the Cookie header for a request, None when no cookie matches;
+    //longer paths go first and then older cookies, rfc 6265 section 5.4
+    pub fn header(&self, url: &Url, method: &Method, cross_site: bool) -> Option<String> {
+        let now = Utc::now();
+        let mut cookies: Vec<_> = self
+            .cookies
+            .iter()
+            .filter(|c| c.matches(url, method, cross_site, now))
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+        cookies.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.created.cmp(&b.created))
+        });
+        let pairs: Vec<_> = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+    //netscape cookies.txt as written by curl and browser extensions, SameSite is not part
+    //of the format and is lost
+    pub fn to_netscape(&self) -> String {
+        let mut out = String::from("# Netscape HTTP Cookie File\n\n");
+        for cookie in &self.cookies {
+            let prefix = if cookie.http_only {
+                HTTP_ONLY_PREFIX
+            } else {
+                ""
+            };
+            let domain = match cookie.host_only {
+                true => cookie.domain.clone(),
+                false => format!(".{}", cookie.domain),
+            };
+            out.push_str(&format!(
+                "{prefix}{domain}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                flag(!cookie.host_only),
+                cookie.path,
+                flag(cookie.secure),
+                cookie.expires.map(|e| e.timestamp()).unwrap_or(0),
+                cookie.name,
+                cookie.value
+            ));
+        }
+        out
+    }
+    //adds the cookies of a cookies.txt file, replacing the ones with the same name, domain and path
+    pub fn import_netscape(&mut self, text: &str) -> Result<usize, CookieError> {
+        let now = Utc::now();
+        let mut cookies = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+                Some(line) => (line, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<_> = line.split('\t').collect();
+            let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+                return Err(CookieError::Netscape(i + 1));
+            };
+            let expires: i64 = expires
+                .trim()
+                .parse()
+                .map_err(|_| CookieError::Netscape(i + 1))?;
+            let expires = match expires {
+                0 => None,
+                secs => Some(
+                    Utc.timestamp_opt(secs, 0)
+                        .single()
+                        .ok_or(CookieError::Netscape(i + 1))?,
+                ),
+            };
+            cookies.push(Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: normalize_domain(domain),
+                host_only: !subdomains.eq_ignore_ascii_case("TRUE"),
+                path: path.to_string(),
+                secure: secure.eq_ignore_ascii_case("TRUE"),
+                http_only,
+                same_site: None,
+                expires,
+                created: now,
+            });
+        }
+        let count = cookies.len();
+        for cookie in cookies {
+            self.insert(cookie);
+        }
+        Ok(count)
+    }
+}
+impl CookieStore {
+    //a missing file is an empty store, so the first start needs no special casing
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CookieError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    //session cookies are kept too, an api client is expected to stay logged in across restarts
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CookieError> {
+        let mut stored = self.clone();
+        stored.default.clear_expired();
+        for jar in stored.environments.values_mut() {
+            jar.clear_expired();
+        }
+        stored.environments.retain(|_, jar| !jar.is_empty());
+        fs::write(path, toml::to_string_pretty(&stored)?)?;
+        Ok(())
+    }
+    pub fn jar(&self, environment: Option<&str>) -> Option<&CookieJar> {
+        match environment {
+            Some(name) => self.environments.get(name),
+            None => Some(&self.default),
+        }
+    }
+    pub fn jar_mut(&mut self, environment: Option<&str>) -> &mut CookieJar {
+        match environment {
+            Some(name) => self.environments.entry(name.to_string()).or_default(),
+            None => &mut self.default,
+        }
+    }
+}
+//without a public suffix list the site is approximated by the last two labels of the host
+pub fn same_site(a: &Url, b: &Url) -> bool {
+    let site = |url: &Url| {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+            return host;
+        }
+        let labels: Vec<_> = host.rsplitn(3, '.').collect();
+        match labels[..] {
+            [tld, domain, ..] => format!("{domain}.{tld}"),
+            _ => host,
+        }
+    };
+    site(a) == site(b)
+}
+fn normalize_domain(domain: &str) -> String {
+    domain.trim().trim_start_matches('.').to_ascii_lowercase()
+}
+fn flag(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+fn is_secure(url: &Url) -> bool {
+    matches!(url.scheme(), "https" | "wss")
+}
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.trim_matches(['[', ']']).parse::<IpAddr>().is_err())
+}
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+//the directory of the request path, `/a/b` gives `/a`
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => String::from("/"),
+        Some(i) => url.path()[..i].to_string(),
+    }
+}
+//the formats servers still send: rfc 1123, the dashed netscape one, rfc 850 and asctime
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    let value = value.trim_end_matches(" GMT").trim_end_matches(" UTC");
+    [
+        "%a, %d-%b-%Y %H:%M:%S",
+        "%A, %d-%b-%y %H:%M:%S",
+        "%a, %d %b %Y %H:%M:%S",
+        "%a %b %e %H:%M:%S %Y",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .map(|date| date.and_utc())
+}
@@ -0,0 +1,518 @@
+use std::{collections::BTreeMap, fmt::Display, fs, io, path::Path};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+use crate::reqx::{
+    AwsSigV4, FormPart, GraphQlQuery, OAuth2Config, OAuth2Grant, PartValue, ProxySettings,
+    ReqxAuth, ReqxBody, ReqxData, ReqxError, ReqxRequest,
+};
+
+//what secrets are replaced with whenever they are shown instead of sent
+pub const MASK: &str = "********";
+//headers and query parameters that carry credentials by their name, masked in exports
+//whether or not their value came from a secret variable
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "api-key",
+    "apikey",
+    "x-auth-token",
+    "x-amz-security-token",
+];
+const SECRET_PARAMS: &[&str] = &[
+    "api_key",
+    "api-key",
+    "apikey",
+    "key",
+    "token",
+    "access_token",
+    "client_secret",
+    "password",
+    "signature",
+    "x-amz-signature",
+    "x-amz-security-token",
+];
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Variable {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+}
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    pub name: String,
+    pub variables: BTreeMap<String, Variable>,
+    //values are templates, so a proxy password can live in a secret variable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxySettings>,
+    //argon2 salt of the passphrase key, kept between saves so unchanged files stay stable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+}
+#[derive(Debug)]
+pub enum EnvironmentError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    PassphraseRequired,
+    //wrong passphrase or a tampered file, aes-gcm cannot tell them apart
+    Decrypt(String),
+}
+impl std::fmt::Debug for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Variable")
+            .field("value", &self.display())
+            .field("secret", &self.secret)
+            .finish()
+    }
+}
+impl Display for EnvironmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Parse(e) => write!(f, "could not parse environment: {e}"),
+            Self::Serialize(e) => write!(f, "could not serialize environment: {e}"),
+            Self::PassphraseRequired => {
+                write!(f, "environment has secrets, a passphrase is needed")
+            }
+            Self::Decrypt(name) => {
+                write!(f, "could not decrypt secret `{name}`, wrong passphrase?")
+            }
+        }
+    }
+}
+impl std::error::Error for EnvironmentError {}
+impl From<io::Error> for EnvironmentError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<toml::de::Error> for EnvironmentError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Parse(e)
+    }
+}
+impl From<toml::ser::Error> for EnvironmentError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Serialize(e)
+    }
+}
+impl Variable {
+    pub fn plain(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            secret: false,
+        }
+    }
+    pub fn secret(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            secret: true,
+        }
+    }
+    //value safe to show in the ui or logs
+    pub fn display(&self) -> &str {
+        if self.secret {
+            MASK
+        } else {
+            &self.value
+        }
+    }
+}
+//collects every unresolved name so a single error can list all of them
+struct Renderer<'a> {
+    variables: &'a BTreeMap<String, Variable>,
+    reveal: bool,
+    missing: Vec<String>,
+    recursive: Vec<String>,
+}
+impl Renderer<'_> {
+    fn render(&mut self, template: &str) -> String {
+        self.expand(template, &mut Vec::new())
+    }
+    //values are templates too, `stack` holds the variables being expanded to catch cycles
+    fn expand(&mut self, template: &str, stack: &mut Vec<String>) -> String {
+        let variables = self.variables;
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let name = rest[start + 2..start + 2 + len].trim();
+            match variables.get(name) {
+                //a masked secret is never expanded, what it refers to could be a secret too
+                Some(var) if var.secret && !self.reveal => out.push_str(MASK),
+                Some(_) if stack.iter().any(|n| n == name) => {
+                    if !self.recursive.iter().any(|r| r == name) {
+                        self.recursive.push(name.to_string());
+                    }
+                    out.push_str(&rest[start..start + len + 4]);
+                }
+                Some(var) => {
+                    stack.push(name.to_string());
+                    let value = self.expand(&var.value, stack);
+                    stack.pop();
+                    out.push_str(&value);
+                }
+                None => {
+                    if !self.missing.iter().any(|m| m == name) {
+                        self.missing.push(name.to_string());
+                    }
+                    out.push_str(&rest[start..start + len + 4]);
+                }
+            }
+            rest = &rest[start + len + 4..];
+        }
+        out.push_str(rest);
+        out
+    }
+    fn auth(&mut self, auth: &ReqxAuth) -> ReqxAuth {
+        match auth {
+            ReqxAuth::None => ReqxAuth::None,
+            ReqxAuth::Basic { password, username } => ReqxAuth::Basic {
+                password: password.as_deref().map(|p| self.render(p)),
+                username: self.render(username),
+            },
+            ReqxAuth::Bearer(token) => ReqxAuth::Bearer(self.render(token)),
+            ReqxAuth::OAuth2(config) => ReqxAuth::OAuth2(OAuth2Config {
+                grant: match &config.grant {
+                    OAuth2Grant::ClientCredentials => OAuth2Grant::ClientCredentials,
+                    OAuth2Grant::Password { username, password } => OAuth2Grant::Password {
+                        username: self.render(username),
+                        password: self.render(password),
+                    },
+                    OAuth2Grant::AuthorizationCode {
+                        auth_url,
+                        redirect_port,
+                    } => OAuth2Grant::AuthorizationCode {
+                        auth_url: self.render(auth_url),
+                        redirect_port: *redirect_port,
+                    },
+                },
+                token_url: self.render(&config.token_url),
+                client_id: self.render(&config.client_id),
+                client_secret: config.client_secret.as_deref().map(|s| self.render(s)),
+                scope: config.scope.as_deref().map(|s| self.render(s)),
+            }),
+            ReqxAuth::Digest { password, username } => ReqxAuth::Digest {
+                password: self.render(password),
+                username: self.render(username),
+            },
+            ReqxAuth::ApiKey {
+                name,
+                value,
+                location,
+            } => ReqxAuth::ApiKey {
+                name: self.render(name),
+                value: self.render(value),
+                location: *location,
+            },
+            ReqxAuth::AwsSigV4(config) => ReqxAuth::AwsSigV4(AwsSigV4 {
+                access_key: self.render(&config.access_key),
+                secret_key: self.render(&config.secret_key),
+                session_token: config.session_token.as_deref().map(|s| self.render(s)),
+                region: self.render(&config.region),
+                service: self.render(&config.service),
+            }),
+        }
+    }
+    //file contents are sent as they are, only their paths are templated
+    fn parts(&mut self, parts: &[FormPart]) -> Vec<FormPart> {
+        parts
+            .iter()
+            .map(|part| FormPart {
+                name: self.render(&part.name),
+                value: match &part.value {
+                    PartValue::Text(text) => PartValue::Text(self.render(text)),
+                    PartValue::File(path) => match path.to_str() {
+                        Some(path) => PartValue::File(self.render(path).into()),
+                        None => PartValue::File(path.clone()),
+                    },
+                },
+                filename: part.filename.as_deref().map(|f| self.render(f)),
+                content_type: part.content_type.as_deref().map(|c| self.render(c)),
+            })
+            .collect()
+    }
+    //only string values are rendered, keys and the structure stay as they are
+    fn json(&mut self, value: &serde_json::Value) -> serde_json::Value {
+        use serde_json::Value;
+        match value {
+            Value::String(text) => Value::String(self.render(text)),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.json(v)).collect()),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), self.json(v)))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+    fn finish<T>(mut self, value: T) -> Result<T, ReqxError> {
+        if !self.recursive.is_empty() {
+            self.recursive.sort();
+            Err(ReqxError::RecursiveVariables(self.recursive))
+        } else if !self.missing.is_empty() {
+            self.missing.sort();
+            Err(ReqxError::UnresolvedVariables(self.missing))
+        } else {
+            Ok(value)
+        }
+    }
+}
+impl Environment {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.variables.insert(name.into(), Variable::plain(value));
+    }
+    pub fn set_secret(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.variables.insert(name.into(), Variable::secret(value));
+    }
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(|var| var.value.as_str())
+    }
+    pub fn has_secrets(&self) -> bool {
+        self.variables.values().any(|var| var.secret)
+    }
+    //secrets are stored as base64(nonce || ciphertext) under a key derived from the passphrase
+    pub fn load(
+        path: impl AsRef<Path>,
+        passphrase: Option<&str>,
+    ) -> Result<Self, EnvironmentError> {
+        let mut env: Self = toml::from_str(&fs::read_to_string(path)?)?;
+        if !env.has_secrets() {
+            return Ok(env);
+        }
+        let passphrase = passphrase.ok_or(EnvironmentError::PassphraseRequired)?;
+        let salt = env.salt.clone().unwrap_or_default();
+        let cipher = cipher(passphrase, &salt)?;
+        for (name, var) in env.variables.iter_mut().filter(|(_, var)| var.secret) {
+            let decrypt = || {
+                let raw = BASE64.decode(&var.value).ok()?;
+                if raw.len() < NONCE_LEN {
+                    return None;
+                }
+                let (nonce, data) = raw.split_at(NONCE_LEN);
+                let plain = cipher.decrypt(Nonce::from_slice(nonce), data).ok()?;
+                String::from_utf8(plain).ok()
+            };
+            var.value = decrypt().ok_or_else(|| EnvironmentError::Decrypt(name.clone()))?;
+        }
+        Ok(env)
+    }
+    pub fn save(
+        &mut self,
+        path: impl AsRef<Path>,
+        passphrase: Option<&str>,
+    ) -> Result<(), EnvironmentError> {
+        let mut stored = self.clone();
+        if self.has_secrets() {
+            let passphrase = passphrase.ok_or(EnvironmentError::PassphraseRequired)?;
+            let salt = self.salt.get_or_insert_with(|| {
+                let mut salt = [0u8; SALT_LEN];
+                aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut OsRng, &mut salt);
+                BASE64.encode(salt)
+            });
+            stored.salt = Some(salt.clone());
+            let cipher = cipher(passphrase, salt)?;
+            for (name, var) in stored.variables.iter_mut().filter(|(_, var)| var.secret) {
+                let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+                let data = cipher
+                    .encrypt(&nonce, var.value.as_bytes())
+                    .map_err(|_| EnvironmentError::Decrypt(name.clone()))?;
+                var.value = BASE64.encode([nonce.as_slice(), &data].concat());
+            }
+        }
+        fs::write(path, toml::to_string_pretty(&stored)?)?;
+        Ok(())
+    }
+    fn renderer(&self, reveal: bool) -> Renderer<'_> {
+        Renderer {
+            variables: &self.variables,
+            reveal,
+            missing: Vec::new(),
+            recursive: Vec::new(),
+        }
+    }
+    pub fn render(&self, template: &str) -> Result<String, ReqxError> {
+        let mut renderer = self.renderer(true);
+        let out = renderer.render(template);
+        renderer.finish(out)
+    }
+    pub fn render_proxy(&self) -> Result<Option<ProxySettings>, ReqxError> {
+        let Some(proxy) = &self.proxy else {
+            return Ok(None);
+        };
+        let mut r = self.renderer(true);
+        let rendered = ProxySettings {
+            http: proxy.http.as_deref().map(|p| r.render(p)),
+            https: proxy.https.as_deref().map(|p| r.render(p)),
+            username: proxy.username.as_deref().map(|u| r.render(u)),
+            password: proxy.password.as_deref().map(|p| r.render(p)),
+            no_proxy: proxy.no_proxy.iter().map(|host| r.render(host)).collect(),
+            use_system: proxy.use_system,
+        };
+        r.finish(Some(rendered))
+    }
+    pub fn render_request(&self, request: &ReqxRequest) -> Result<ReqxRequest, ReqxError> {
+        self.render_request_with(request, true)
+    }
+    //same as render_request but secrets show up as MASK, for display and exports
+    pub fn render_request_masked(&self, request: &ReqxRequest) -> Result<ReqxRequest, ReqxError> {
+        self.render_request_with(request, false)
+    }
+    fn render_request_with(
+        &self,
+        request: &ReqxRequest,
+        reveal: bool,
+    ) -> Result<ReqxRequest, ReqxError> {
+        let mut r = self.renderer(reveal);
+        let rendered = ReqxRequest {
+            method: request.method.clone(),
+            url: r.render(&request.url),
+            headers: request
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), r.render(value)))
+                .collect(),
+            authentication: r.auth(&request.authentication),
+            body: match &request.body {
+                ReqxBody::Text(text) => ReqxBody::Text(r.render(text)),
+                ReqxBody::Form(fields) => ReqxBody::Form(
+                    fields
+                        .iter()
+                        .map(|(k, v)| (r.render(k), r.render(v)))
+                        .collect(),
+                ),
+                ReqxBody::Multipart(parts) => ReqxBody::Multipart(r.parts(parts)),
+                ReqxBody::GraphQl(query) => ReqxBody::GraphQl(GraphQlQuery {
+                    query: r.render(&query.query),
+                    variables: query.variables.as_ref().map(|v| r.json(v)),
+                    operation_name: query.operation_name.as_deref().map(|n| r.render(n)),
+                }),
+                body => body.clone(),
+            },
+        };
+        r.finish(rendered)
+    }
+    //bodies that are streams or not utf8 are sent as they are
+    pub fn render_data(&self, url: &str, data: ReqxData) -> Result<(String, ReqxData), ReqxError> {
+        self.render_data_with(url, data, true)
+    }
+    //secret variables show up as the mask, for displaying or exporting what would be sent
+    pub fn render_data_masked(
+        &self,
+        url: &str,
+        data: ReqxData,
+    ) -> Result<(String, ReqxData), ReqxError> {
+        self.render_data_with(url, data, false)
+    }
+    fn render_data_with(
+        &self,
+        url: &str,
+        data: ReqxData,
+        reveal: bool,
+    ) -> Result<(String, ReqxData), ReqxError> {
+        let mut r = self.renderer(reveal);
+        let url = r.render(url);
+        let mut headers = HeaderMap::with_capacity(data.headers.len());
+        for (name, value) in &data.headers {
+            let value = match value.to_str() {
+                Ok(text) => HeaderValue::from_str(&r.render(text))
+                    .map_err(|_| ReqxError::InvalidHeader(name.to_string()))?,
+                Err(_) => value.clone(),
+            };
+            headers.append(name.clone(), value);
+        }
+        let body = match data.body {
+            Some(body) => match body.as_bytes().map(std::str::from_utf8) {
+                Some(Ok(text)) => Some(r.render(text).into()),
+                _ => Some(body),
+            },
+            None => None,
+        };
+        let form = data.form.map(|form| {
+            form.iter()
+                .map(|(k, v)| (r.render(k), r.render(v)))
+                .collect()
+        });
+        let multipart = data.multipart.map(|parts| r.parts(&parts));
+        let data = ReqxData {
+            headers,
+            authentication: r.auth(&data.authentication),
+            body,
+            form,
+            multipart,
+        };
+        r.finish((url, data))
+    }
+}
+//the value of a header as an export shows it, cookies keep their names
+pub(crate) fn mask_header(name: &str, value: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+        "cookie" => value
+            .split(';')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) => format!("{}={MASK}", name.trim()),
+                None => MASK.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+        "set-cookie" => match value.split_once(';') {
+            Some((pair, attributes)) => format!("{};{attributes}", mask_header("cookie", pair)),
+            None => mask_header("cookie", value),
+        },
+        name if SECRET_HEADERS.contains(&name) => MASK.to_string(),
+        _ => value.to_string(),
+    }
+}
+//the url with the values of credential query parameters masked
+pub(crate) fn mask_url(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+    let secret = |name: &str| SECRET_PARAMS.contains(&name.to_ascii_lowercase().as_str());
+    if !parsed.query_pairs().any(|(name, _)| secret(&name)) {
+        return url.to_string();
+    }
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(name, value)| match secret(&name) {
+            true => (name.into_owned(), MASK.to_string()),
+            false => (name.into_owned(), value.into_owned()),
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+fn cipher(passphrase: &str, salt: &str) -> Result<Aes256Gcm, EnvironmentError> {
+    let salt = BASE64
+        .decode(salt)
+        .map_err(|_| EnvironmentError::Decrypt(String::from("salt")))?;
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|_| EnvironmentError::Decrypt(String::from("salt")))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
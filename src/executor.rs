@@ -0,0 +1,137 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use tokio::{runtime::Handle, sync::mpsc::UnboundedSender};
+use yrl::winit::event_loop::EventLoopProxy;
+
+use crate::{
+    environment::Environment,
+    reqx::{Reqx, ReqxCancel, ReqxDownload, ReqxError, ReqxRequest, ReqxResponse},
+};
+
+pub type RequestId = u64;
+
+//runs Reqx work on the tokio runtime while the window loop owns the main thread, every
+//request ends in exactly one ReqxEvent sent back through the sink
+pub struct Executor<S> {
+    reqx: Arc<Reqx>,
+    runtime: Handle,
+    sink: S,
+    next: AtomicU64,
+}
+//where finished requests go, the window's event loop proxy or a channel
+pub trait EventSink: Clone + Send + 'static {
+    fn deliver(&self, event: ReqxEvent);
+}
+impl<E: From<ReqxEvent> + Send + 'static> EventSink for EventLoopProxy<E> {
+    fn deliver(&self, event: ReqxEvent) {
+        //the loop is gone once the window closed, nobody is left to tell
+        let _ = self.send_event(E::from(event));
+    }
+}
+impl EventSink for UnboundedSender<ReqxEvent> {
+    fn deliver(&self, event: ReqxEvent) {
+        let _ = self.send(event);
+    }
+}
+//a finished, failed or cancelled request, delivered to YHandler::user_event
+#[derive(Debug)]
+pub struct ReqxEvent {
+    pub id: RequestId,
+    pub result: Result<ReqxResponse, ReqxError>,
+}
+#[derive(Debug, Clone)]
+pub struct RequestHandle {
+    pub id: RequestId,
+    cancel: ReqxCancel,
+}
+impl RequestHandle {
+    //the request ends with ReqxError::Cancelled unless it already finished
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+//keeps one request in flight, sending another cancels it and drops its result
+#[derive(Debug, Default)]
+pub struct Pending(Option<RequestHandle>);
+impl Pending {
+    pub fn replace(&mut self, handle: RequestHandle) {
+        if let Some(previous) = self.0.replace(handle) {
+            previous.cancel();
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+    //the result when `event` is the request in flight, None for one that was replaced
+    pub fn finish(&mut self, event: ReqxEvent) -> Option<Result<ReqxResponse, ReqxError>> {
+        if self.0.as_ref().map(|p| p.id) != Some(event.id) {
+            return None;
+        }
+        self.0 = None;
+        Some(event.result)
+    }
+}
+impl<S: EventSink> Executor<S> {
+    //takes the runtime it is created in, so it has to be made inside #[tokio::main]
+    pub fn new(reqx: Arc<Reqx>, sink: S) -> Self {
+        Self {
+            reqx,
+            runtime: Handle::current(),
+            sink,
+            next: AtomicU64::new(1),
+        }
+    }
+    pub fn reqx(&self) -> &Arc<Reqx> {
+        &self.reqx
+    }
+    pub fn send(&self, request: ReqxRequest) -> RequestHandle {
+        let cancel = ReqxCancel::default();
+        let guard = cancel.clone();
+        self.spawn(cancel, |reqx| async move {
+            guard.guard(reqx.send(&request)).await
+        })
+    }
+    pub fn send_in(&self, env: Environment, request: ReqxRequest) -> RequestHandle {
+        let cancel = ReqxCancel::default();
+        let guard = cancel.clone();
+        self.spawn(cancel, |reqx| async move {
+            guard.guard(reqx.send_in(&env, &request)).await
+        })
+    }
+    //the handle cancels through the download's own ReqxCancel, progress still comes
+    //through ReqxDownload::progress
+    pub fn download(&self, request: ReqxRequest, download: ReqxDownload) -> RequestHandle {
+        self.spawn(download.canceller(), |reqx| async move {
+            reqx.download(&request, &download).await
+        })
+    }
+    pub fn download_in(
+        &self,
+        env: Environment,
+        request: ReqxRequest,
+        download: ReqxDownload,
+    ) -> RequestHandle {
+        self.spawn(download.canceller(), |reqx| async move {
+            reqx.download_in(&env, &request, &download).await
+        })
+    }
+    fn spawn<F>(&self, cancel: ReqxCancel, work: impl FnOnce(Arc<Reqx>) -> F) -> RequestHandle
+    where
+        F: Future<Output = Result<ReqxResponse, ReqxError>> + Send + 'static,
+    {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let work = work(self.reqx.clone());
+        let sink = self.sink.clone();
+        self.runtime.spawn(async move {
+            let result = work.await;
+            sink.deliver(ReqxEvent { id, result });
+        });
+        RequestHandle { id, cancel }
+    }
+}
@@ -0,0 +1,646 @@
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use http::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    environment::{mask_header, mask_url, MASK},
+    import::{ImportError, ImportIssue},
+    reqx::{
+        export::api_key_url, redirect_method, ApiKeyLocation, FormPart, PartValue, ReqxAuth,
+        ReqxBody, ReqxRequest, ReqxResponse, ReqxTiming, ORIGIN_BOUND,
+    },
+};
+
+const HAR_VERSION: &str = "1.2";
+
+//one request/response exchange, what a HAR entry holds and what can be replayed with Reqx::send
+#[derive(Debug, Clone, PartialEq)]
+pub struct HarEntry {
+    pub started: DateTime<Utc>,
+    pub request: ReqxRequest,
+    pub response: Option<ReqxResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Har {
+    log: Log,
+}
+#[derive(Serialize, Deserialize)]
+struct Log {
+    version: String,
+    creator: Creator,
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+#[derive(Serialize, Deserialize)]
+struct Creator {
+    name: String,
+    version: String,
+}
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: DateTime<Utc>,
+    time: f64,
+    request: Request,
+    response: Response,
+    #[serde(default)]
+    cache: serde_json::Value,
+    timings: Timings,
+    #[serde(rename = "serverIPAddress", skip_serializing_if = "Option::is_none")]
+    server_ip_address: Option<String>,
+}
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    method: String,
+    url: String,
+    http_version: String,
+    #[serde(default)]
+    cookies: Vec<Cookie>,
+    #[serde(default)]
+    headers: Vec<NameValue>,
+    #[serde(default)]
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    status: i64,
+    #[serde(default)]
+    status_text: String,
+    #[serde(default)]
+    http_version: String,
+    #[serde(default)]
+    cookies: Vec<Cookie>,
+    #[serde(default)]
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(default, rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+#[derive(Serialize, Deserialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Param {
+    name: String,
+    #[serde(default)]
+    value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+}
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Cookie {
+    name: String,
+    value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secure: Option<bool>,
+}
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    #[serde(default)]
+    mime_type: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    params: Vec<Param>,
+    #[serde(default)]
+    text: String,
+    //not in the 1.2 spec for requests, but chrome and firefox read and write it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+}
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: i64,
+    #[serde(default)]
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+}
+//milliseconds, -1 when the phase does not apply
+#[derive(Serialize, Deserialize)]
+struct Timings {
+    #[serde(default = "not_applicable")]
+    blocked: f64,
+    #[serde(default = "not_applicable")]
+    dns: f64,
+    #[serde(default = "not_applicable")]
+    connect: f64,
+    #[serde(default = "not_applicable")]
+    ssl: f64,
+    #[serde(default)]
+    send: f64,
+    #[serde(default)]
+    wait: f64,
+    #[serde(default)]
+    receive: f64,
+}
+
+fn not_applicable() -> f64 {
+    -1.0
+}
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+fn phase(ms: f64) -> Option<Duration> {
+    (ms >= 0.0).then(|| Duration::from_secs_f64(ms / 1000.0))
+}
+fn pairs(values: &[NameValue]) -> Vec<(String, String)> {
+    values
+        .iter()
+        .map(|nv| (nv.name.clone(), nv.value.clone()))
+        .collect()
+}
+fn name_values(pairs: &[(String, String)]) -> Vec<NameValue> {
+    pairs
+        .iter()
+        .map(|(name, value)| NameValue {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+//utf8 bodies are kept as text, anything else goes through base64
+fn encode_body(bytes: &[u8]) -> (String, Option<String>) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), None),
+        Err(_) => (BASE64.encode(bytes), Some(String::from("base64"))),
+    }
+}
+fn decode_body(text: &str, encoding: Option<&str>) -> Vec<u8> {
+    match encoding {
+        Some("base64") => BASE64
+            .decode(text)
+            .unwrap_or_else(|_| text.as_bytes().to_vec()),
+        _ => text.as_bytes().to_vec(),
+    }
+}
+fn request_cookies(headers: &[(String, String)]) -> Vec<Cookie> {
+    headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+        .flat_map(|(_, value)| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+fn response_cookies(headers: &[(String, String)]) -> Vec<Cookie> {
+    let mut cookies = Vec::new();
+    for (_, value) in headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+    {
+        let mut parts = value.split(';');
+        let Some((name, val)) = parts.next().and_then(|p| p.trim().split_once('=')) else {
+            continue;
+        };
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: val.to_string(),
+            ..Default::default()
+        };
+        for attr in parts {
+            let (key, val) = attr.trim().split_once('=').unwrap_or((attr.trim(), ""));
+            match key.to_ascii_lowercase().as_str() {
+                "path" => cookie.path = Some(val.to_string()),
+                "domain" => cookie.domain = Some(val.to_string()),
+                "expires" => cookie.expires = Some(val.to_string()),
+                "httponly" => cookie.http_only = Some(true),
+                "secure" => cookie.secure = Some(true),
+                _ => {}
+            }
+        }
+        cookies.push(cookie);
+    }
+    cookies
+}
+
+//secrets from ReqxAuth and Authorization headers are masked unless `reveal_secrets` is set
+pub fn export(entries: &[HarEntry], reveal_secrets: bool) -> String {
+    let entries = entries
+        .iter()
+        .flat_map(split_redirects)
+        .map(|entry| export_entry(&entry, reveal_secrets))
+        .collect();
+    let har = Har {
+        log: Log {
+            version: HAR_VERSION.into(),
+            creator: Creator {
+                name: env!("CARGO_PKG_NAME").into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            },
+            entries,
+        },
+    };
+    serde_json::to_string_pretty(&har).unwrap()
+}
+//a followed redirect chain is written the way browsers record it, one entry per hop;
+//only the headers of the last request are not known and are derived from the first
+fn split_redirects(entry: &HarEntry) -> Vec<HarEntry> {
+    let Some(response) = entry.response.as_ref().filter(|r| !r.redirects.is_empty()) else {
+        return vec![entry.clone()];
+    };
+    let mut entries = Vec::with_capacity(response.redirects.len() + 1);
+    let mut started = entry.started;
+    let mut method = entry.request.method.clone();
+    for hop in &response.redirects {
+        entries.push(HarEntry {
+            started,
+            request: ReqxRequest {
+                method: hop.method.clone(),
+                url: hop.url.clone(),
+                headers: hop.request_headers.clone(),
+                authentication: ReqxAuth::None,
+                body: match hop.method == entry.request.method {
+                    true => entry.request.body.clone(),
+                    false => ReqxBody::Empty,
+                },
+            },
+            response: Some(ReqxResponse {
+                status: hop.status,
+                version: hop.version.clone(),
+                headers: hop.headers.clone(),
+                body: Vec::new(),
+                remote_addr: hop.remote_addr,
+                timing: hop.timing.clone(),
+                redirects: Vec::new(),
+            }),
+        });
+        started += chrono::Duration::from_std(hop.timing.total).unwrap_or_default();
+        method = redirect_method(hop.status, &hop.method);
+    }
+    let same_origin = match (
+        url::Url::parse(&entry.request.url),
+        url::Url::parse(response.final_url().unwrap_or_default()),
+    ) {
+        (Ok(first), Ok(last)) => first.origin() == last.origin(),
+        _ => false,
+    };
+    let mut request = ReqxRequest {
+        url: response.final_url().unwrap_or_default().into(),
+        ..entry.request.clone()
+    };
+    if method != request.method {
+        request.method = method;
+        request.body = ReqxBody::Empty;
+    }
+    if !same_origin {
+        request.authentication = ReqxAuth::None;
+        request
+            .headers
+            .retain(|(name, _)| !ORIGIN_BOUND.iter().any(|bound| bound == name.as_str()));
+    }
+    entries.push(HarEntry {
+        started,
+        request,
+        response: Some(ReqxResponse {
+            redirects: Vec::new(),
+            ..response.clone()
+        }),
+    });
+    entries
+}
+fn export_entry(entry: &HarEntry, reveal_secrets: bool) -> Entry {
+    let req = &entry.request;
+    let auth = match reveal_secrets {
+        true => req.authentication.clone(),
+        false => req.authentication.masked(),
+    };
+    let mut headers = req.headers.clone();
+    match (&auth, auth.header_value()) {
+        (_, Some(value)) => headers.push(("Authorization".into(), value)),
+        (
+            ReqxAuth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Header,
+            },
+            None,
+        ) => headers.push((name.clone(), value.clone())),
+        _ => {}
+    }
+    let mut url = api_key_url(&req.url, &auth);
+    let mut response_headers = entry
+        .response
+        .as_ref()
+        .map(|res| res.headers.clone())
+        .unwrap_or_default();
+    if !reveal_secrets {
+        url = mask_url(&url);
+        for (name, value) in headers.iter_mut().chain(response_headers.iter_mut()) {
+            *value = mask_header(name, value);
+        }
+    }
+    let query_string = url::Url::parse(&url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| NameValue {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let mime = |default: &str| req.header("content-type").unwrap_or(default).to_string();
+    let post_data = match &req.body {
+        ReqxBody::Empty => None,
+        ReqxBody::Text(text) => Some(PostData {
+            mime_type: mime("text/plain"),
+            params: Vec::new(),
+            text: text.clone(),
+            encoding: None,
+        }),
+        ReqxBody::Bytes(bytes) => {
+            let (text, encoding) = encode_body(bytes);
+            Some(PostData {
+                mime_type: mime("application/octet-stream"),
+                params: Vec::new(),
+                text,
+                encoding,
+            })
+        }
+        ReqxBody::Form(fields) => Some(PostData {
+            mime_type: "application/x-www-form-urlencoded".into(),
+            params: fields
+                .iter()
+                .map(|(name, value)| Param {
+                    name: name.clone(),
+                    value: value.clone(),
+                    file_name: None,
+                    content_type: None,
+                })
+                .collect(),
+            text: url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(fields)
+                .finish(),
+            encoding: None,
+        }),
+        ReqxBody::GraphQl(query) => Some(PostData {
+            mime_type: mime("application/json"),
+            params: Vec::new(),
+            text: query.payload(),
+            encoding: None,
+        }),
+        //the boundary is only chosen when sending, so the parts go in params and text stays empty
+        ReqxBody::Multipart(parts) => Some(PostData {
+            mime_type: "multipart/form-data".into(),
+            params: parts
+                .iter()
+                .map(|part| {
+                    let (file_name, content_type) = part.resolved();
+                    Param {
+                        name: part.name.clone(),
+                        value: match &part.value {
+                            PartValue::Text(text) => text.clone(),
+                            PartValue::File(_) => String::new(),
+                        },
+                        file_name,
+                        content_type,
+                    }
+                })
+                .collect(),
+            text: String::new(),
+            encoding: None,
+        }),
+    };
+    let body_size = post_data.as_ref().map_or(0, |p| p.text.len() as i64);
+    let request = Request {
+        method: req.method.to_string(),
+        url,
+        http_version: entry
+            .response
+            .as_ref()
+            .map_or_else(|| "HTTP/1.1".into(), |r| r.version.clone()),
+        cookies: request_cookies(&headers),
+        headers: name_values(&headers),
+        query_string,
+        post_data,
+        headers_size: -1,
+        body_size,
+    };
+    let (response, timings, time, server_ip_address) = match &entry.response {
+        Some(res) => {
+            let (text, encoding) = encode_body(&res.body);
+            let t = &res.timing;
+            let ssl = t.tls.map_or(-1.0, millis);
+            let timings = Timings {
+                blocked: -1.0,
+                dns: t.dns.map_or(-1.0, millis),
+                //har counts the tls handshake inside connect as well
+                connect: t.connect.map_or(-1.0, |c| millis(c) + ssl.max(0.0)),
+                ssl,
+                send: 0.0,
+                wait: millis(t.ttfb),
+                receive: millis(t.download),
+            };
+            let response = Response {
+                status: res.status as i64,
+                status_text: http::StatusCode::from_u16(res.status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or_default()
+                    .into(),
+                http_version: res.version.clone(),
+                cookies: response_cookies(&response_headers),
+                headers: name_values(&response_headers),
+                content: Content {
+                    size: res.body.len() as i64,
+                    mime_type: res.header("content-type").unwrap_or_default().into(),
+                    text: Some(text),
+                    encoding,
+                },
+                redirect_url: res.header("location").unwrap_or_default().into(),
+                headers_size: -1,
+                body_size: res.body.len() as i64,
+            };
+            let ip = res.remote_addr.map(|addr| addr.ip().to_string());
+            (response, timings, millis(t.total), ip)
+        }
+        //har requires a response, a request that never got one is written as status 0
+        None => (
+            Response {
+                status: 0,
+                status_text: String::new(),
+                http_version: String::new(),
+                cookies: Vec::new(),
+                headers: Vec::new(),
+                content: Content {
+                    size: 0,
+                    mime_type: String::new(),
+                    text: None,
+                    encoding: None,
+                },
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: -1,
+            },
+            Timings {
+                blocked: -1.0,
+                dns: -1.0,
+                connect: -1.0,
+                ssl: -1.0,
+                send: 0.0,
+                wait: 0.0,
+                receive: 0.0,
+            },
+            0.0,
+            None,
+        ),
+    };
+    Entry {
+        started_date_time: entry.started,
+        time,
+        request,
+        response,
+        cache: serde_json::json!({}),
+        timings,
+        server_ip_address,
+    }
+}
+
+pub fn import(json: &str) -> Result<(Vec<HarEntry>, Vec<ImportIssue>), ImportError> {
+    let har: Har = serde_json::from_str(json)?;
+    let mut issues = Vec::new();
+    let entries = har
+        .log
+        .entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| import_entry(entry, &format!("entry {}", index + 1), &mut issues))
+        .collect();
+    Ok((entries, issues))
+}
+fn import_entry(entry: Entry, item: &str, issues: &mut Vec<ImportIssue>) -> HarEntry {
+    let req = entry.request;
+    //http2 pseudo headers and lengths are recomputed when the request is sent again,
+    //masked credentials from our own exports would only be sent as the mask
+    let headers: Vec<(String, String)> = pairs(&req.headers)
+        .into_iter()
+        .filter(|(name, value)| {
+            !name.starts_with(':')
+                && !name.eq_ignore_ascii_case("content-length")
+                && !name.eq_ignore_ascii_case("host")
+                && !value.contains(MASK)
+        })
+        .collect();
+    let body = match req.post_data {
+        None => ReqxBody::Empty,
+        //file contents are not in the har, parts keep whatever value was recorded
+        Some(post)
+            if !post.params.is_empty() && post.mime_type.starts_with("multipart/form-data") =>
+        {
+            let parts = post.params.into_iter().map(|param| FormPart {
+                name: param.name,
+                value: PartValue::Text(param.value),
+                filename: param.file_name,
+                content_type: param.content_type,
+            });
+            ReqxBody::Multipart(parts.collect())
+        }
+        Some(post)
+            if !post.params.is_empty()
+                && (post.text.is_empty()
+                    || post
+                        .mime_type
+                        .starts_with("application/x-www-form-urlencoded")) =>
+        {
+            ReqxBody::Form(
+                post.params
+                    .into_iter()
+                    .map(|param| (param.name, param.value))
+                    .collect(),
+            )
+        }
+        Some(post) => {
+            let bytes = decode_body(&post.text, post.encoding.as_deref());
+            match String::from_utf8(bytes) {
+                Ok(text) => ReqxBody::Text(text),
+                Err(e) => ReqxBody::Bytes(e.into_bytes()),
+            }
+        }
+    };
+    let method = Method::from_bytes(req.method.as_bytes()).unwrap_or_else(|_| {
+        let message = format!("invalid method `{}`, using GET", req.method);
+        issues.push(ImportIssue::new(item, message));
+        Method::GET
+    });
+    let request = ReqxRequest {
+        method,
+        url: req.url,
+        headers,
+        authentication: ReqxAuth::None,
+        body,
+    };
+    let res = entry.response;
+    let status = u16::try_from(res.status)
+        .ok()
+        .filter(|status| http::StatusCode::from_u16(*status).is_ok());
+    //status 0 is how browsers record requests that failed or were blocked
+    if status.is_none() && res.status != 0 {
+        let message = format!("invalid status `{}`, response skipped", res.status);
+        issues.push(ImportIssue::new(item, message));
+    }
+    let response = status.map(|status| {
+        let t = entry.timings;
+        let ssl = phase(t.ssl);
+        ReqxResponse {
+            status,
+            version: res.http_version,
+            headers: pairs(&res.headers),
+            body: res
+                .content
+                .text
+                .map(|text| decode_body(&text, res.content.encoding.as_deref()))
+                .unwrap_or_default(),
+            remote_addr: entry
+                .server_ip_address
+                .and_then(|ip| ip.trim_matches(['[', ']']).parse().ok())
+                .map(|ip| std::net::SocketAddr::new(ip, 0)),
+            timing: ReqxTiming {
+                dns: phase(t.dns),
+                connect: phase(t.connect).map(|c| c.saturating_sub(ssl.unwrap_or_default())),
+                tls: ssl,
+                ttfb: phase(t.send).unwrap_or_default() + phase(t.wait).unwrap_or_default(),
+                download: phase(t.receive).unwrap_or_default(),
+                total: phase(entry.time).unwrap_or_default(),
+            },
+            redirects: Vec::new(),
+        }
+    });
+    HarEntry {
+        started: entry.started_date_time,
+        request,
+        response,
+    }
+}
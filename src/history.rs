@@ -0,0 +1,268 @@
+use std::{fmt::Display, path::Path};
+
+use chrono::{DateTime, Utc};
+use http::Method;
+use rusqlite::{params, types::ToSql, Connection, OptionalExtension, Row};
+
+use crate::{
+    har::HarEntry,
+    reqx::{ReqxRequest, ReqxResponse},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started INTEGER NOT NULL,
+    method TEXT NOT NULL,
+    url TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    environment TEXT,
+    request TEXT NOT NULL,
+    response TEXT NOT NULL,
+    body BLOB NOT NULL,
+    size INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS history_started ON history (started);
+CREATE INDEX IF NOT EXISTS history_url ON history (url);
+";
+const COLUMNS: &str = "id, started, environment, request, response, body";
+
+//oldest entries are evicted first once either limit is exceeded, the newest entry is always kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryLimit {
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+#[derive(Debug)]
+pub struct History {
+    conn: Connection,
+    limit: HistoryLimit,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub started: DateTime<Utc>,
+    //name of the environment the request was rendered in, the request is stored unrendered
+    //so secrets stay in the environment; render it there again to resend it
+    pub environment: Option<String>,
+    pub request: ReqxRequest,
+    pub response: ReqxResponse,
+}
+//every set field has to match, `url` is a case sensitive substring of the url as it was
+//sent, secrets masked, and the range is inclusive
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryQuery {
+    pub url: Option<String>,
+    pub status: Option<u16>,
+    pub method: Option<Method>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+#[derive(Debug)]
+pub enum HistoryError {
+    Sqlite(rusqlite::Error),
+    Json(serde_json::Error),
+}
+impl Default for HistoryLimit {
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sqlite(e) => write!(f, "history store error: {e}"),
+            Self::Json(e) => write!(f, "could not (de)serialize history entry: {e}"),
+        }
+    }
+}
+impl std::error::Error for HistoryError {}
+impl From<rusqlite::Error> for HistoryError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+impl From<serde_json::Error> for HistoryError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+impl From<HistoryEntry> for HarEntry {
+    fn from(entry: HistoryEntry) -> Self {
+        Self {
+            started: entry.started,
+            request: entry.request,
+            response: Some(entry.response),
+        }
+    }
+}
+impl History {
+    pub fn open(path: impl AsRef<Path>, limit: HistoryLimit) -> Result<Self, HistoryError> {
+        Self::init(Connection::open(path)?, limit)
+    }
+    pub fn in_memory(limit: HistoryLimit) -> Result<Self, HistoryError> {
+        Self::init(Connection::open_in_memory()?, limit)
+    }
+    fn init(conn: Connection, limit: HistoryLimit) -> Result<Self, HistoryError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn, limit })
+    }
+    pub fn limit(&self) -> HistoryLimit {
+        self.limit
+    }
+    pub fn set_limit(&mut self, limit: HistoryLimit) -> Result<(), HistoryError> {
+        self.limit = limit;
+        self.evict()
+    }
+    //`url` is the one searches match, `request` is stored as it is
+    pub fn record(
+        &self,
+        started: DateTime<Utc>,
+        environment: Option<&str>,
+        url: &str,
+        request: &ReqxRequest,
+        response: &ReqxResponse,
+    ) -> Result<i64, HistoryError> {
+        let request_json = serde_json::to_string(request)?;
+        //the body goes in its own blob, json would store every byte as a number
+        let response_json = serde_json::to_string(&ReqxResponse {
+            status: response.status,
+            version: response.version.clone(),
+            headers: response.headers.clone(),
+            body: Vec::new(),
+            remote_addr: response.remote_addr,
+            timing: response.timing.clone(),
+            redirects: response.redirects.clone(),
+        })?;
+        let size = request_json.len() + response_json.len() + response.body.len();
+        self.conn.execute(
+            "INSERT INTO history (started, method, url, status, environment, request, response, body, size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                started.timestamp_millis(),
+                request.method.as_str(),
+                url,
+                response.status,
+                environment,
+                request_json,
+                response_json,
+                response.body,
+                size as i64,
+            ],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.evict()?;
+        Ok(id)
+    }
+    fn evict(&self) -> Result<(), HistoryError> {
+        self.conn.execute(
+            "DELETE FROM history WHERE id IN
+             (SELECT id FROM history ORDER BY id DESC LIMIT -1 OFFSET ?1)",
+            params![self.limit.max_entries.max(1) as i64],
+        )?;
+        self.conn.execute(
+            "DELETE FROM history WHERE id IN
+             (SELECT id FROM (SELECT id, SUM(size) OVER (ORDER BY id DESC) AS total FROM history)
+              WHERE total > ?1 AND id < (SELECT MAX(id) FROM history))",
+            params![self.limit.max_bytes.min(i64::MAX as u64) as i64],
+        )?;
+        Ok(())
+    }
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>, HistoryError> {
+        let row = self
+            .conn
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM history WHERE id = ?1"),
+                params![id],
+                RawEntry::from_row,
+            )
+            .optional()?;
+        row.map(RawEntry::parse).transpose()
+    }
+    //newest first
+    pub fn search(&self, query: &HistoryQuery) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let mut filters = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+        if let Some(url) = &query.url {
+            values.push(Box::new(url.clone()));
+            filters.push(format!("instr(url, ?{}) > 0", values.len()));
+        }
+        if let Some(status) = query.status {
+            values.push(Box::new(status));
+            filters.push(format!("status = ?{}", values.len()));
+        }
+        if let Some(method) = &query.method {
+            values.push(Box::new(method.as_str().to_string()));
+            filters.push(format!("method = ?{}", values.len()));
+        }
+        if let Some(since) = query.since {
+            values.push(Box::new(since.timestamp_millis()));
+            filters.push(format!("started >= ?{}", values.len()));
+        }
+        if let Some(until) = query.until {
+            values.push(Box::new(until.timestamp_millis()));
+            filters.push(format!("started <= ?{}", values.len()));
+        }
+        let mut sql = format!("SELECT {COLUMNS} FROM history");
+        if !filters.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&filters.join(" AND "));
+        }
+        sql.push_str(" ORDER BY started DESC, id DESC");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(
+            rusqlite::params_from_iter(values.iter().map(|v| v.as_ref())),
+            RawEntry::from_row,
+        )?;
+        rows.map(|row| row?.parse()).collect()
+    }
+    pub fn remove(&self, id: i64) -> Result<bool, HistoryError> {
+        Ok(self
+            .conn
+            .execute("DELETE FROM history WHERE id = ?1", params![id])?
+            > 0)
+    }
+    pub fn clear(&self) -> Result<(), HistoryError> {
+        self.conn.execute("DELETE FROM history", [])?;
+        Ok(())
+    }
+}
+//columns as read, parsed outside of rusqlite so json errors keep their own variant
+struct RawEntry {
+    id: i64,
+    started: i64,
+    environment: Option<String>,
+    request: String,
+    response: String,
+    body: Vec<u8>,
+}
+impl RawEntry {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            started: row.get(1)?,
+            environment: row.get(2)?,
+            request: row.get(3)?,
+            response: row.get(4)?,
+            body: row.get(5)?,
+        })
+    }
+    fn parse(self) -> Result<HistoryEntry, HistoryError> {
+        let mut response: ReqxResponse = serde_json::from_str(&self.response)?;
+        response.body = self.body;
+        Ok(HistoryEntry {
+            id: self.id,
+            started: DateTime::from_timestamp_millis(self.started).unwrap_or_default(),
+            environment: self.environment,
+            request: serde_json::from_str(&self.request)?,
+            response,
+        })
+    }
+}
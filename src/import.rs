@@ -0,0 +1,43 @@
+use std::fmt::Display;
+
+pub mod openapi;
+pub mod postman;
+
+//something in the source that could not be mapped onto argus, tied to the item it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportIssue {
+    pub item: String,
+    pub message: String,
+}
+#[derive(Debug)]
+pub enum ImportError {
+    Json(serde_json::Error),
+    Unsupported(String),
+}
+impl ImportIssue {
+    pub fn new(item: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            item: item.into(),
+            message: message.into(),
+        }
+    }
+}
+impl Display for ImportIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.item, self.message)
+    }
+}
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "invalid json: {e}"),
+            Self::Unsupported(what) => write!(f, "unsupported document: {what}"),
+        }
+    }
+}
+impl std::error::Error for ImportError {}
+impl From<serde_json::Error> for ImportError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
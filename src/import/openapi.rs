@@ -0,0 +1,537 @@
+use std::collections::BTreeMap;
+
+use http::Method;
+use serde_json::{json, Map, Value};
+
+use super::{ImportError, ImportIssue};
+use crate::{
+    collection::{sanitize_name, Collection, Folder},
+    environment::{Environment, Variable},
+    reqx::{ApiKeyLocation, FormPart, OAuth2Config, OAuth2Grant, ReqxAuth, ReqxBody, ReqxRequest},
+};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+//bound on chained references, guards against reference loops
+const MAX_DEPTH: usize = 8;
+const BASE_URL: &str = "baseUrl";
+
+#[derive(Debug)]
+pub struct OpenApiImport {
+    pub collection: Collection,
+    //one per server, each holding `baseUrl` and every parameter and credential variable,
+    //credentials are named after their security scheme as `scheme.name`
+    pub environments: Vec<Environment>,
+    pub issues: Vec<ImportIssue>,
+}
+
+pub fn import(source: &str) -> Result<OpenApiImport, ImportError> {
+    let doc: Value = match serde_json::from_str(source) {
+        Ok(doc) => doc,
+        Err(_) => serde_yaml::from_str(source)
+            .map_err(|e| ImportError::Unsupported(format!("neither json nor yaml: {e}")))?,
+    };
+    let version = doc
+        .get("openapi")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !version.starts_with("3.") {
+        return Err(ImportError::Unsupported(format!(
+            "expected an openapi 3.x document, version is `{version}`"
+        )));
+    }
+    let title = doc
+        .pointer("/info/title")
+        .and_then(Value::as_str)
+        .unwrap_or("OpenAPI")
+        .to_string();
+    let mut importer = Importer {
+        doc: &doc,
+        variables: BTreeMap::new(),
+        issues: Vec::new(),
+    };
+    let mut collection = Collection::new(title.clone());
+    if let Some(paths) = doc.get("paths").and_then(Value::as_object) {
+        for (path, item) in paths {
+            let item = importer.resolve(item);
+            for method in METHODS {
+                if let Some(op) = item.get(method) {
+                    importer.operation(&mut collection.root, path, method, item, op);
+                }
+            }
+        }
+    }
+    let environments = importer.environments(&title);
+    Ok(OpenApiImport {
+        collection,
+        environments,
+        issues: importer.issues,
+    })
+}
+
+struct Importer<'a> {
+    doc: &'a Value,
+    variables: BTreeMap<String, Variable>,
+    issues: Vec<ImportIssue>,
+}
+impl<'a> Importer<'a> {
+    fn issue(&mut self, item: &str, message: impl Into<String>) {
+        self.issues.push(ImportIssue::new(item, message));
+    }
+    //follows local `#/...` references, anything else is returned as is
+    fn resolve(&self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_DEPTH {
+            let Some(reference) = value.get("$ref").and_then(Value::as_str) else {
+                break;
+            };
+            let Some(target) = reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.doc.pointer(pointer))
+            else {
+                break;
+            };
+            value = target;
+        }
+        value
+    }
+    fn variable(&mut self, name: &str, value: Option<&Value>, secret: bool) {
+        let value = match value {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(other) => other.to_string(),
+        };
+        let entry = self.variables.entry(name.to_string()).or_default();
+        if entry.value.is_empty() {
+            entry.value = value;
+        }
+        entry.secret |= secret;
+    }
+    fn operation(
+        &mut self,
+        root: &mut Folder,
+        path: &str,
+        method: &str,
+        item: &'a Value,
+        op: &'a Value,
+    ) {
+        let name = op
+            .get("operationId")
+            .or_else(|| op.get("summary"))
+            .and_then(Value::as_str)
+            .map(String::from)
+            .unwrap_or_else(|| format!("{} {path}", method.to_uppercase()));
+        let folder = match op.pointer("/tags/0").and_then(Value::as_str) {
+            Some(tag) => root.folders.entry(sanitize_name(tag)).or_default(),
+            None => root,
+        };
+        let name = folder.free_name(&name);
+        let label = format!("{} {path}", method.to_uppercase());
+
+        let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+        let mut url = format!(
+            "{{{{{BASE_URL}}}}}{}",
+            path.replace('{', "{{").replace('}', "}}")
+        );
+        let mut request = ReqxRequest::new(method, String::new());
+        let mut query = Vec::new();
+
+        let params = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .chain(
+                op.get("parameters")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten(),
+            );
+        //operation parameters override path item ones with the same name and location
+        let mut merged: Vec<&Value> = Vec::new();
+        for param in params {
+            let param = self.resolve(param);
+            let key = |p: &Value| (p.get("name").cloned(), p.get("in").cloned());
+            merged.retain(|p| key(p) != key(param));
+            merged.push(param);
+        }
+        for param in merged {
+            let Some(pname) = param.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let example = param.get("example").or_else(|| {
+                param
+                    .get("schema")
+                    .map(|s| self.resolve(s))
+                    .and_then(|s| s.get("example").or_else(|| s.get("default")))
+            });
+            self.variable(pname, example, false);
+            match param.get("in").and_then(Value::as_str) {
+                Some("path") => {}
+                Some("query") => query.push(format!("{pname}={{{{{pname}}}}}")),
+                Some("header") => request
+                    .headers
+                    .push((pname.to_string(), format!("{{{{{pname}}}}}"))),
+                Some("cookie") => request
+                    .headers
+                    .push(("Cookie".into(), format!("{pname}={{{{{pname}}}}}"))),
+                other => self.issue(
+                    &label,
+                    format!("parameter location {other:?} is not supported"),
+                ),
+            }
+        }
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query.join("&"));
+        }
+        request.url = url;
+
+        if let Some(body) = op.get("requestBody") {
+            self.body(&label, self.resolve(body), &mut request);
+        }
+        let security = op.get("security").or_else(|| self.doc.get("security"));
+        if let Some(requirements) = security.and_then(Value::as_array) {
+            if let Some(requirement) = requirements.first().and_then(Value::as_object) {
+                if requirements.len() > 1 {
+                    self.issue(
+                        &label,
+                        "several security alternatives, only the first is used",
+                    );
+                }
+                for (scheme, scopes) in requirement {
+                    self.security(&label, scheme, scopes, &mut request);
+                }
+            }
+        }
+        //cookie parameters and cookie api keys all go in one header
+        let cookies: Vec<String> = request
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+            .map(|(_, value)| value.clone())
+            .collect();
+        if !cookies.is_empty() {
+            request
+                .headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("cookie"));
+            request.headers.push(("Cookie".into(), cookies.join("; ")));
+        }
+        folder.requests.insert(name, request);
+    }
+    fn body(&mut self, label: &str, body: &'a Value, request: &mut ReqxRequest) {
+        let Some(content) = body.get("content").and_then(Value::as_object) else {
+            return;
+        };
+        let preferred = ["application/json", "application/x-www-form-urlencoded"];
+        let Some((content_type, media)) = preferred
+            .iter()
+            .find_map(|ct| content.get_key_value(*ct))
+            .or_else(|| content.iter().next())
+        else {
+            return;
+        };
+        let example = media
+            .get("example")
+            .cloned()
+            .or_else(|| {
+                media
+                    .get("examples")
+                    .and_then(Value::as_object)
+                    .and_then(|examples| examples.values().next())
+                    .map(|example| self.resolve(example))
+                    .and_then(|example| example.get("value").cloned())
+            })
+            .or_else(|| {
+                media
+                    .get("schema")
+                    .map(|schema| self.sample(schema, &mut Vec::new()))
+            })
+            .unwrap_or(Value::Null);
+        let is_json = content_type.contains("json");
+        let is_form = content_type == "application/x-www-form-urlencoded"
+            || content_type == "multipart/form-data";
+        if is_form {
+            let fields: Vec<(String, String)> = example
+                .as_object()
+                .map(|fields| {
+                    fields
+                        .iter()
+                        .map(|(k, v)| {
+                            (
+                                k.clone(),
+                                v.as_str()
+                                    .map(String::from)
+                                    .unwrap_or_else(|| v.to_string()),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            if content_type == "application/x-www-form-urlencoded" {
+                request.body = ReqxBody::Form(fields);
+                return;
+            }
+            //binary properties become file parts, the file itself has to be picked after import
+            let properties = media
+                .get("schema")
+                .map(|schema| self.resolve(schema))
+                .and_then(|schema| schema.get("properties"))
+                .and_then(Value::as_object);
+            let mut parts: Vec<FormPart> = fields
+                .into_iter()
+                .map(|(name, value)| FormPart::text(name, value))
+                .collect();
+            for (name, schema) in properties.into_iter().flatten() {
+                let schema = self.resolve(schema);
+                if schema.get("format").and_then(Value::as_str) != Some("binary") {
+                    continue;
+                }
+                self.issue(label, format!("file part `{name}` has no file selected"));
+                let part = FormPart::file(name, "");
+                match parts.iter_mut().find(|p| p.name == *name) {
+                    Some(existing) => *existing = part,
+                    None => parts.push(part),
+                }
+            }
+            request.body = ReqxBody::Multipart(parts);
+            return;
+        }
+        request.body = match example {
+            Value::String(text) if !is_json => ReqxBody::Text(text),
+            Value::Null if !is_json => {
+                self.issue(label, format!("no example for `{content_type}` body"));
+                ReqxBody::Empty
+            }
+            value => ReqxBody::Text(serde_json::to_string_pretty(&value).unwrap_or_default()),
+        };
+        request
+            .headers
+            .push(("Content-Type".into(), content_type.clone()));
+    }
+    fn security(&mut self, label: &str, name: &str, scopes: &Value, request: &mut ReqxRequest) {
+        let pointer = format!("/components/securitySchemes/{name}");
+        let Some(scheme) = self.doc.pointer(&pointer).map(|s| self.resolve(s)) else {
+            self.issue(label, format!("security scheme `{name}` is not defined"));
+            return;
+        };
+        let kind = scheme
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let http_scheme = scheme
+            .get("scheme")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_lowercase();
+        //credentials are scoped to their scheme so they cannot meet a parameter of the same name
+        let mut credential = |var: &str, secret: bool| {
+            let var = format!("{name}.{var}");
+            self.variable(&var, None, secret);
+            format!("{{{{{var}}}}}")
+        };
+        match (kind, http_scheme.as_str()) {
+            ("http", "basic") => {
+                request.authentication = ReqxAuth::Basic {
+                    username: credential("username", false),
+                    password: Some(credential("password", true)),
+                };
+            }
+            ("http", "digest") => {
+                request.authentication = ReqxAuth::Digest {
+                    username: credential("username", false),
+                    password: credential("password", true),
+                };
+            }
+            ("http", "bearer") => {
+                request.authentication = ReqxAuth::Bearer(credential("token", true));
+            }
+            ("apiKey", _) => {
+                let key = scheme.get("name").and_then(Value::as_str).unwrap_or(name);
+                let value = credential("key", true);
+                let location = match scheme.get("in").and_then(Value::as_str) {
+                    Some("query") => ApiKeyLocation::Query,
+                    Some("cookie") => {
+                        request
+                            .headers
+                            .push(("Cookie".into(), format!("{key}={value}")));
+                        return;
+                    }
+                    _ => ApiKeyLocation::Header,
+                };
+                request.authentication = ReqxAuth::ApiKey {
+                    name: key.to_string(),
+                    value,
+                    location,
+                };
+            }
+            ("oauth2", _) => {
+                let flows = scheme.get("flows");
+                let flow = |key: &str| flows.and_then(|f| f.get(key));
+                let url = |flow: &Value, key: &str| {
+                    flow.get(key)
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let (grant, flow) = if let Some(flow) = flow("authorizationCode") {
+                    let grant = OAuth2Grant::AuthorizationCode {
+                        auth_url: url(flow, "authorizationUrl"),
+                        redirect_port: 0,
+                    };
+                    (grant, flow)
+                } else if let Some(flow) = flow("clientCredentials") {
+                    (OAuth2Grant::ClientCredentials, flow)
+                } else if let Some(flow) = flow("password") {
+                    let grant = OAuth2Grant::Password {
+                        username: credential("username", false),
+                        password: credential("password", true),
+                    };
+                    (grant, flow)
+                } else {
+                    self.issue(
+                        label,
+                        format!("oauth2 scheme `{name}` has no supported flow"),
+                    );
+                    return;
+                };
+                let client_id = credential("clientId", false);
+                let client_secret = credential("clientSecret", true);
+                let scopes: Vec<&str> = scopes
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect();
+                request.authentication = ReqxAuth::OAuth2(OAuth2Config {
+                    grant,
+                    token_url: url(flow, "tokenUrl"),
+                    client_id,
+                    client_secret: Some(client_secret),
+                    scope: (!scopes.is_empty()).then(|| scopes.join(" ")),
+                });
+            }
+            _ => self.issue(
+                label,
+                format!("security scheme `{name}` of type `{kind}` is not supported"),
+            ),
+        }
+    }
+    //builds an example value from a schema, preferring the examples and defaults it declares
+    //`seen` holds the references being expanded, a recursive schema stops at its first repeat
+    fn sample(&self, schema: &'a Value, seen: &mut Vec<&'a str>) -> Value {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            if seen.contains(&reference) || seen.len() > MAX_DEPTH {
+                return Value::Null;
+            }
+            seen.push(reference);
+            let value = self.sample(self.resolve(schema), seen);
+            seen.pop();
+            return value;
+        }
+        if let Some(example) = schema
+            .get("example")
+            .or_else(|| schema.pointer("/examples/0"))
+            .or_else(|| schema.get("default"))
+            .or_else(|| schema.pointer("/enum/0"))
+            .or_else(|| schema.get("const"))
+        {
+            return example.clone();
+        }
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            let mut merged = Map::new();
+            for part in all {
+                if let Value::Object(fields) = self.sample(part, seen) {
+                    merged.extend(fields);
+                }
+            }
+            return Value::Object(merged);
+        }
+        if let Some(first) = schema
+            .pointer("/oneOf/0")
+            .or_else(|| schema.pointer("/anyOf/0"))
+        {
+            return self.sample(first, seen);
+        }
+        //3.1 allows `type: [string, "null"]`
+        let kind = match schema.get("type") {
+            Some(Value::String(kind)) => kind.as_str(),
+            Some(Value::Array(kinds)) => kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|k| *k != "null")
+                .unwrap_or("null"),
+            _ if schema.get("properties").is_some() => "object",
+            _ => "",
+        };
+        match kind {
+            "object" => {
+                let mut out = Map::new();
+                if let Some(props) = schema.get("properties").and_then(Value::as_object) {
+                    for (name, prop) in props {
+                        out.insert(name.clone(), self.sample(prop, seen));
+                    }
+                }
+                Value::Object(out)
+            }
+            "array" => match schema.get("items") {
+                Some(items) => json!([self.sample(items, seen)]),
+                None => json!([]),
+            },
+            "string" => match schema.get("format").and_then(Value::as_str) {
+                Some("date-time") => json!("2024-01-01T00:00:00Z"),
+                Some("date") => json!("2024-01-01"),
+                Some("email") => json!("user@example.com"),
+                Some("uuid") => json!("00000000-0000-0000-0000-000000000000"),
+                Some("uri") | Some("url") => json!("https://example.com"),
+                _ => json!("string"),
+            },
+            "integer" | "number" => json!(0),
+            "boolean" => json!(false),
+            _ => Value::Null,
+        }
+    }
+    fn environments(&mut self, title: &str) -> Vec<Environment> {
+        let servers: Vec<&Value> = self
+            .doc
+            .get("servers")
+            .and_then(Value::as_array)
+            .map(|servers| servers.iter().collect())
+            .unwrap_or_default();
+        let mut envs = Vec::new();
+        //paths start with `/`, without a server the base url has to be filled in by hand
+        if servers.is_empty() {
+            self.issue(title, format!("no servers, `{BASE_URL}` is left empty"));
+            let mut env = Environment::new(title);
+            env.variables = self.variables.clone();
+            env.set(BASE_URL, "");
+            return vec![env];
+        }
+        for server in servers {
+            let mut url = server
+                .get("url")
+                .and_then(Value::as_str)
+                .unwrap_or("/")
+                .to_string();
+            if let Some(vars) = server.get("variables").and_then(Value::as_object) {
+                for (name, var) in vars {
+                    let default = var
+                        .get("default")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    url = url.replace(&format!("{{{name}}}"), default);
+                }
+            }
+            let name = server
+                .get("description")
+                .and_then(Value::as_str)
+                .map(String::from)
+                .unwrap_or_else(|| url.clone());
+            let mut env = Environment::new(name);
+            env.variables = self.variables.clone();
+            env.set(BASE_URL, url.trim_end_matches('/'));
+            envs.push(env);
+        }
+        envs
+    }
+}
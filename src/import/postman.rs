@@ -0,0 +1,595 @@
+use http::Method;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ImportError, ImportIssue};
+use crate::{
+    collection::{Collection, Folder},
+    environment::Environment,
+    reqx::{
+        ApiKeyLocation, AwsSigV4, FormPart, GraphQlQuery, OAuth2Config, OAuth2Grant, ReqxAuth,
+        ReqxBody, ReqxRequest,
+    },
+};
+
+#[derive(Debug)]
+pub struct PostmanImport {
+    pub collection: Collection,
+    //collection level variables, postman uses the same {{name}} syntax as argus
+    pub variables: Environment,
+    pub issues: Vec<ImportIssue>,
+}
+
+#[derive(Deserialize)]
+struct PmCollection {
+    info: PmInfo,
+    #[serde(default)]
+    item: Vec<PmItem>,
+    #[serde(default)]
+    variable: Vec<PmVariable>,
+    auth: Option<PmAuth>,
+    #[serde(default)]
+    event: Vec<PmEvent>,
+}
+#[derive(Deserialize)]
+struct PmInfo {
+    name: String,
+    #[serde(default)]
+    schema: String,
+}
+#[derive(Deserialize)]
+struct PmItem {
+    #[serde(default)]
+    name: String,
+    item: Option<Vec<PmItem>>,
+    //parsed per item so one malformed request does not abort the import
+    request: Option<Value>,
+    auth: Option<PmAuth>,
+    #[serde(default)]
+    event: Vec<PmEvent>,
+}
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PmRequestKind {
+    Url(String),
+    Full(Box<PmRequest>),
+}
+#[derive(Deserialize)]
+struct PmRequest {
+    method: Option<String>,
+    #[serde(default)]
+    header: Vec<PmField>,
+    url: Option<PmUrl>,
+    body: Option<PmBody>,
+    auth: Option<PmAuth>,
+}
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PmUrl {
+    Raw(String),
+    Parts {
+        raw: Option<String>,
+        protocol: Option<String>,
+        host: Option<Value>,
+        port: Option<String>,
+        path: Option<Value>,
+        #[serde(default)]
+        query: Vec<PmField>,
+        //values of `:name` path segments
+        #[serde(default)]
+        variable: Vec<PmField>,
+    },
+}
+#[derive(Deserialize)]
+struct PmField {
+    #[serde(default)]
+    key: String,
+    value: Option<Value>,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    //form-data only: file path(s) of file parts and the part content type
+    src: Option<Value>,
+    #[serde(rename = "contentType")]
+    content_type: Option<String>,
+}
+#[derive(Deserialize)]
+struct PmBody {
+    mode: Option<String>,
+    raw: Option<String>,
+    #[serde(default)]
+    urlencoded: Vec<PmField>,
+    #[serde(default)]
+    formdata: Vec<PmField>,
+    graphql: Option<Value>,
+    options: Option<Value>,
+    #[serde(default)]
+    disabled: bool,
+}
+#[derive(Deserialize)]
+struct PmAuth {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    basic: Vec<PmField>,
+    #[serde(default)]
+    bearer: Vec<PmField>,
+    #[serde(default)]
+    apikey: Vec<PmField>,
+    #[serde(default)]
+    oauth2: Vec<PmField>,
+    #[serde(default)]
+    digest: Vec<PmField>,
+    #[serde(default)]
+    awsv4: Vec<PmField>,
+}
+#[derive(Deserialize)]
+struct PmEvent {
+    #[serde(default)]
+    listen: String,
+    script: Option<PmScript>,
+}
+#[derive(Deserialize)]
+struct PmScript {
+    exec: Option<Value>,
+}
+#[derive(Deserialize)]
+struct PmVariable {
+    #[serde(default)]
+    key: String,
+    value: Option<Value>,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+#[derive(Deserialize)]
+struct PmEnvironment {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    values: Vec<PmEnvValue>,
+}
+#[derive(Deserialize)]
+struct PmEnvValue {
+    #[serde(default)]
+    key: String,
+    value: Option<Value>,
+    #[serde(default = "enabled")]
+    enabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+fn enabled() -> bool {
+    true
+}
+//postman stores most values as strings but numbers and booleans show up in exports too
+fn value_string(value: &Option<Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+fn field<'a>(fields: &'a [PmField], key: &str) -> Option<&'a PmField> {
+    fields.iter().find(|f| f.key == key)
+}
+
+pub fn import_collection(json: &str) -> Result<PostmanImport, ImportError> {
+    let pm: PmCollection = serde_json::from_str(json)?;
+    if !pm.info.schema.contains("collection/v2") {
+        return Err(ImportError::Unsupported(format!(
+            "expected a postman v2.x collection, schema is `{}`",
+            pm.info.schema
+        )));
+    }
+    let mut importer = Importer::default();
+    let mut collection = Collection::new(pm.info.name.clone());
+    importer.events(&pm.info.name, &pm.event);
+    importer.items(
+        &mut collection.root,
+        &pm.info.name,
+        &pm.item,
+        pm.auth.as_ref(),
+    );
+
+    let mut variables = Environment::new(format!("{} variables", pm.info.name));
+    for var in pm
+        .variable
+        .iter()
+        .filter(|v| !v.disabled && !v.key.is_empty())
+    {
+        let value = value_string(&var.value);
+        if var.kind.as_deref() == Some("secret") {
+            variables.set_secret(var.key.clone(), value);
+        } else {
+            variables.set(var.key.clone(), value);
+        }
+    }
+    //path variables become collection variables unless one of that name is already there
+    for (item, name, value) in std::mem::take(&mut importer.path_variables) {
+        match variables.get(&name) {
+            None => variables.set(name, value),
+            Some(existing) if existing != value => importer.issue(
+                &item,
+                format!(
+                    "path variable `:{name}` is `{value}` here, `{{{{{name}}}}}` is `{existing}`"
+                ),
+            ),
+            Some(_) => {}
+        }
+    }
+    Ok(PostmanImport {
+        collection,
+        variables,
+        issues: importer.issues,
+    })
+}
+pub fn import_environment(json: &str) -> Result<(Environment, Vec<ImportIssue>), ImportError> {
+    let pm: PmEnvironment = serde_json::from_str(json)?;
+    let mut env = Environment::new(pm.name.clone());
+    let mut issues = Vec::new();
+    for var in pm.values {
+        if var.key.is_empty() {
+            continue;
+        }
+        if !var.enabled {
+            issues.push(ImportIssue::new(
+                &pm.name,
+                format!("variable `{}` is disabled, skipped", var.key),
+            ));
+            continue;
+        }
+        let value = value_string(&var.value);
+        if var.kind.as_deref() == Some("secret") {
+            env.set_secret(var.key, value);
+        } else {
+            env.set(var.key, value);
+        }
+    }
+    Ok((env, issues))
+}
+
+#[derive(Default)]
+struct Importer {
+    issues: Vec<ImportIssue>,
+    //item, name and value of every `:name` path segment, which becomes `{{name}}`
+    path_variables: Vec<(String, String, String)>,
+}
+impl Importer {
+    fn issue(&mut self, item: &str, message: impl Into<String>) {
+        self.issues.push(ImportIssue::new(item, message));
+    }
+    fn events(&mut self, item: &str, events: &[PmEvent]) {
+        for event in events {
+            let has_code = match event.script.as_ref().and_then(|s| s.exec.as_ref()) {
+                Some(Value::Array(lines)) => lines
+                    .iter()
+                    .any(|l| l.as_str().is_some_and(|l| !l.trim().is_empty())),
+                Some(Value::String(code)) => !code.trim().is_empty(),
+                _ => false,
+            };
+            if has_code {
+                let kind = match event.listen.as_str() {
+                    "prerequest" => "pre-request script",
+                    "test" => "test script",
+                    _ => "script",
+                };
+                self.issue(item, format!("{kind} is not supported, skipped"));
+            }
+        }
+    }
+    fn items(&mut self, folder: &mut Folder, path: &str, items: &[PmItem], auth: Option<&PmAuth>) {
+        for item in items {
+            let name = folder.free_name(&item.name);
+            let item_path = format!("{path}/{name}");
+            self.events(&item_path, &item.event);
+            if let Some(children) = &item.item {
+                let mut sub = Folder::default();
+                let auth = item.auth.as_ref().or(auth);
+                self.items(&mut sub, &item_path, children, auth);
+                folder.folders.insert(name, sub);
+            } else if let Some(request) = &item.request {
+                match PmRequestKind::deserialize(request) {
+                    Ok(request) => {
+                        let request = self.request(&item_path, &request, auth);
+                        folder.requests.insert(name, request);
+                    }
+                    Err(e) => self.issue(&item_path, format!("malformed request, skipped: {e}")),
+                }
+            } else {
+                self.issue(
+                    &item_path,
+                    "item has neither a request nor children, skipped",
+                );
+            }
+        }
+    }
+    fn request(
+        &mut self,
+        path: &str,
+        kind: &PmRequestKind,
+        inherited: Option<&PmAuth>,
+    ) -> ReqxRequest {
+        let pm = match kind {
+            PmRequestKind::Url(url) => return ReqxRequest::new(Method::GET, url.clone()),
+            PmRequestKind::Full(pm) => pm,
+        };
+        let method = pm.method.as_deref().unwrap_or("GET").to_uppercase();
+        let method = Method::from_bytes(method.as_bytes()).unwrap_or_else(|_| {
+            self.issue(path, format!("invalid method `{method}`, using GET"));
+            Method::GET
+        });
+        let mut request = ReqxRequest::new(method, url(pm.url.as_ref()));
+        if let Some(PmUrl::Parts { variable, .. }) = &pm.url {
+            for var in variable.iter().filter(|v| !v.disabled && !v.key.is_empty()) {
+                request.url = path_variable(&request.url, &var.key);
+                let value = value_string(&var.value);
+                if value.is_empty() {
+                    self.issue(
+                        path,
+                        format!(
+                            "path variable `:{}` has no value, set it in an environment",
+                            var.key
+                        ),
+                    );
+                } else {
+                    self.path_variables
+                        .push((path.to_string(), var.key.clone(), value));
+                }
+            }
+        }
+        for header in pm.header.iter().filter(|h| !h.disabled) {
+            request
+                .headers
+                .push((header.key.clone(), value_string(&header.value)));
+        }
+        if let Some(auth) = pm.auth.as_ref().or(inherited) {
+            self.auth(path, auth, &mut request);
+        }
+        if let Some(body) = pm.body.as_ref().filter(|b| !b.disabled) {
+            self.body(path, body, &mut request);
+        }
+        request
+    }
+    fn auth(&mut self, path: &str, auth: &PmAuth, request: &mut ReqxRequest) {
+        let get =
+            |fields: &[PmField], key: &str| field(fields, key).map(|f| value_string(&f.value));
+        match auth.kind.as_str() {
+            "noauth" => {}
+            "basic" => {
+                request.authentication = ReqxAuth::Basic {
+                    username: get(&auth.basic, "username").unwrap_or_default(),
+                    password: get(&auth.basic, "password"),
+                }
+            }
+            "bearer" => {
+                request.authentication =
+                    ReqxAuth::Bearer(get(&auth.bearer, "token").unwrap_or_default())
+            }
+            "apikey" => {
+                request.authentication = ReqxAuth::ApiKey {
+                    name: get(&auth.apikey, "key").unwrap_or_default(),
+                    value: get(&auth.apikey, "value").unwrap_or_default(),
+                    location: match get(&auth.apikey, "in").as_deref() {
+                        Some("query") => ApiKeyLocation::Query,
+                        _ => ApiKeyLocation::Header,
+                    },
+                }
+            }
+            "digest" => {
+                request.authentication = ReqxAuth::Digest {
+                    password: get(&auth.digest, "password").unwrap_or_default(),
+                    username: get(&auth.digest, "username").unwrap_or_default(),
+                }
+            }
+            "awsv4" => {
+                let get = |key: &str| get(&auth.awsv4, key).filter(|v| !v.is_empty());
+                request.authentication = ReqxAuth::AwsSigV4(AwsSigV4 {
+                    access_key: get("accessKey").unwrap_or_default(),
+                    secret_key: get("secretKey").unwrap_or_default(),
+                    session_token: get("sessionToken"),
+                    region: get("region").unwrap_or_else(|| "us-east-1".into()),
+                    service: get("service").unwrap_or_default(),
+                })
+            }
+            "oauth2" => {
+                let get = |key: &str| get(&auth.oauth2, key).filter(|v| !v.is_empty());
+                let grant = match get("grant_type").as_deref() {
+                    Some("client_credentials") => OAuth2Grant::ClientCredentials,
+                    Some("password_credentials") => OAuth2Grant::Password {
+                        username: get("username").unwrap_or_default(),
+                        password: get("password").unwrap_or_default(),
+                    },
+                    Some("authorization_code" | "authorization_code_with_pkce") | None => {
+                        //only loopback redirects can be caught, others get any free port
+                        let redirect_port = get("redirect_uri")
+                            .and_then(|uri| url::Url::parse(&uri).ok())
+                            .filter(|uri| matches!(uri.host_str(), Some("127.0.0.1" | "localhost")))
+                            .and_then(|uri| uri.port())
+                            .unwrap_or(0);
+                        OAuth2Grant::AuthorizationCode {
+                            auth_url: get("authUrl").unwrap_or_default(),
+                            redirect_port,
+                        }
+                    }
+                    Some(other) => {
+                        self.issue(
+                            path,
+                            format!("oauth2 grant `{other}` is not supported, skipped"),
+                        );
+                        return;
+                    }
+                };
+                request.authentication = ReqxAuth::OAuth2(OAuth2Config {
+                    grant,
+                    token_url: get("accessTokenUrl").unwrap_or_default(),
+                    client_id: get("clientId").unwrap_or_default(),
+                    client_secret: get("clientSecret"),
+                    scope: get("scope"),
+                });
+            }
+            other => self.issue(
+                path,
+                format!("auth type `{other}` is not supported, skipped"),
+            ),
+        }
+    }
+    fn body(&mut self, path: &str, body: &PmBody, request: &mut ReqxRequest) {
+        match body.mode.as_deref() {
+            Some("raw") => {
+                let raw = body.raw.clone().unwrap_or_default();
+                let language = body
+                    .options
+                    .as_ref()
+                    .and_then(|o| o.pointer("/raw/language"))
+                    .and_then(Value::as_str);
+                let content_type = match language {
+                    Some("json") => Some("application/json"),
+                    Some("xml") => Some("application/xml"),
+                    Some("html") => Some("text/html"),
+                    Some("javascript") => Some("application/javascript"),
+                    Some("text") => Some("text/plain"),
+                    _ => None,
+                };
+                if let Some(content_type) = content_type {
+                    if request.header("content-type").is_none() {
+                        request
+                            .headers
+                            .push(("Content-Type".into(), content_type.into()));
+                    }
+                }
+                request.body = ReqxBody::Text(raw);
+            }
+            Some("urlencoded") => {
+                request.body = ReqxBody::Form(
+                    body.urlencoded
+                        .iter()
+                        .filter(|f| !f.disabled)
+                        .map(|f| (f.key.clone(), value_string(&f.value)))
+                        .collect(),
+                );
+            }
+            Some("formdata") => {
+                let mut parts = Vec::new();
+                for f in body.formdata.iter().filter(|f| !f.disabled) {
+                    if f.kind.as_deref() != Some("file") {
+                        let mut part = FormPart::text(&f.key, value_string(&f.value));
+                        part.content_type = f.content_type.clone();
+                        parts.push(part);
+                        continue;
+                    }
+                    //postman allows several files per key, each becomes its own part
+                    let files: Vec<&str> = match &f.src {
+                        Some(Value::String(src)) => vec![src.as_str()],
+                        Some(Value::Array(srcs)) => srcs.iter().filter_map(Value::as_str).collect(),
+                        _ => Vec::new(),
+                    };
+                    if files.is_empty() {
+                        self.issue(path, format!("file part `{}` has no file, skipped", f.key));
+                    }
+                    for file in files {
+                        let mut part = FormPart::file(&f.key, file);
+                        part.content_type = f.content_type.clone();
+                        parts.push(part);
+                    }
+                }
+                request.body = ReqxBody::Multipart(parts);
+            }
+            Some("graphql") => {
+                let graphql = body.graphql.clone().unwrap_or_default();
+                let query = graphql.get("query").cloned().unwrap_or_default();
+                //postman keeps variables as a json string typed by the user
+                let variables = match graphql.get("variables") {
+                    Some(Value::String(vars)) if !vars.trim().is_empty() => {
+                        serde_json::from_str(vars).unwrap_or_else(|_| {
+                            self.issue(path, "graphql variables are not valid json, dropped");
+                            Value::Null
+                        })
+                    }
+                    Some(Value::Object(vars)) => Value::Object(vars.clone()),
+                    _ => Value::Null,
+                };
+                request.body = ReqxBody::GraphQl(GraphQlQuery {
+                    query: query.as_str().unwrap_or_default().to_string(),
+                    variables: Some(variables).filter(|v| !v.is_null()),
+                    operation_name: None,
+                });
+            }
+            Some(mode) => self.issue(
+                path,
+                format!("body mode `{mode}` is not supported, skipped"),
+            ),
+            None => {}
+        }
+    }
+}
+//`:name` segments of the path become `{{name}}`, the query is left alone
+fn path_variable(url: &str, name: &str) -> String {
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    let (path, rest) = url.split_at(end);
+    let segment = format!(":{name}");
+    let path: Vec<_> = path
+        .split('/')
+        .map(|part| match part == segment {
+            true => format!("{{{{{name}}}}}"),
+            false => part.to_string(),
+        })
+        .collect();
+    format!("{}{rest}", path.join("/"))
+}
+fn url(url: Option<&PmUrl>) -> String {
+    match url {
+        None => String::new(),
+        Some(PmUrl::Raw(raw)) => raw.clone(),
+        Some(PmUrl::Parts { raw: Some(raw), .. }) => raw.clone(),
+        Some(PmUrl::Parts {
+            raw: None,
+            protocol,
+            host,
+            port,
+            path,
+            query,
+            ..
+        }) => {
+            let join = |v: &Option<Value>, sep: &str| match v {
+                Some(Value::Array(parts)) => parts
+                    .iter()
+                    .map(|p| {
+                        p.as_str()
+                            .map(String::from)
+                            .unwrap_or_else(|| p.to_string())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(sep),
+                Some(Value::String(s)) => s.clone(),
+                _ => String::new(),
+            };
+            let mut url = String::new();
+            if let Some(protocol) = protocol {
+                url.push_str(&format!("{protocol}://"));
+            }
+            url.push_str(&join(host, "."));
+            if let Some(port) = port {
+                url.push_str(&format!(":{port}"));
+            }
+            let path = join(path, "/");
+            if !path.is_empty() {
+                url.push('/');
+                url.push_str(path.trim_start_matches('/'));
+            }
+            let query: Vec<_> = query
+                .iter()
+                .filter(|q| !q.disabled)
+                .map(|q| format!("{}={}", q.key, value_string(&q.value)))
+                .collect();
+            if !query.is_empty() {
+                url.push('?');
+                url.push_str(&query.join("&"));
+            }
+            url
+        }
+    }
+}
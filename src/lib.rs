@@ -0,0 +1,9 @@
+pub mod collection;
+pub mod cookies;
+pub mod environment;
+pub mod executor;
+pub mod har;
+pub mod history;
+pub mod import;
+pub mod reqx;
+pub mod viewer;
@@ -1,25 +1,145 @@
-use yrl::{WgpuState, YWindow};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
-mod reqx;
+use argus::{
+    cookies::CookieStore,
+    executor::{Executor, Pending, ReqxEvent},
+    reqx::{Reqx, ReqxError, ReqxRequest, ReqxResponse},
+    viewer::{self, BodyView},
+};
+use yrl::{
+    winit::{event::WindowEvent, event_loop::EventLoopProxy},
+    ImagePreview, WgpuState, YWindow,
+};
 
 struct Yhandling {
     state: WgpuState<'static>,
+    //set when the selected response body is an image, see argus::viewer::BodyView::Image
+    preview: Option<ImagePreview>,
+    executor: Executor<EventLoopProxy<ReqxEvent>>,
+    //the request in flight, results of older ones are dropped
+    pending: Pending,
+    response: Option<ReqxResponse>,
+    //why the last request failed, cleared by the next response
+    error: Option<ReqxError>,
+    cookies: Arc<Mutex<CookieStore>>,
 }
 impl yrl::YHandler for Yhandling {
-    fn create(window: &yrl::winit::window::Window) -> Self {
-        Self {
+    type Event = ReqxEvent;
+    fn create(window: &yrl::winit::window::Window, proxy: EventLoopProxy<ReqxEvent>) -> Self {
+        let store = match cookie_path().map(CookieStore::load) {
+            Some(Ok(store)) => store,
+            Some(Err(e)) => {
+                eprintln!("argus: cookies not loaded, {e}");
+                CookieStore::default()
+            }
+            None => CookieStore::default(),
+        };
+        let cookies = Arc::new(Mutex::new(store));
+        let reqx = Reqx::default().with_cookies(cookies.clone());
+        let mut handling = Self {
             state: WgpuState::new(window),
+            preview: None,
+            executor: Executor::new(Arc::new(reqx), proxy),
+            pending: Pending::default(),
+            response: None,
+            error: None,
+            cookies,
+        };
+        //`argus <url>` fetches the url on start
+        if let Some(url) = std::env::args().nth(1) {
+            handling.send(ReqxRequest::new(http::Method::GET, url));
         }
+        handling
+    }
+    fn user_event(
+        &mut self,
+        _ev_loop: &yrl::winit::event_loop::ActiveEventLoop,
+        event: ReqxEvent,
+    ) -> yrl::YMessage {
+        //a failed request can still have set cookies on a redirect
+        self.save_cookies();
+        let response = match self.pending.finish(event) {
+            Some(Ok(response)) => response,
+            Some(Err(error)) => {
+                eprintln!("argus: {error}");
+                self.error = Some(error);
+                return yrl::YMessage::RequestWindow;
+            }
+            None => return yrl::YMessage::None,
+        };
+        self.error = None;
+        self.preview = match viewer::view(&response) {
+            BodyView::Image => ImagePreview::new(&self.state, response.body.clone()).ok(),
+            _ => None,
+        };
+        self.response = Some(response);
+        yrl::YMessage::RequestWindow
+    }
+    fn window_req(&mut self, window: &yrl::winit::window::Window) {
+        if let Some(error) = &self.error {
+            window.set_title(&format!("Argus - {error}"));
+        } else if let Some(response) = &self.response {
+            window.set_title(&format!(
+                "Argus - {} ({} bytes, {:?})",
+                response.status,
+                response.body.len(),
+                response.timing.total
+            ));
+        }
+        window.request_redraw();
     }
     fn handle_event(
         &mut self,
-        ev_loop: &yrl::winit::event_loop::ActiveEventLoop,
-        window_id: yrl::winit::window::WindowId,
-        event: yrl::winit::event::WindowEvent,
+        _ev_loop: &yrl::winit::event_loop::ActiveEventLoop,
+        _window_id: yrl::winit::window::WindowId,
+        event: WindowEvent,
     ) -> yrl::YMessage {
+        match event {
+            WindowEvent::Resized(size) => {
+                self.state.resize(size.width, size.height);
+                if let Some(preview) = &self.preview {
+                    preview.fit(&self.state);
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                if let Some(preview) = &self.preview {
+                    let _ = preview.render(&self.state);
+                }
+            }
+            _ => {}
+        }
         yrl::YMessage::None
     }
 }
+impl Yhandling {
+    //a new request replaces the one in flight
+    fn send(&mut self, request: ReqxRequest) {
+        self.pending.replace(self.executor.send(request));
+    }
+    fn save_cookies(&self) {
+        let Some(path) = cookie_path() else {
+            return;
+        };
+        let saved = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(Into::into)
+            .and_then(|_| {
+                let store = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+                store.save(&path)
+            });
+        if let Err(e) = saved {
+            eprintln!("argus: cookies not saved, {e}");
+        }
+    }
+}
+//the jars are kept across restarts in ~/.argus/cookies.toml
+fn cookie_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".argus").join("cookies.toml"))
+}
 #[tokio::main]
 async fn main() {
     let mut window: YWindow<Yhandling> = yrl::YWindow::new(yrl::YWindowData {
@@ -1,13 +1,82 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
 
-use http::{HeaderMap, HeaderValue, Method};
-use reqwest::{Body, Client, Proxy, RequestBuilder};
+use http::{header, HeaderMap, HeaderValue, Method, Uri};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use reqwest::{Body, Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use body::{Deadline, Head, Incoming};
+
+use crate::{
+    cookies::{self, CookieStore},
+    environment::{mask_url, Environment, MASK},
+    history::{History, HistoryEntry},
+};
+
+mod body;
+mod cancel;
+mod config;
+mod connector;
+pub mod curl;
+mod digest;
+mod download;
+pub mod export;
+mod graphql;
+mod model;
+mod multipart;
+mod oauth;
+mod proxy;
+mod sigv4;
+mod sse;
+mod websocket;
+pub use cancel::ReqxCancel;
+pub use config::{ClientCertificate, HttpVersion, RedirectPolicy, ReqxConfig, TlsVersion};
+pub use connector::TimedConnector;
+pub use digest::DigestChallenge;
+pub use download::{DownloadProgress, ReqxDownload};
+pub use graphql::{
+    GraphQlArgument, GraphQlError, GraphQlField, GraphQlIssue, GraphQlQuery, GraphQlSchema,
+    GraphQlType, SchemaCache, TypeKind, TypeRef, INTROSPECTION_QUERY,
+};
+pub(crate) use model::{redirect_method, ORIGIN_BOUND};
+pub use model::{ReqxBody, ReqxRedirect, ReqxRequest, ReqxResponse, ReqxTiming};
+pub use multipart::{FormPart, PartValue};
+pub use oauth::{AuthorizeHook, OAuth2Config, OAuth2Grant, OAuth2Token, OAuthError, TokenCache};
+pub use proxy::ProxySettings;
+pub use sigv4::AwsSigV4;
+pub use sse::{SseEvent, SseParser, SseStream, SseUpdate};
+pub use websocket::{WsDirection, WsEvent, WsFrame, WsLog, WsMessage, WsRequest, WsSession};
 
 #[derive(Debug)]
 pub struct Reqx {
+    //swapped by set_proxy while sends share the Reqx, each send takes its own copy
+    route: RwLock<Route>,
+    //clients for the proxies of environments, built on first use
+    routes: Mutex<Vec<(ProxySettings, Route)>>,
+    connector: TimedConnector,
+    config: ReqxConfig,
+    history: Option<Arc<Mutex<History>>>,
+    tokens: TokenCache,
+    schemas: SchemaCache,
+    authorize_hook: AuthorizeHook,
+    cookies: Arc<Mutex<CookieStore>>,
+}
+//a client and the proxy settings it was built with, system variables already merged in
+#[derive(Debug, Clone)]
+struct Route {
+    proxy: ProxySettings,
     client: Client,
 }
-#[derive(Debug)]
+//where a send goes: the environment picks the cookie jar and token cache, the route the proxy
+struct Target<'a> {
+    environment: Option<&'a str>,
+    route: Route,
+}
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum ReqxAuth {
     None,
     Basic {
@@ -15,12 +84,255 @@ pub enum ReqxAuth {
         username: String,
     },
     Bearer(String),
+    //resolved to a bearer token when sent, see Reqx::authorize
+    OAuth2(OAuth2Config),
+    //answered after a first unauthenticated round trip returns the challenge
+    Digest {
+        password: String,
+        username: String,
+    },
+    ApiKey {
+        name: String,
+        value: String,
+        location: ApiKeyLocation,
+    },
+    //signed once the request is built, so the signature covers exactly what is sent
+    AwsSigV4(AwsSigV4),
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiKeyLocation {
+    Header,
+    Query,
+}
+#[derive(Debug)]
 pub struct ReqxData {
     pub headers: HeaderMap<HeaderValue>,
     pub authentication: ReqxAuth,
     pub body: Option<Body>,
-    pub form: Option<std::collections::HashMap<String, String>>,
+    //urlencoded fields in order, keys can repeat
+    pub form: Option<Vec<(String, String)>>,
+    pub multipart: Option<Vec<FormPart>>,
+}
+#[derive(Debug)]
+pub enum ReqxError {
+    //body, form and multipart all set the request payload, only one can be used
+    BodyWithForm,
+    //method has no payload semantics (HEAD, TRACE, CONNECT)
+    PayloadNotAllowed(Method),
+    //authentication set while an Authorization header is also given
+    AuthConflict,
+    InvalidHeader(String),
+    //{{name}} placeholders with no value in the environment
+    UnresolvedVariables(Vec<String>),
+    //variables whose values end up referring back to themselves
+    RecursiveVariables(Vec<String>),
+    //history entry recorded in the named environment, it has to be rendered there again
+    EnvironmentRequired(String),
+    //body is a stream and its bytes cannot be read without consuming it
+    StreamingBody,
+    InvalidUrl(String),
+    Http(reqwest::Error),
+    Hyper(hyper::Error),
+    Tls(native_tls::Error),
+    Io(std::io::Error),
+    OAuth(OAuthError),
+    //digest and sigv4 have to see the final request, which fetch leaves to its caller
+    SendOnlyAuth,
+    //ReqxConfig that cannot be turned into a client, unreadable certificates and the like
+    Config(String),
+    Timeout(Duration),
+    //http/2 was required but the server did not agree to it through alpn
+    Http2NotNegotiated(String),
+    //stopped through its ReqxCancel
+    Cancelled,
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    GraphQl(GraphQlError),
+}
+//credentials never show up in logs, only whether they are set
+impl std::fmt::Debug for ReqxAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Basic { password, username } => f
+                .debug_struct("Basic")
+                .field("password", &password.as_ref().map(|_| MASK))
+                .field("username", username)
+                .finish(),
+            Self::Bearer(_) => f.debug_tuple("Bearer").field(&MASK).finish(),
+            Self::OAuth2(config) => f.debug_tuple("OAuth2").field(config).finish(),
+            Self::Digest { username, .. } => f
+                .debug_struct("Digest")
+                .field("password", &MASK)
+                .field("username", username)
+                .finish(),
+            Self::ApiKey { name, location, .. } => f
+                .debug_struct("ApiKey")
+                .field("name", name)
+                .field("value", &MASK)
+                .field("location", location)
+                .finish(),
+            Self::AwsSigV4(config) => f.debug_tuple("AwsSigV4").field(config).finish(),
+        }
+    }
+}
+impl ReqxAuth {
+    //the Authorization header value reqwest would send for this auth,
+    //schemes that need a token, a challenge or the final request are not known here
+    pub fn header_value(&self) -> Option<String> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        match self {
+            Self::None => None,
+            Self::Basic { password, username } => {
+                let credentials = format!("{username}:{}", password.as_deref().unwrap_or(""));
+                Some(format!("Basic {}", STANDARD.encode(credentials)))
+            }
+            Self::Bearer(token) => Some(format!("Bearer {token}")),
+            Self::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Header,
+            } if name.eq_ignore_ascii_case("authorization") => Some(value.clone()),
+            _ => None,
+        }
+    }
+    //the same auth with every credential replaced by the mask, for display and exports
+    pub fn masked(&self) -> Self {
+        let mask = || String::from(MASK);
+        match self {
+            Self::None => Self::None,
+            Self::Basic { password, username } => Self::Basic {
+                password: password.as_ref().map(|_| mask()),
+                username: username.clone(),
+            },
+            Self::Bearer(_) => Self::Bearer(mask()),
+            Self::OAuth2(config) => Self::OAuth2(OAuth2Config {
+                grant: match &config.grant {
+                    OAuth2Grant::Password { username, .. } => OAuth2Grant::Password {
+                        username: username.clone(),
+                        password: mask(),
+                    },
+                    grant => grant.clone(),
+                },
+                client_secret: config.client_secret.as_ref().map(|_| mask()),
+                ..config.clone()
+            }),
+            Self::Digest { username, .. } => Self::Digest {
+                password: mask(),
+                username: username.clone(),
+            },
+            Self::ApiKey { name, location, .. } => Self::ApiKey {
+                name: name.clone(),
+                value: mask(),
+                location: *location,
+            },
+            Self::AwsSigV4(config) => Self::AwsSigV4(AwsSigV4 {
+                secret_key: mask(),
+                session_token: config.session_token.as_ref().map(|_| mask()),
+                ..config.clone()
+            }),
+        }
+    }
+}
+impl Display for ReqxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BodyWithForm => write!(
+                f,
+                "request can only have one of a body, a form or a multipart form"
+            ),
+            Self::PayloadNotAllowed(method) => {
+                write!(f, "{method} requests cannot carry a body or a form")
+            }
+            Self::AuthConflict => write!(
+                f,
+                "authentication conflicts with the Authorization header already set"
+            ),
+            Self::InvalidHeader(name) => write!(f, "invalid header `{name}`"),
+            Self::UnresolvedVariables(names) => {
+                write!(f, "unresolved variables: {}", names.join(", "))
+            }
+            Self::RecursiveVariables(names) => {
+                write!(f, "variables refer to themselves: {}", names.join(", "))
+            }
+            Self::StreamingBody => write!(f, "streaming bodies cannot be read back"),
+            Self::InvalidUrl(url) => write!(f, "invalid url `{url}`"),
+            Self::Http(e) => write!(f, "http error: {e}"),
+            Self::Hyper(e) => write!(f, "http error: {e}"),
+            Self::Tls(e) => write!(f, "tls error: {e}"),
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::OAuth(e) => write!(f, "oauth2 error: {e}"),
+            Self::SendOnlyAuth => write!(
+                f,
+                "digest and aws sigv4 authentication need the request to be sent through Reqx"
+            ),
+            Self::Config(reason) => write!(f, "invalid client configuration: {reason}"),
+            Self::Timeout(timeout) => write!(f, "request timed out after {timeout:?}"),
+            Self::Http2NotNegotiated(url) => {
+                write!(
+                    f,
+                    "{url} did not negotiate http/2, which the config requires"
+                )
+            }
+            Self::Cancelled => write!(f, "request cancelled"),
+            Self::EnvironmentRequired(name) => {
+                write!(
+                    f,
+                    "request was sent in environment `{name}`, it is needed to resend it"
+                )
+            }
+            Self::WebSocket(e) => write!(f, "websocket error: {e}"),
+            Self::GraphQl(e) => write!(f, "graphql error: {e}"),
+        }
+    }
+}
+impl std::error::Error for ReqxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(e) => Some(e),
+            Self::Hyper(e) => Some(e),
+            Self::Tls(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::OAuth(e) => Some(e),
+            Self::WebSocket(e) => Some(e.as_ref()),
+            Self::GraphQl(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<reqwest::Error> for ReqxError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+impl From<hyper::Error> for ReqxError {
+    fn from(e: hyper::Error) -> Self {
+        Self::Hyper(e)
+    }
+}
+impl From<native_tls::Error> for ReqxError {
+    fn from(e: native_tls::Error) -> Self {
+        Self::Tls(e)
+    }
+}
+impl From<std::io::Error> for ReqxError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<tokio_tungstenite::tungstenite::Error> for ReqxError {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(e))
+    }
+}
+impl From<OAuthError> for ReqxError {
+    fn from(e: OAuthError) -> Self {
+        Self::OAuth(e)
+    }
+}
+impl From<GraphQlError> for ReqxError {
+    fn from(e: GraphQlError) -> Self {
+        Self::GraphQl(e)
+    }
 }
 impl Default for ReqxData {
     fn default() -> Self {
@@ -29,35 +341,827 @@ impl Default for ReqxData {
             authentication: ReqxAuth::None,
             body: None,
             form: None,
+            multipart: None,
+        }
+    }
+}
+impl ReqxData {
+    pub fn validate(&self, method: &Method) -> Result<(), ReqxError> {
+        let payloads = [
+            self.body.is_some(),
+            self.form.is_some(),
+            self.multipart.is_some(),
+        ];
+        let has_payload = payloads.contains(&true);
+        if payloads.iter().filter(|set| **set).count() > 1 {
+            return Err(ReqxError::BodyWithForm);
+        }
+        if has_payload && !Reqx::allows_payload(method) {
+            return Err(ReqxError::PayloadNotAllowed(method.clone()));
         }
+        let sets_authorization = match &self.authentication {
+            ReqxAuth::None => false,
+            ReqxAuth::ApiKey { .. } => self.authentication.header_value().is_some(),
+            _ => true,
+        };
+        if sets_authorization && self.headers.contains_key(header::AUTHORIZATION) {
+            return Err(ReqxError::AuthConflict);
+        }
+        Ok(())
+    }
+    //the boundary has to match, so a content type set by hand is replaced
+    fn set_multipart(&mut self, encoded: multipart::Encoded) -> Result<(), ReqxError> {
+        let content_type = HeaderValue::from_str(&encoded.content_type)
+            .map_err(|_| ReqxError::InvalidHeader(header::CONTENT_TYPE.to_string()))?;
+        self.headers.insert(header::CONTENT_TYPE, content_type);
+        self.body = Some(encoded.body.into());
+        self.multipart = None;
+        Ok(())
     }
 }
 impl Default for Reqx {
+    //the default configuration only fails when the system tls library cannot be loaded
     fn default() -> Self {
-        Self {
-            client: Client::new(),
-        }
+        Self::new(ReqxConfig::default()).expect("default client configuration")
     }
 }
 impl Reqx {
-    pub fn new(proxy: Option<Proxy>) -> Self {
-        if let Some(proxy) = proxy {
-            Self {
-                client: Client::builder().proxy(proxy).build().unwrap(),
+    pub fn new(config: ReqxConfig) -> Result<Self, ReqxError> {
+        Ok(Self {
+            route: RwLock::new(Route::new(&config, &config.proxy)?),
+            routes: Mutex::default(),
+            connector: TimedConnector::new(config.tls()?),
+            config,
+            history: None,
+            tokens: TokenCache::default(),
+            schemas: SchemaCache::default(),
+            authorize_hook: AuthorizeHook::default(),
+            cookies: Arc::default(),
+        })
+    }
+    pub fn config(&self) -> &ReqxConfig {
+        &self.config
+    }
+    //switches the proxy of sends outside of an environment, environments keep their own
+    //sends already under way finish on the proxy they started with
+    pub fn set_proxy(&self, proxy: ProxySettings) -> Result<(), ReqxError> {
+        let route = Route::new(&self.config, &proxy)?;
+        *self.route.write().unwrap_or_else(|e| e.into_inner()) = route;
+        Ok(())
+    }
+    //drops the clients built for environment proxies, they are rebuilt with current system
+    //variables on the next send
+    pub fn reset_routes(&self) {
+        if let Ok(mut routes) = self.routes.lock() {
+            routes.clear();
+        }
+    }
+    pub fn allows_payload(method: &Method) -> bool {
+        !matches!(*method, Method::HEAD | Method::TRACE | Method::CONNECT)
+    }
+    //the builder carries the configured timeout, the client itself has none
+    pub fn fetch(
+        &self,
+        url: &str,
+        method: http::Method,
+        data: Option<ReqxData>,
+    ) -> Result<RequestBuilder, ReqxError> {
+        let req = self.builder(url, method, data)?;
+        Ok(match self.config.timeout {
+            Some(timeout) => req.timeout(timeout),
+            None => req,
+        })
+    }
+    fn builder(
+        &self,
+        url: &str,
+        method: http::Method,
+        data: Option<ReqxData>,
+    ) -> Result<RequestBuilder, ReqxError> {
+        let data = data.unwrap_or_default();
+        data.validate(&method)?;
+        match data.authentication {
+            ReqxAuth::OAuth2(_) => return Err(OAuthError::TokenRequired.into()),
+            ReqxAuth::Digest { .. } | ReqxAuth::AwsSigV4(_) => return Err(ReqxError::SendOnlyAuth),
+            _ => {}
+        }
+        let req = self.default_route().client.request(method, url);
+        Self::handle_request(req, data)
+    }
+    pub fn fetch_in(
+        &self,
+        env: &Environment,
+        url: &str,
+        method: http::Method,
+        data: Option<ReqxData>,
+    ) -> Result<RequestBuilder, ReqxError> {
+        let (url, data) = env.render_data(url, data.unwrap_or_default())?;
+        self.fetch(&url, method, Some(data))
+    }
+    pub async fn send_in(
+        &self,
+        env: &Environment,
+        request: &ReqxRequest,
+    ) -> Result<ReqxResponse, ReqxError> {
+        let target = Target {
+            environment: Some(&env.name),
+            route: self.route_for(env.render_proxy()?.as_ref())?,
+        };
+        self.send_recorded(request, Some(env), &target, None).await
+    }
+    pub async fn send(&self, request: &ReqxRequest) -> Result<ReqxResponse, ReqxError> {
+        self.send_recorded(request, None, &self.target(None), None)
+            .await
+    }
+    //streams the body into the download's file instead of memory, the response comes back
+    //without it; redirects, auth and cookies work as they do for a send
+    pub async fn download(
+        &self,
+        request: &ReqxRequest,
+        download: &ReqxDownload,
+    ) -> Result<ReqxResponse, ReqxError> {
+        self.send_recorded(request, None, &self.target(None), Some(download))
+            .await
+    }
+    pub async fn download_in(
+        &self,
+        env: &Environment,
+        request: &ReqxRequest,
+        download: &ReqxDownload,
+    ) -> Result<ReqxResponse, ReqxError> {
+        let target = Target {
+            environment: Some(&env.name),
+            route: self.route_for(env.render_proxy()?.as_ref())?,
+        };
+        self.send_recorded(request, Some(env), &target, Some(download))
+            .await
+    }
+    //keeps a text/event-stream response open on the runtime and reconnects when it drops,
+    //events are not recorded in history
+    pub fn events(self: &Arc<Self>, request: &ReqxRequest) -> SseStream {
+        SseStream::spawn(self.clone(), request.clone(), None, self.default_route())
+    }
+    pub fn events_in(
+        self: &Arc<Self>,
+        env: &Environment,
+        request: &ReqxRequest,
+    ) -> Result<SseStream, ReqxError> {
+        let route = self.route_for(env.render_proxy()?.as_ref())?;
+        let request = env.render_request(request)?;
+        Ok(SseStream::spawn(
+            self.clone(),
+            request,
+            Some(env.name.clone()),
+            route,
+        ))
+    }
+    //the upgrade request is authenticated and gets cookies like a send, it is not recorded
+    //in history; websockets do not go through proxies
+    pub async fn websocket(&self, request: &WsRequest) -> Result<WsSession, ReqxError> {
+        self.connect_websocket(request, &self.target(None)).await
+    }
+    pub async fn websocket_in(
+        &self,
+        env: &Environment,
+        request: &WsRequest,
+    ) -> Result<WsSession, ReqxError> {
+        let target = Target {
+            environment: Some(&env.name),
+            route: self.route_for(env.render_proxy()?.as_ref())?,
+        };
+        let request = WsRequest {
+            request: env.render_request(&request.request)?,
+            subprotocols: request.subprotocols.clone(),
+        };
+        self.connect_websocket(&request, &target).await
+    }
+    //the schema of the endpoint `request` goes to, introspected with its headers and auth and
+    //cached by url until `refresh` asks for it again; the body of `request` is not used
+    pub async fn schema(
+        &self,
+        request: &ReqxRequest,
+        refresh: bool,
+    ) -> Result<Arc<GraphQlSchema>, ReqxError> {
+        self.introspect(request, None, &self.target(None), refresh)
+            .await
+    }
+    pub async fn schema_in(
+        &self,
+        env: &Environment,
+        request: &ReqxRequest,
+        refresh: bool,
+    ) -> Result<Arc<GraphQlSchema>, ReqxError> {
+        let target = Target {
+            environment: Some(&env.name),
+            route: self.route_for(env.render_proxy()?.as_ref())?,
+        };
+        self.introspect(request, Some(env), &target, refresh).await
+    }
+    pub fn schemas(&self) -> &SchemaCache {
+        &self.schemas
+    }
+    //sends a stored request again, entries recorded in an environment only keep its
+    //templates and have to go through resend_in
+    pub async fn resend(&self, entry: &HistoryEntry) -> Result<ReqxResponse, ReqxError> {
+        if let Some(name) = &entry.environment {
+            return Err(ReqxError::EnvironmentRequired(name.clone()));
+        }
+        self.send(&entry.request).await
+    }
+    //renders the stored templates in `env` again, which need not be the one it was sent in
+    pub async fn resend_in(
+        &self,
+        env: &Environment,
+        entry: &HistoryEntry,
+    ) -> Result<ReqxResponse, ReqxError> {
+        self.send_in(env, &entry.request).await
+    }
+    //the handle is shared so the ui can search the same store sends are recorded to
+    pub fn with_history(mut self, history: Arc<Mutex<History>>) -> Self {
+        self.history = Some(history);
+        self
+    }
+    //tokens are shared with every send of this Reqx and can be dropped to force a new grant
+    pub fn tokens(&self) -> &TokenCache {
+        &self.tokens
+    }
+    pub fn with_authorize_hook(mut self, hook: AuthorizeHook) -> Self {
+        self.authorize_hook = hook;
+        self
+    }
+    //every send reads and updates the jar of its environment, share the store to persist it
+    pub fn with_cookies(mut self, cookies: Arc<Mutex<CookieStore>>) -> Self {
+        self.cookies = cookies;
+        self
+    }
+    pub fn cookies(&self) -> &Arc<Mutex<CookieStore>> {
+        &self.cookies
+    }
+    //the token for `config` in `environment`, from the cache while valid, refreshed or newly granted
+    pub async fn authorize(
+        &self,
+        environment: Option<&str>,
+        config: &OAuth2Config,
+    ) -> Result<OAuth2Token, ReqxError> {
+        oauth::authorize(
+            &self.default_route().client,
+            self.config.timeout,
+            &self.tokens,
+            &self.authorize_hook,
+            environment,
+            config,
+        )
+        .await
+    }
+    //introspection is not recorded in history, it is not a request anybody made
+    //the body is swapped for the introspection query before rendering, so variables only
+    //the query uses need not resolve; dispatch follows redirects like a send
+    async fn introspect(
+        &self,
+        request: &ReqxRequest,
+        env: Option<&Environment>,
+        target: &Target<'_>,
+        refresh: bool,
+    ) -> Result<Arc<GraphQlSchema>, ReqxError> {
+        let introspection = ReqxRequest {
+            method: Method::POST,
+            body: ReqxBody::GraphQl(GraphQlQuery::introspection()),
+            ..request.clone()
+        };
+        let introspection = match env {
+            Some(env) => env.render_request(&introspection)?,
+            None => introspection,
+        };
+        if !refresh {
+            if let Some(schema) = self.schemas.get(&introspection.url) {
+                return Ok(schema);
+            }
+        }
+        let head = self.dispatch(&introspection, target).await?;
+        let response = head.read().await?;
+        let schema = Arc::new(GraphQlSchema::from_response(&response)?);
+        self.schemas.insert(&introspection.url, schema.clone());
+        Ok(schema)
+    }
+    fn target<'a>(&self, environment: Option<&'a str>) -> Target<'a> {
+        Target {
+            environment,
+            route: self.default_route(),
+        }
+    }
+    fn default_route(&self) -> Route {
+        self.route.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    fn route_for(&self, proxy: Option<&ProxySettings>) -> Result<Route, ReqxError> {
+        let default = self.default_route();
+        let Some(proxy) = proxy.filter(|proxy| proxy.resolved() != default.proxy) else {
+            return Ok(default);
+        };
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, route)) = routes.iter().find(|(settings, _)| settings == proxy) {
+            return Ok(route.clone());
+        }
+        let route = Route::new(&self.config, proxy)?;
+        routes.push((proxy.clone(), route.clone()));
+        Ok(route)
+    }
+    //history stores `request` as it is, before `env` rendered secrets in, and is searched
+    //by the rendered url with its secrets masked
+    async fn send_recorded(
+        &self,
+        request: &ReqxRequest,
+        env: Option<&Environment>,
+        target: &Target<'_>,
+        download: Option<&ReqxDownload>,
+    ) -> Result<ReqxResponse, ReqxError> {
+        let (rendered, url) = match env {
+            Some(env) => (
+                env.render_request(request)?,
+                env.render_request_masked(request)?.url,
+            ),
+            None => (request.clone(), request.url.clone()),
+        };
+        let started = chrono::Utc::now();
+        let response = match download {
+            Some(download) => {
+                let work = self.download_to(&rendered, target, download);
+                let result = download.cancel().guard(work).await;
+                if let Err(ReqxError::Cancelled) = result {
+                    download.discard().await;
+                }
+                result?
             }
+            None => {
+                let head = self.dispatch(&rendered, target).await?;
+                head.read().await?
+            }
+        };
+        if let Some(history) = &self.history {
+            //history is best effort, failing to write it must not lose the response
+            if let Ok(history) = history.lock() {
+                let url = mask_url(&url);
+                let _ = history.record(started, target.environment, &url, request, &response);
+            }
+        }
+        Ok(response)
+    }
+    async fn download_to(
+        &self,
+        request: &ReqxRequest,
+        target: &Target<'_>,
+        download: &ReqxDownload,
+    ) -> Result<ReqxResponse, ReqxError> {
+        let offset = download.offset().await?;
+        let head = if offset > 0 {
+            let mut request = request.clone();
+            request
+                .headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("range"));
+            request
+                .headers
+                .push((String::from("Range"), format!("bytes={offset}-")));
+            self.dispatch(&request, target).await?
         } else {
-            Self::default()
+            self.dispatch(request, target).await?
+        };
+        download.write(head, offset, self.config.timeout).await
+    }
+    async fn dispatch(
+        &self,
+        request: &ReqxRequest,
+        target: &Target<'_>,
+    ) -> Result<Head, ReqxError> {
+        let deadline = Deadline::after(self.config.timeout);
+        let mut data = request.to_data()?;
+        let auth = self.resolve_auth(&mut data, target).await?;
+        if let Some(parts) = &data.multipart {
+            data.validate(&request.method)?;
+            let encoded = multipart::encode(parts).await?;
+            data.set_multipart(encoded)?;
+        }
+        let mut req = self
+            .builder(&request.url, request.method.clone(), Some(data))?
+            .build()?;
+        match auth {
+            ReqxAuth::AwsSigV4(config) => config.sign(&mut req, chrono::Utc::now())?,
+            ReqxAuth::Digest { password, username } => {
+                let retry = req.try_clone().ok_or(ReqxError::StreamingBody)?;
+                let head = self.execute(req, target, deadline).await?;
+                let challenge = DigestChallenge::find(
+                    head.response
+                        .headers
+                        .iter()
+                        .filter(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+                        .map(|(_, value)| value.as_str()),
+                );
+                let Some(challenge) = challenge.filter(|_| head.response.status == 401) else {
+                    return Ok(head);
+                };
+                return self
+                    .digest(retry, &challenge, &username, &password, target, deadline)
+                    .await;
+            }
+            _ => {}
         }
+        self.execute(req, target, deadline).await
     }
-    pub fn fetch(&self, url: &str, method: http::Method, data: Option<ReqxData>) -> RequestBuilder {
-        let req = self.client.request(method.clone(), url);
-        if !matches!(method, Method::GET) {
-            req
+    //oauth2 becomes a bearer token in `data`, digest and sigv4 are taken out and returned
+    //as they have to be applied around the built request
+    async fn resolve_auth(
+        &self,
+        data: &mut ReqxData,
+        target: &Target<'_>,
+    ) -> Result<ReqxAuth, ReqxError> {
+        match &data.authentication {
+            ReqxAuth::OAuth2(config) => {
+                //token requests go through the same proxy as the request they authorize
+                let token = oauth::authorize(
+                    &target.route.client,
+                    self.config.timeout,
+                    &self.tokens,
+                    &self.authorize_hook,
+                    target.environment,
+                    config,
+                )
+                .await?;
+                data.authentication = ReqxAuth::Bearer(token.access_token);
+                Ok(ReqxAuth::None)
+            }
+            ReqxAuth::Digest { .. } | ReqxAuth::AwsSigV4(_) => {
+                Ok(std::mem::replace(&mut data.authentication, ReqxAuth::None))
+            }
+            _ => Ok(ReqxAuth::None),
+        }
+    }
+    async fn digest(
+        &self,
+        mut req: reqwest::Request,
+        challenge: &DigestChallenge,
+        username: &str,
+        password: &str,
+        target: &Target<'_>,
+        deadline: Option<Deadline>,
+    ) -> Result<Head, ReqxError> {
+        Self::authorize_digest(&mut req, challenge, username, password)?;
+        self.execute(req, target, deadline).await
+    }
+    fn authorize_digest(
+        req: &mut reqwest::Request,
+        challenge: &DigestChallenge,
+        username: &str,
+        password: &str,
+    ) -> Result<(), ReqxError> {
+        let uri = &req.url()[url::Position::BeforePath..url::Position::AfterQuery];
+        let body = match req.body() {
+            Some(body) => body.as_bytes().ok_or(ReqxError::StreamingBody)?,
+            None => &[],
+        };
+        let authorization = challenge.respond(
+            username,
+            password,
+            req.method().as_str(),
+            uri,
+            body,
+            &oauth::random_token(16),
+        );
+        let authorization = HeaderValue::from_str(&authorization)
+            .map_err(|_| ReqxError::InvalidHeader(header::AUTHORIZATION.to_string()))?;
+        req.headers_mut()
+            .insert(header::AUTHORIZATION, authorization);
+        Ok(())
+    }
+    async fn connect_websocket(
+        &self,
+        request: &WsRequest,
+        target: &Target<'_>,
+    ) -> Result<WsSession, ReqxError> {
+        let invalid = || ReqxError::InvalidUrl(request.request.url.clone());
+        let mut url = url::Url::parse(&request.request.url).map_err(|_| invalid())?;
+        if target.route.proxy.proxy_for(&url).is_some() {
+            return Err(ReqxError::Config(format!(
+                "websockets cannot go through the proxy set for `{}`",
+                url.host_str().unwrap_or_default()
+            )));
+        }
+        //the upgrade is built as the http request it is, so auth and cookies apply as usual
+        let scheme = match url.scheme() {
+            "ws" => "http",
+            "wss" => "https",
+            _ => return Err(invalid()),
+        };
+        url.set_scheme(scheme).map_err(|_| invalid())?;
+        if request.request.body != ReqxBody::Empty {
+            return Err(ReqxError::PayloadNotAllowed(Method::GET));
+        }
+        let mut data = request.request.to_data()?;
+        let auth = self.resolve_auth(&mut data, target).await?;
+        let mut req = self
+            .builder(url.as_str(), Method::GET, Some(data))?
+            .build()?;
+        if let ReqxAuth::AwsSigV4(config) = &auth {
+            config.sign(&mut req, chrono::Utc::now())?;
+        }
+        self.add_cookies(&mut req, target.environment, false)?;
+        let tls = self.config.websocket_tls()?;
+        let timeout = self.config.timeout;
+        let shown = mask_url(&export::api_key_url(
+            &request.request.url,
+            &request.request.authentication.masked(),
+        ));
+        let mut session =
+            websocket::connect(&req, &shown, &request.subprotocols, tls.clone(), timeout).await;
+        if let (Err(e), ReqxAuth::Digest { password, username }) = (&session, &auth) {
+            let challenge = websocket::rejection(e)
+                .filter(|(status, _)| *status == 401)
+                .and_then(|(_, headers)| {
+                    DigestChallenge::find(
+                        headers
+                            .iter()
+                            .filter(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+                            .map(|(_, value)| value.as_str()),
+                    )
+                });
+            if let Some(challenge) = challenge {
+                Self::authorize_digest(&mut req, &challenge, username, password)?;
+                session =
+                    websocket::connect(&req, &shown, &request.subprotocols, tls, timeout).await;
+            }
+        }
+        let session = session?;
+        if let Ok(mut cookies) = self.cookies.lock() {
+            cookies.jar_mut(target.environment).store(
+                req.url(),
+                session
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+                    .map(|(_, value)| value.as_str()),
+            );
+        }
+        Ok(session)
+    }
+    //follows redirects as the config allows and records every hop on the final response,
+    //each hop reads and updates the cookie jar
+    async fn execute(
+        &self,
+        mut req: reqwest::Request,
+        target: &Target<'_>,
+        deadline: Option<Deadline>,
+    ) -> Result<Head, ReqxError> {
+        let limit = match self.config.redirects {
+            RedirectPolicy::Follow(limit) => limit,
+            RedirectPolicy::Never => 0,
+        };
+        let origin = req.url().clone();
+        let mut redirects = Vec::new();
+        loop {
+            let next = if redirects.len() < limit {
+                req.try_clone()
+            } else {
+                None
+            };
+            let cross_site = !cookies::same_site(&origin, req.url());
+            self.add_cookies(&mut req, target.environment, cross_site)?;
+            let (method, url) = (req.method().clone(), req.url().to_string());
+            let request_headers = model::header_pairs(req.headers());
+            let mut head = self.send_once(req, target, deadline).await?;
+            let Some(next) = next.and_then(|next| Self::redirect(next, &head.response)) else {
+                head.response.redirects = redirects;
+                return Ok(head);
+            };
+            //the body of a redirect is not read
+            let response = head.finish();
+            redirects.push(ReqxRedirect {
+                method,
+                url,
+                request_headers,
+                status: response.status,
+                version: response.version,
+                headers: response.headers,
+                location: next.url().to_string(),
+                remote_addr: response.remote_addr,
+                timing: response.timing,
+            });
+            req = next;
+        }
+    }
+    //the request to send for a redirect response, None when it is not a redirect to follow;
+    //methods change the way browsers change them and credentials stay on the origin that got them
+    fn redirect(mut req: reqwest::Request, response: &ReqxResponse) -> Option<reqwest::Request> {
+        if !matches!(response.status, 301 | 302 | 303 | 307 | 308) {
+            return None;
+        }
+        let location = req.url().join(response.header("location")?).ok()?;
+        if !matches!(location.scheme(), "http" | "https") {
+            return None;
+        }
+        let method = redirect_method(response.status, req.method());
+        if method != *req.method() {
+            *req.method_mut() = method;
+            *req.body_mut() = None;
+            for name in [
+                header::CONTENT_TYPE,
+                header::CONTENT_LENGTH,
+                header::CONTENT_ENCODING,
+            ] {
+                req.headers_mut().remove(name);
+            }
+        }
+        if location.origin() != req.url().origin() {
+            for name in ORIGIN_BOUND {
+                req.headers_mut().remove(name);
+            }
+        }
+        *req.url_mut() = location;
+        Some(req)
+    }
+    //the deadline covers the head here and goes on the head for the body
+    async fn send_once(
+        &self,
+        req: reqwest::Request,
+        target: &Target<'_>,
+        deadline: Option<Deadline>,
+    ) -> Result<Head, ReqxError> {
+        let url = req.url().clone();
+        //requests through a proxy go through reqwest, which hides the connection phases
+        let send = async {
+            match target.route.proxy.proxy_for(&url) {
+                Some(_) => Self::send_proxied(&target.route.client, req).await,
+                None => self.send_direct(req).await,
+            }
+        };
+        let mut head = Deadline::limit(deadline, send).await?;
+        head.deadline = deadline;
+        if let Ok(mut cookies) = self.cookies.lock() {
+            cookies.jar_mut(target.environment).store(
+                &url,
+                head.response
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+                    .map(|(_, value)| value.as_str()),
+            );
+        }
+        Ok(head)
+    }
+    //cookies from the jar join a Cookie header set by hand, which wins on a name both have
+    fn add_cookies(
+        &self,
+        req: &mut reqwest::Request,
+        environment: Option<&str>,
+        cross_site: bool,
+    ) -> Result<(), ReqxError> {
+        let Ok(cookies) = self.cookies.lock() else {
+            return Ok(());
+        };
+        let Some(jar) = cookies.jar(environment) else {
+            return Ok(());
+        };
+        let Some(stored) = jar.header(req.url(), req.method(), cross_site) else {
+            return Ok(());
+        };
+        let value = match req.headers().get(header::COOKIE) {
+            Some(existing) => {
+                let existing = String::from_utf8_lossy(existing.as_bytes()).into_owned();
+                let names: Vec<_> = existing
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('=').map(|(name, _)| name.trim()))
+                    .collect();
+                let mut value = existing.clone();
+                for pair in stored.split("; ") {
+                    let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+                    if !names.contains(&name) {
+                        value.push_str("; ");
+                        value.push_str(pair);
+                    }
+                }
+                value
+            }
+            None => stored,
+        };
+        let value = HeaderValue::from_str(&value)
+            .map_err(|_| ReqxError::InvalidHeader(header::COOKIE.to_string()))?;
+        req.headers_mut().insert(header::COOKIE, value);
+        Ok(())
+    }
+    async fn send_proxied(client: &Client, req: reqwest::Request) -> Result<Head, ReqxError> {
+        let start = Instant::now();
+        let response = client.execute(req).await?;
+        Ok(Head {
+            response: ReqxResponse {
+                status: response.status().as_u16(),
+                version: format!("{:?}", response.version()),
+                headers: model::header_pairs(response.headers()),
+                body: Vec::new(),
+                remote_addr: response.remote_addr(),
+                timing: ReqxTiming {
+                    ttfb: start.elapsed(),
+                    ..Default::default()
+                },
+                redirects: Vec::new(),
+            },
+            body: Incoming::Proxied(response),
+            start,
+            sent: start,
+            deadline: None,
+        })
+    }
+    async fn send_direct(&self, req: reqwest::Request) -> Result<Head, ReqxError> {
+        let start = Instant::now();
+        let mut req: http::Request<Body> = req.try_into()?;
+        let uri = req.uri().clone();
+        let connected = match self.config.connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.connector.connect(&uri))
+                .await
+                .map_err(|_| ReqxError::Timeout(timeout))??,
+            None => self.connector.connect(&uri).await?,
+        };
+        //alpn picks the version over tls, plain http only speaks http/2 when it is forced
+        let forced = self.config.http_version == HttpVersion::Http2;
+        let h2 = match connected.alpn_h2 {
+            Some(false) if forced => return Err(ReqxError::Http2NotNegotiated(uri.to_string())),
+            Some(h2) => h2,
+            None => forced,
+        };
+        let io = TokioIo::new(connected.stream);
+        let sent;
+        let response = if h2 {
+            //http/2 takes the authority from the absolute uri, Host is not sent
+            req.headers_mut().remove(header::HOST);
+            if !req.headers().contains_key(header::ACCEPT) {
+                req.headers_mut()
+                    .insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+            }
+            *req.version_mut() = http::Version::HTTP_2;
+            let (mut sender, conn) =
+                hyper::client::conn::http2::handshake(TokioExecutor::new(), io).await?;
+            tokio::spawn(async move {
+                let _ = conn.await;
+            });
+            sent = Instant::now();
+            sender.send_request(req).await?
         } else {
-            Self::handle_request(req, data.unwrap_or_default())
+            Self::to_origin_form(&mut req, &uri)?;
+            *req.version_mut() = http::Version::HTTP_11;
+            let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await?;
+            tokio::spawn(async move {
+                let _ = conn.await;
+            });
+            sent = Instant::now();
+            sender.send_request(req).await?
+        };
+        let ttfb = sent.elapsed();
+        let (parts, body) = response.into_parts();
+        Ok(Head {
+            response: ReqxResponse {
+                status: parts.status.as_u16(),
+                version: format!("{:?}", parts.version),
+                headers: model::header_pairs(&parts.headers),
+                body: Vec::new(),
+                remote_addr: Some(connected.remote_addr),
+                timing: ReqxTiming {
+                    dns: Some(connected.dns),
+                    connect: Some(connected.connect),
+                    tls: connected.tls,
+                    ttfb,
+                    ..Default::default()
+                },
+                redirects: Vec::new(),
+            },
+            body: Incoming::Direct(body),
+            start,
+            sent,
+            deadline: None,
+        })
+    }
+    //a raw http1 connection expects the path in the request line and the authority in Host
+    fn to_origin_form(req: &mut http::Request<Body>, uri: &Uri) -> Result<(), ReqxError> {
+        let authority = uri
+            .authority()
+            .ok_or_else(|| ReqxError::InvalidUrl(uri.to_string()))?;
+        let headers = req.headers_mut();
+        if !headers.contains_key(header::HOST) {
+            let host = HeaderValue::from_str(authority.as_str())
+                .map_err(|_| ReqxError::InvalidUrl(uri.to_string()))?;
+            headers.insert(header::HOST, host);
         }
+        if !headers.contains_key(header::ACCEPT) {
+            headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        }
+        let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        *req.uri_mut() = path
+            .parse()
+            .map_err(|_| ReqxError::InvalidUrl(uri.to_string()))?;
+        Ok(())
     }
-    fn handle_request(mut req: RequestBuilder, data: ReqxData) -> RequestBuilder {
+    fn handle_request(
+        mut req: RequestBuilder,
+        mut data: ReqxData,
+    ) -> Result<RequestBuilder, ReqxError> {
+        if let Some(parts) = &data.multipart {
+            let encoded = multipart::encode_blocking(parts)?;
+            data.set_multipart(encoded)?;
+        }
         req = req.headers(data.headers);
         if let Some(body) = data.body {
             req = req.body(body);
@@ -66,10 +1170,31 @@ impl Reqx {
             ReqxAuth::None => {}
             ReqxAuth::Basic { password, username } => req = req.basic_auth(username, password),
             ReqxAuth::Bearer(tk) => req = req.bearer_auth(tk),
+            ReqxAuth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Header,
+            } => req = req.header(name, value),
+            ReqxAuth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Query,
+            } => req = req.query(&[(name, value)]),
+            //refused by fetch, dispatch resolves them around the built request
+            ReqxAuth::OAuth2(_) | ReqxAuth::Digest { .. } | ReqxAuth::AwsSigV4(_) => {}
         }
         if let Some(form) = data.form {
             req = req.form(&form);
         }
-        req
+        Ok(req)
+    }
+}
+impl Route {
+    fn new(config: &ReqxConfig, proxy: &ProxySettings) -> Result<Self, ReqxError> {
+        let proxy = proxy.resolved();
+        Ok(Self {
+            client: config.client(&proxy)?,
+            proxy,
+        })
     }
 }
@@ -0,0 +1,88 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+
+use super::{ReqxError, ReqxResponse};
+
+//a response whose body is still on the connection, read into memory or streamed to a file
+pub(crate) struct Head {
+    //everything but the body and the download phase of the timing
+    pub response: ReqxResponse,
+    pub body: Incoming,
+    pub start: Instant,
+    //ttfb is measured from here
+    pub sent: Instant,
+    //when the request this answers has to be done by, body included
+    pub deadline: Option<Deadline>,
+}
+//the configured timeout counted once from the start of a request, every redirect hop and
+//digest retry runs in what is left of it
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    at: Instant,
+    timeout: Duration,
+}
+impl Deadline {
+    pub fn after(timeout: Option<Duration>) -> Option<Self> {
+        timeout.map(|timeout| Self {
+            at: Instant::now() + timeout,
+            timeout,
+        })
+    }
+    pub async fn limit<T>(
+        deadline: Option<Self>,
+        work: impl Future<Output = Result<T, ReqxError>>,
+    ) -> Result<T, ReqxError> {
+        match deadline {
+            Some(Self { at, timeout }) => tokio::time::timeout_at(at.into(), work)
+                .await
+                .map_err(|_| ReqxError::Timeout(timeout))?,
+            None => work.await,
+        }
+    }
+}
+pub(crate) enum Incoming {
+    Direct(hyper::body::Incoming),
+    Proxied(reqwest::Response),
+}
+impl Incoming {
+    //None once the body is done, trailers are skipped
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, ReqxError> {
+        match self {
+            Self::Direct(body) => loop {
+                let Some(frame) = body.frame().await else {
+                    return Ok(None);
+                };
+                if let Ok(data) = frame?.into_data() {
+                    return Ok(Some(data));
+                }
+            },
+            Self::Proxied(response) => Ok(response.chunk().await?),
+        }
+    }
+}
+impl Head {
+    pub async fn read(mut self) -> Result<ReqxResponse, ReqxError> {
+        let mut body = Vec::new();
+        let read = async {
+            while let Some(chunk) = self.body.chunk().await? {
+                body.extend_from_slice(&chunk);
+            }
+            Ok(())
+        };
+        Deadline::limit(self.deadline, read).await?;
+        self.response.body = body;
+        Ok(self.finish())
+    }
+    //the response as it is now, a body that was not read is dropped with the connection
+    pub fn finish(mut self) -> ReqxResponse {
+        let timing = &mut self.response.timing;
+        timing.download = self.sent.elapsed().saturating_sub(timing.ttfb);
+        timing.total = self.start.elapsed();
+        self.response
+    }
+}
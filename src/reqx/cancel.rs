@@ -0,0 +1,45 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use tokio::sync::Notify;
+
+use super::ReqxError;
+
+//stops the request or download it was taken from, a resumable download keeps its partial
+//file; a cancel stops one run, the same ReqxDownload can be started again to resume
+#[derive(Debug, Clone, Default)]
+pub struct ReqxCancel {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+impl ReqxCancel {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        //a stored permit wakes a wait that starts after this
+        self.notify.notify_one();
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+    //runs `work` until it finishes or is cancelled
+    pub(crate) async fn guard<T>(
+        &self,
+        work: impl std::future::Future<Output = Result<T, ReqxError>>,
+    ) -> Result<T, ReqxError> {
+        let cancelled = async {
+            while !self.is_cancelled() {
+                self.notify.notified().await;
+            }
+        };
+        tokio::select! {
+            biased;
+            _ = cancelled => {
+                self.cancelled.store(false, Ordering::SeqCst);
+                Err(ReqxError::Cancelled)
+            }
+            result = work => result,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use reqwest::{tls, Client};
+
+use super::{ProxySettings, ReqxError};
+use crate::environment::MASK;
+
+#[derive(Debug, Clone, Default)]
+pub struct ReqxConfig {
+    pub connect_timeout: Option<Duration>,
+    //time allowed for each request from the start to the last byte of the body, redirects
+    //and a digest challenge included; downloads only wait this long for each chunk
+    pub timeout: Option<Duration>,
+    pub redirects: RedirectPolicy,
+    //for local dev servers with self signed certificates; any certificate is taken, expired
+    //ones and ones for another hostname as well
+    pub accept_invalid_certs: bool,
+    //pem files, each can hold a bundle of certificates; trusted on top of the system roots
+    pub ca_certificates: Vec<PathBuf>,
+    pub client_certificate: Option<ClientCertificate>,
+    pub http_version: HttpVersion,
+    pub min_tls_version: Option<TlsVersion>,
+    pub max_tls_version: Option<TlsVersion>,
+    //for sends outside of an environment, an environment with proxy settings uses its own
+    pub proxy: ProxySettings,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    //up to this many hops, the last redirect response is returned once they run out
+    Follow(usize),
+    Never,
+}
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    //http/2 when the server offers it through alpn, http/1.1 otherwise and on plain http
+    #[default]
+    Auto,
+    Http1,
+    //plain http goes straight to http/2 without an upgrade
+    Http2,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls10,
+    Tls11,
+    Tls12,
+    Tls13,
+}
+#[derive(Clone, PartialEq)]
+pub enum ClientCertificate {
+    //the key has to be pkcs#8, `BEGIN PRIVATE KEY`; both can be the same file
+    Pem { cert: PathBuf, key: PathBuf },
+    Pkcs12 { path: PathBuf, password: String },
+}
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::Follow(10)
+    }
+}
+impl std::fmt::Debug for ClientCertificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pem { cert, key } => f
+                .debug_struct("Pem")
+                .field("cert", cert)
+                .field("key", key)
+                .finish(),
+            Self::Pkcs12 { path, .. } => f
+                .debug_struct("Pkcs12")
+                .field("path", path)
+                .field("password", &MASK)
+                .finish(),
+        }
+    }
+}
+impl TlsVersion {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Tls10 => "1.0",
+            Self::Tls11 => "1.1",
+            Self::Tls12 => "1.2",
+            Self::Tls13 => "1.3",
+        }
+    }
+    //native-tls has no 1.3 variant, as a maximum it means no limit
+    fn native(self) -> Option<native_tls::Protocol> {
+        match self {
+            Self::Tls10 => Some(native_tls::Protocol::Tlsv10),
+            Self::Tls11 => Some(native_tls::Protocol::Tlsv11),
+            Self::Tls12 => Some(native_tls::Protocol::Tlsv12),
+            Self::Tls13 => None,
+        }
+    }
+    fn reqwest(self) -> tls::Version {
+        match self {
+            Self::Tls10 => tls::Version::TLS_1_0,
+            Self::Tls11 => tls::Version::TLS_1_1,
+            Self::Tls12 => tls::Version::TLS_1_2,
+            Self::Tls13 => tls::Version::TLS_1_3,
+        }
+    }
+}
+impl ReqxConfig {
+    //reqwest serves proxied requests and oauth token requests, redirects are always left
+    //to Reqx so both paths follow them the same way
+    //`proxy` has the system variables merged in already, reqwest never reads them itself
+    //no timeout is set, a client one would cut long downloads short; dispatch applies it
+    pub(crate) fn client(&self, proxy: &ProxySettings) -> Result<Client, ReqxError> {
+        self.check_tls_versions()?;
+        let mut builder = Client::builder()
+            .use_native_tls()
+            .no_proxy()
+            .redirect(reqwest::redirect::Policy::none())
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        for pem in self.ca_pems()? {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+        builder = match &self.client_certificate {
+            None => builder,
+            Some(ClientCertificate::Pem { cert, key }) => builder.identity(
+                reqwest::Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)?,
+            ),
+            Some(ClientCertificate::Pkcs12 { path, password }) => {
+                builder.identity(reqwest::Identity::from_pkcs12_der(&read(path)?, password)?)
+            }
+        };
+        builder = match self.http_version {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1 => builder.http1_only(),
+            HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        };
+        if let Some(min) = self.min_tls_version {
+            builder = builder.min_tls_version(min.reqwest());
+        }
+        if let Some(max) = self.max_tls_version.filter(|max| max.native().is_some()) {
+            builder = builder.max_tls_version(max.reqwest());
+        }
+        if let Some(proxy) = proxy.reqwest()? {
+            builder = builder.proxy(proxy);
+        }
+        Ok(builder.build()?)
+    }
+    pub(crate) fn tls(&self) -> Result<native_tls::TlsConnector, ReqxError> {
+        let alpns: &[&str] = match self.http_version {
+            HttpVersion::Auto => &["h2", "http/1.1"],
+            HttpVersion::Http1 => &["http/1.1"],
+            HttpVersion::Http2 => &["h2"],
+        };
+        self.tls_with(alpns)
+    }
+    //websocket upgrades only exist in http/1.1, whatever version the config asks for
+    pub(crate) fn websocket_tls(&self) -> Result<native_tls::TlsConnector, ReqxError> {
+        self.tls_with(&["http/1.1"])
+    }
+    fn tls_with(&self, alpns: &[&str]) -> Result<native_tls::TlsConnector, ReqxError> {
+        self.check_tls_versions()?;
+        let mut builder = native_tls::TlsConnector::builder();
+        builder.danger_accept_invalid_certs(self.accept_invalid_certs);
+        for pem in self.ca_pems()? {
+            builder.add_root_certificate(native_tls::Certificate::from_pem(&pem)?);
+        }
+        match &self.client_certificate {
+            None => {}
+            Some(ClientCertificate::Pem { cert, key }) => {
+                builder.identity(native_tls::Identity::from_pkcs8(&read(cert)?, &read(key)?)?);
+            }
+            Some(ClientCertificate::Pkcs12 { path, password }) => {
+                builder.identity(native_tls::Identity::from_pkcs12(&read(path)?, password)?);
+            }
+        }
+        builder.request_alpns(alpns);
+        builder.min_protocol_version(self.min_tls_version.and_then(TlsVersion::native));
+        builder.max_protocol_version(self.max_tls_version.and_then(TlsVersion::native));
+        Ok(builder.build()?)
+    }
+    fn check_tls_versions(&self) -> Result<(), ReqxError> {
+        if let (Some(min), Some(max)) = (self.min_tls_version, self.max_tls_version) {
+            if min > max {
+                return Err(ReqxError::Config(format!(
+                    "minimum TLS version {} is above the maximum {}",
+                    min.name(),
+                    max.name()
+                )));
+            }
+        }
+        if self.min_tls_version == Some(TlsVersion::Tls13) {
+            return Err(ReqxError::Config(String::from(
+                "the native TLS backend cannot require TLS 1.3",
+            )));
+        }
+        Ok(())
+    }
+    //every certificate of every bundle, native-tls only reads the first one of a pem file
+    fn ca_pems(&self) -> Result<Vec<Vec<u8>>, ReqxError> {
+        let mut pems = Vec::new();
+        for path in &self.ca_certificates {
+            let text = String::from_utf8_lossy(&read(path)?).into_owned();
+            let before = pems.len();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("-----BEGIN CERTIFICATE-----") {
+                let end_marker = "-----END CERTIFICATE-----";
+                let Some(end) = rest[start..].find(end_marker) else {
+                    break;
+                };
+                let end = start + end + end_marker.len();
+                pems.push(rest.as_bytes()[start..end].to_vec());
+                rest = &rest[end..];
+            }
+            if pems.len() == before {
+                return Err(ReqxError::Config(format!(
+                    "no pem certificate in `{}`",
+                    path.display()
+                )));
+            }
+        }
+        Ok(pems)
+    }
+}
+fn read(path: &PathBuf) -> Result<Vec<u8>, ReqxError> {
+    fs::read(path)
+        .map_err(|e| ReqxError::Config(format!("could not read `{}`: {e}", path.display())))
+}
@@ -0,0 +1,137 @@
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use http::Uri;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+use tokio_native_tls::TlsStream;
+
+use super::ReqxError;
+
+//connector which opens a fresh connection for every request so each phase can be measured
+#[derive(Clone)]
+pub struct TimedConnector {
+    tls: tokio_native_tls::TlsConnector,
+}
+pub enum TimedStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+pub struct Connected {
+    pub stream: TimedStream,
+    pub remote_addr: SocketAddr,
+    pub dns: Duration,
+    pub connect: Duration,
+    pub tls: Option<Duration>,
+    //whether alpn settled on http/2, None on plain connections
+    pub alpn_h2: Option<bool>,
+}
+impl std::fmt::Debug for TimedConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimedConnector").finish_non_exhaustive()
+    }
+}
+impl TimedConnector {
+    pub fn new(tls: native_tls::TlsConnector) -> Self {
+        Self { tls: tls.into() }
+    }
+    pub async fn connect(&self, uri: &Uri) -> Result<Connected, ReqxError> {
+        let https = match uri.scheme_str() {
+            Some("https") => true,
+            Some("http") => false,
+            _ => return Err(ReqxError::InvalidUrl(uri.to_string())),
+        };
+        let host = uri
+            .host()
+            .ok_or_else(|| ReqxError::InvalidUrl(uri.to_string()))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+        let start = Instant::now();
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+        let dns = start.elapsed();
+
+        let start = Instant::now();
+        let mut last_err = None;
+        let mut tcp = None;
+        for addr in addrs {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => {
+                    tcp = Some((stream, addr));
+                    break;
+                }
+                Err(e) => last_err = Some(e),
+            }
+        }
+        let Some((tcp, remote_addr)) = tcp else {
+            return Err(last_err
+                .unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("no address for {host}"))
+                })
+                .into());
+        };
+        tcp.set_nodelay(true)?;
+        let connect = start.elapsed();
+
+        let (stream, tls, alpn_h2) = if https {
+            let start = Instant::now();
+            let stream = self.tls.connect(host, tcp).await?;
+            let tls = start.elapsed();
+            let h2 = stream.get_ref().negotiated_alpn()?.as_deref() == Some(b"h2");
+            (TimedStream::Tls(Box::new(stream)), Some(tls), Some(h2))
+        } else {
+            (TimedStream::Plain(tcp), None, None)
+        };
+        Ok(Connected {
+            stream,
+            remote_addr,
+            dns,
+            connect,
+            tls,
+            alpn_h2,
+        })
+    }
+}
+impl AsyncRead for TimedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            Self::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+impl AsyncWrite for TimedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            Self::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_flush(cx),
+            Self::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            Self::Tls(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
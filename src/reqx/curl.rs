@@ -0,0 +1,471 @@
+use std::{collections::VecDeque, fmt::Display, iter::Peekable, str::Chars};
+
+use http::{header, HeaderName, HeaderValue, Method};
+
+use super::{AwsSigV4, FormPart, ReqxAuth, ReqxData};
+
+#[derive(Debug)]
+pub struct CurlCommand {
+    pub method: Method,
+    pub url: String,
+    pub data: ReqxData,
+    pub proxy: Option<String>,
+    pub insecure: bool,
+    //flags that were recognized but not mapped onto the request
+    pub warnings: Vec<String>,
+}
+#[derive(Debug)]
+pub enum CurlError {
+    NotCurl,
+    UnterminatedQuote,
+    MissingValue(String),
+    MissingUrl,
+    BodyWithForm,
+    InvalidHeader(String),
+    InvalidMethod(String),
+    Io(String, std::io::Error),
+}
+impl Display for CurlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotCurl => write!(f, "command does not start with `curl`"),
+            Self::UnterminatedQuote => write!(f, "unterminated quote in command"),
+            Self::MissingValue(flag) => write!(f, "flag `{flag}` expects a value"),
+            Self::MissingUrl => write!(f, "no url given"),
+            Self::BodyWithForm => write!(f, "-d and -F cannot be used together"),
+            Self::InvalidHeader(header) => write!(f, "invalid header `{header}`"),
+            Self::InvalidMethod(method) => write!(f, "invalid method `{method}`"),
+            Self::Io(path, e) => write!(f, "could not read `{path}`: {e}"),
+        }
+    }
+}
+impl std::error::Error for CurlError {}
+
+//short flags that take a value, so `-XPOST` and `-sX POST` can be split
+const SHORT_WITH_VALUE: &str = "XHdFuxAbceEmortTwKU";
+//flags which take a value but are not mapped, their value is skipped with a warning
+const IGNORED_WITH_VALUE: &[&str] = &[
+    "-b",
+    "--cookie",
+    "-c",
+    "--cookie-jar",
+    "-e",
+    "--referer",
+    "-E",
+    "--cert",
+    "--cacert",
+    "--key",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "-o",
+    "--output",
+    "-r",
+    "--range",
+    "-T",
+    "--upload-file",
+    "-w",
+    "--write-out",
+    "-K",
+    "--config",
+    "--resolve",
+    "--data-urlencode",
+    "--proxy-user",
+    "-U",
+    "--max-redirs",
+    "--retry",
+];
+
+pub fn parse(command: &str) -> Result<CurlCommand, CurlError> {
+    let words = split(command)?;
+    let mut args = words.into_iter();
+    if args.next().as_deref() != Some("curl") {
+        return Err(CurlError::NotCurl);
+    }
+    let mut parser = Parser::default();
+    let mut pending: VecDeque<String> = args.collect();
+    while let Some(arg) = pending.pop_front() {
+        if arg.len() > 2 && arg.starts_with('-') && !arg.starts_with("--") {
+            for (idx, short) in expand_short(&arg).into_iter().enumerate() {
+                pending.insert(idx, short);
+            }
+            continue;
+        }
+        parser.arg(&arg, &mut pending)?;
+    }
+    parser.finish()
+}
+
+#[derive(Default)]
+struct Parser {
+    method: Option<String>,
+    url: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Vec<String>,
+    parts: Vec<FormPart>,
+    auth: Option<ReqxAuth>,
+    digest: bool,
+    aws_sigv4: Option<String>,
+    proxy: Option<String>,
+    insecure: bool,
+    head: bool,
+    warnings: Vec<String>,
+}
+impl Parser {
+    fn arg(&mut self, arg: &str, rest: &mut VecDeque<String>) -> Result<(), CurlError> {
+        let mut value = || {
+            rest.pop_front()
+                .ok_or_else(|| CurlError::MissingValue(arg.into()))
+        };
+        match arg {
+            "-X" | "--request" => self.method = Some(value()?),
+            "-H" | "--header" => {
+                let header = value()?;
+                let Some((name, val)) = header.split_once(':') else {
+                    return Err(CurlError::InvalidHeader(header));
+                };
+                self.headers
+                    .push((name.trim().to_string(), val.trim().to_string()));
+            }
+            "-d" | "--data" | "--data-ascii" => {
+                let data = value()?;
+                self.body.push(match data.strip_prefix('@') {
+                    Some(path) => read(path)?.replace(['\r', '\n'], ""),
+                    None => data,
+                });
+            }
+            "--data-binary" => {
+                let data = value()?;
+                self.body.push(match data.strip_prefix('@') {
+                    Some(path) => read(path)?,
+                    None => data,
+                });
+            }
+            "--data-raw" => {
+                let data = value()?;
+                self.body.push(data);
+            }
+            "-F" | "--form" => {
+                let field = value()?;
+                let Some((name, val)) = field.split_once('=') else {
+                    return Err(CurlError::MissingValue(arg.into()));
+                };
+                //`name=@file;type=...;filename=...`, `name=<file` sends the file as a text part
+                let mut attrs = val.split(';');
+                let content = attrs.next().unwrap_or_default();
+                let mut part = if let Some(path) = content.strip_prefix('@') {
+                    FormPart::file(name, path)
+                } else if let Some(path) = content.strip_prefix('<') {
+                    FormPart::text(name, read(path)?)
+                } else {
+                    FormPart::text(name, content)
+                };
+                for attr in attrs {
+                    match attr.trim().split_once('=') {
+                        Some(("type", ty)) => part = part.content_type(ty),
+                        Some(("filename", filename)) => {
+                            part = part.filename(filename.trim_matches('"'))
+                        }
+                        _ => self
+                            .warnings
+                            .push(format!("form part attribute `{attr}` ignored")),
+                    }
+                }
+                self.parts.push(part);
+            }
+            "--form-string" => {
+                let field = value()?;
+                let Some((name, val)) = field.split_once('=') else {
+                    return Err(CurlError::MissingValue(arg.into()));
+                };
+                self.parts.push(FormPart::text(name, val));
+            }
+            "-u" | "--user" => {
+                let user = value()?;
+                self.auth = Some(match user.split_once(':') {
+                    Some((username, password)) => ReqxAuth::Basic {
+                        username: username.to_string(),
+                        password: Some(password.to_string()),
+                    },
+                    None => ReqxAuth::Basic {
+                        username: user,
+                        password: None,
+                    },
+                });
+            }
+            "--oauth2-bearer" => self.auth = Some(ReqxAuth::Bearer(value()?)),
+            "--digest" => self.digest = true,
+            "--basic" => self.digest = false,
+            "--aws-sigv4" => self.aws_sigv4 = Some(value()?),
+            "-x" | "--proxy" => self.proxy = Some(value()?),
+            "-A" | "--user-agent" => {
+                let agent = value()?;
+                self.headers.push(("User-Agent".into(), agent));
+            }
+            "--url" => self.url = Some(value()?),
+            "-k" | "--insecure" => self.insecure = true,
+            //responses are not decompressed, asking for an encoding would leave them unreadable
+            "--compressed" => self.warnings.push(String::from(
+                "`--compressed` ignored, responses are not decompressed",
+            )),
+            "-I" | "--head" => self.head = true,
+            flag if IGNORED_WITH_VALUE.contains(&flag) => {
+                let skipped = value()?;
+                self.warnings
+                    .push(format!("unsupported flag `{flag} {skipped}` ignored"));
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                self.warnings
+                    .push(format!("unsupported flag `{flag}` ignored"));
+            }
+            url => {
+                if self.url.is_some() {
+                    self.warnings
+                        .push(format!("extra url `{url}` ignored, only one is supported"));
+                } else {
+                    self.url = Some(url.to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<CurlCommand, CurlError> {
+        let url = self.url.ok_or(CurlError::MissingUrl)?;
+        if !self.body.is_empty() && !self.parts.is_empty() {
+            return Err(CurlError::BodyWithForm);
+        }
+        let method = match self.method {
+            Some(method) => Method::from_bytes(method.to_uppercase().as_bytes())
+                .map_err(|_| CurlError::InvalidMethod(method))?,
+            None if self.head => Method::HEAD,
+            None if !self.body.is_empty() || !self.parts.is_empty() => Method::POST,
+            None => Method::GET,
+        };
+        let mut auth = self.auth.unwrap_or(ReqxAuth::None);
+        //-u only carries the credentials, --digest and --aws-sigv4 say how they are used
+        if let ReqxAuth::Basic { username, password } = &auth {
+            let password = password.clone().unwrap_or_default();
+            if let Some(provider) = &self.aws_sigv4 {
+                //`aws:amz:<region>:<service>`, region and service default to the host name parts
+                let mut parts = provider.split(':').skip(2);
+                let host = url::Url::parse(&url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(str::to_string))
+                    .unwrap_or_default();
+                let mut labels = host.split('.');
+                let host_service = labels.next().unwrap_or_default().to_string();
+                let host_region = labels.next().unwrap_or_default().to_string();
+                auth = ReqxAuth::AwsSigV4(AwsSigV4 {
+                    access_key: username.clone(),
+                    secret_key: password,
+                    session_token: None,
+                    region: parts.next().map_or(host_region, str::to_string),
+                    service: parts.next().map_or(host_service, str::to_string),
+                });
+            } else if self.digest {
+                auth = ReqxAuth::Digest {
+                    password,
+                    username: username.clone(),
+                };
+            }
+        }
+        let mut data = ReqxData {
+            authentication: auth,
+            ..Default::default()
+        };
+        for (name, value) in self.headers {
+            let key = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| CurlError::InvalidHeader(name.clone()))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| CurlError::InvalidHeader(name.clone()))?;
+            data.headers.append(key, value);
+        }
+        if !self.body.is_empty() {
+            if !data.headers.contains_key(header::CONTENT_TYPE) {
+                data.headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                );
+            }
+            data.body = Some(self.body.join("&").into());
+        }
+        if !self.parts.is_empty() {
+            data.multipart = Some(self.parts);
+        }
+        Ok(CurlCommand {
+            method,
+            url,
+            data,
+            proxy: self.proxy,
+            insecure: self.insecure,
+            warnings: self.warnings,
+        })
+    }
+}
+fn read(path: &str) -> Result<String, CurlError> {
+    std::fs::read_to_string(path).map_err(|e| CurlError::Io(path.into(), e))
+}
+//turns `-sSXPOST` into `-s -S -X POST`
+fn expand_short(arg: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for (idx, c) in arg.char_indices().skip(1) {
+        out.push(format!("-{c}"));
+        if SHORT_WITH_VALUE.contains(c) {
+            let value = &arg[idx + c.len_utf8()..];
+            if !value.is_empty() {
+                out.push(value.to_string());
+            }
+            break;
+        }
+    }
+    out
+}
+//posix shell style word splitting, handling quotes, escapes and line continuations, plus
+//bash `$'...'` quoting that browsers use in "copy as cURL"
+fn split(command: &str) -> Result<Vec<String>, CurlError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(CurlError::UnterminatedQuote),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(CurlError::UnterminatedQuote),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(CurlError::UnterminatedQuote),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                in_word = true;
+                word.push_str(&ansi_c(&mut chars)?);
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(c) => {
+                    in_word = true;
+                    word.push(c);
+                }
+                None => {}
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+//the rest of a `$'...'` word up to its closing quote with the escapes decoded; octal and
+//hex escapes are bytes, so a utf8 character can be spelled as several of them
+fn ansi_c(chars: &mut Peekable<Chars>) -> Result<String, CurlError> {
+    fn push(bytes: &mut Vec<u8>, c: char) {
+        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    let mut bytes = Vec::new();
+    loop {
+        let c = chars.next().ok_or(CurlError::UnterminatedQuote)?;
+        if c == '\'' {
+            break;
+        }
+        if c != '\\' {
+            push(&mut bytes, c);
+            continue;
+        }
+        let escape = chars.next().ok_or(CurlError::UnterminatedQuote)?;
+        match escape {
+            'a' => bytes.push(0x07),
+            'b' => bytes.push(0x08),
+            'e' | 'E' => bytes.push(0x1b),
+            'f' => bytes.push(0x0c),
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            'v' => bytes.push(0x0b),
+            '\\' | '\'' | '"' | '?' => push(&mut bytes, escape),
+            'c' => match chars.next() {
+                Some(c) if c.is_ascii() => bytes.push(c.to_ascii_uppercase() as u8 ^ 0x40),
+                _ => return Err(CurlError::UnterminatedQuote),
+            },
+            '0'..='7' => {
+                let mut value = escape.to_digit(8).unwrap_or_default();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            value = value * 8 + digit;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            'x' | 'u' | 'U' => {
+                let max = match escape {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let mut value = 0;
+                let mut digits = 0;
+                while digits < max {
+                    let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) else {
+                        break;
+                    };
+                    value = value * 16 + digit;
+                    digits += 1;
+                    chars.next();
+                }
+                match (digits, escape) {
+                    //no digits, bash keeps the escape as it is
+                    (0, _) => {
+                        bytes.push(b'\\');
+                        push(&mut bytes, escape);
+                    }
+                    (_, 'x') => bytes.push(value as u8),
+                    _ => push(&mut bytes, char::from_u32(value).unwrap_or('\u{fffd}')),
+                }
+            }
+            other => {
+                bytes.push(b'\\');
+                push(&mut bytes, other);
+            }
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
@@ -0,0 +1,200 @@
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+//one `Digest ...` challenge out of a WWW-Authenticate header, rfc 7616
+pub struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    session: bool,
+    qop: Option<Qop>,
+}
+#[derive(Clone, Copy)]
+enum Algorithm {
+    Md5,
+    Sha256,
+}
+#[derive(Clone, Copy)]
+enum Qop {
+    Auth,
+    AuthInt,
+}
+impl Algorithm {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Sha256 => "SHA-256",
+        }
+    }
+    fn hash(self, data: &[u8]) -> String {
+        match self {
+            Self::Md5 => hex(&Md5::digest(data)),
+            Self::Sha256 => hex(&Sha256::digest(data)),
+        }
+    }
+}
+impl Qop {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::AuthInt => "auth-int",
+        }
+    }
+}
+impl DigestChallenge {
+    //the first digest challenge with an algorithm we support, the strongest is not searched for
+    pub fn find<'a>(www_authenticate: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        www_authenticate
+            .into_iter()
+            .flat_map(split_challenges)
+            .find_map(|challenge| Self::parse(&challenge))
+    }
+    fn parse(challenge: &str) -> Option<Self> {
+        let (scheme, params) = challenge.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+        let params = auth_params(params);
+        let get = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.clone())
+        };
+        let algorithm = get("algorithm").unwrap_or_else(|| "MD5".into());
+        let (algorithm, session) = match algorithm.to_ascii_uppercase().as_str() {
+            "MD5" => (Algorithm::Md5, false),
+            "MD5-SESS" => (Algorithm::Md5, true),
+            "SHA-256" => (Algorithm::Sha256, false),
+            "SHA-256-SESS" => (Algorithm::Sha256, true),
+            _ => return None,
+        };
+        //auth is preferred, auth-int is only used when it is the only one offered
+        let qop = get("qop").map(|qop| {
+            let offered: Vec<_> = qop.split(',').map(|q| q.trim().to_lowercase()).collect();
+            if offered.iter().any(|q| q == "auth") || !offered.iter().any(|q| q == "auth-int") {
+                Qop::Auth
+            } else {
+                Qop::AuthInt
+            }
+        });
+        Some(Self {
+            realm: get("realm").unwrap_or_default(),
+            nonce: get("nonce")?,
+            opaque: get("opaque"),
+            algorithm,
+            session,
+            qop,
+        })
+    }
+    //value of the Authorization header answering this challenge, `uri` is the request target
+    pub fn respond(
+        &self,
+        username: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        body: &[u8],
+        cnonce: &str,
+    ) -> String {
+        let h = |data: String| self.algorithm.hash(data.as_bytes());
+        let nc = "00000001";
+        let mut ha1 = h(format!("{username}:{}:{password}", self.realm));
+        if self.session {
+            ha1 = h(format!("{ha1}:{}:{cnonce}", self.nonce));
+        }
+        let ha2 = match self.qop {
+            Some(Qop::AuthInt) => h(format!("{method}:{uri}:{}", self.algorithm.hash(body))),
+            _ => h(format!("{method}:{uri}")),
+        };
+        let response = match self.qop {
+            Some(qop) => h(format!(
+                "{ha1}:{}:{nc}:{cnonce}:{}:{ha2}",
+                self.nonce,
+                qop.name()
+            )),
+            None => h(format!("{ha1}:{}:{ha2}", self.nonce)),
+        };
+        let algorithm = match self.session {
+            true => format!("{}-sess", self.algorithm.name()),
+            false => self.algorithm.name().to_string(),
+        };
+        let mut out = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={algorithm}, response=\"{response}\"",
+            quote(username),
+            quote(&self.realm),
+            quote(&self.nonce),
+            quote(uri),
+        );
+        if let Some(qop) = self.qop {
+            let qop = qop.name();
+            out.push_str(&format!(", qop={qop}, nc={nc}, cnonce=\"{cnonce}\""));
+        }
+        if let Some(opaque) = &self.opaque {
+            out.push_str(&format!(", opaque=\"{}\"", quote(opaque)));
+        }
+        out
+    }
+}
+//a header can hold several challenges, `Basic realm="a", Digest realm="b", nonce="c"`,
+//a new one starts at a token that is not followed by `=`
+fn split_challenges(value: &str) -> Vec<String> {
+    let mut challenges: Vec<String> = Vec::new();
+    for part in split_params(value) {
+        let part = part.trim();
+        let starts_scheme = part
+            .split_once(' ')
+            .is_some_and(|(scheme, _)| !scheme.contains('='))
+            || (!part.contains('=') && !part.is_empty());
+        match challenges.last_mut() {
+            Some(last) if !starts_scheme => {
+                last.push_str(", ");
+                last.push_str(part);
+            }
+            _ => challenges.push(part.to_string()),
+        }
+    }
+    challenges
+}
+//splits on commas outside of quoted strings
+fn split_params(value: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    parts.retain(|p| !p.trim().is_empty());
+    parts
+}
+fn auth_params(params: &str) -> Vec<(String, String)> {
+    split_params(params)
+        .iter()
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+                None => value.to_string(),
+            };
+            (key.trim().to_string(), value)
+        })
+        .collect()
+}
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
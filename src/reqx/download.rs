@@ -0,0 +1,185 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::io::AsyncWriteExt;
+
+use super::{body::Head, ReqxCancel, ReqxError, ReqxResponse};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+//where Reqx::download writes a body, with the receiving ends for the ui
+#[derive(Debug, Clone)]
+pub struct ReqxDownload {
+    pub path: PathBuf,
+    //continues a partial file with a Range request, the file is written from the start
+    //again when the server sends the whole body anyway; without it a cancelled download
+    //removes what it wrote
+    pub resume: bool,
+    progress: Option<mpsc::Sender<DownloadProgress>>,
+    cancel: ReqxCancel,
+    //set while a body is being written to the file
+    writing: Arc<AtomicBool>,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadProgress {
+    //bytes in the file so far, a resumed download starts with what was already there
+    pub received: u64,
+    //None when the server does not send a length
+    pub total: Option<u64>,
+    //bytes per second over this transfer
+    pub rate: f64,
+    pub done: bool,
+}
+impl DownloadProgress {
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| self.received as f64 / total as f64)
+    }
+}
+impl ReqxDownload {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            resume: false,
+            progress: None,
+            cancel: ReqxCancel::default(),
+            writing: Arc::default(),
+        }
+    }
+    pub fn resumed(mut self) -> Self {
+        self.resume = true;
+        self
+    }
+    //progress comes at most every 100ms and once more when the body is done,
+    //the window loop can poll it with try_recv; a new receiver replaces the last one
+    pub fn progress(&mut self) -> mpsc::Receiver<DownloadProgress> {
+        let (sender, receiver) = mpsc::channel();
+        self.progress = Some(sender);
+        receiver
+    }
+    pub fn canceller(&self) -> ReqxCancel {
+        self.cancel.clone()
+    }
+    //the size of the partial file to resume from, 0 when starting over
+    pub(crate) async fn offset(&self) -> Result<u64, ReqxError> {
+        if !self.resume {
+            return Ok(0);
+        }
+        match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+    //only a 2xx body goes to the file, anything else is read into the response like a send;
+    //`timeout` limits the wait for each chunk, a large body can take as long as it needs
+    pub(crate) async fn write(
+        &self,
+        mut head: Head,
+        offset: u64,
+        timeout: Option<Duration>,
+    ) -> Result<ReqxResponse, ReqxError> {
+        let status = head.response.status;
+        let range = head
+            .response
+            .header("content-range")
+            .and_then(content_range);
+        //asked for the rest of a file that is already complete
+        if status == 416 && offset > 0 && range.is_some_and(|(_, total)| total == Some(offset)) {
+            self.report(offset, Some(offset), 0.0, true);
+            return head.read().await;
+        }
+        if !(200..300).contains(&status) {
+            return head.read().await;
+        }
+        let resumed = status == 206 && offset > 0;
+        let (mut received, total) = match (resumed, range) {
+            (true, Some((Some(start), total))) if start == offset => (offset, total),
+            (true, _) => {
+                return Err(ReqxError::InvalidHeader(String::from("content-range")));
+            }
+            (false, _) => (0, None),
+        };
+        let total = total.or_else(|| {
+            head.response
+                .header("content-length")
+                .and_then(|length| length.trim().parse::<u64>().ok())
+                .map(|length| received + length)
+        });
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(&self.path)
+            .await?;
+        self.writing.store(true, Ordering::SeqCst);
+        let (started, first) = (Instant::now(), received);
+        let mut reported = started;
+        let rate = |received: u64| {
+            let secs = started.elapsed().as_secs_f64();
+            match secs > 0.0 {
+                true => (received - first) as f64 / secs,
+                false => 0.0,
+            }
+        };
+        self.report(received, total, 0.0, false);
+        loop {
+            let chunk = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, head.body.chunk())
+                    .await
+                    .map_err(|_| ReqxError::Timeout(timeout))??,
+                None => head.body.chunk().await?,
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            file.write_all(&chunk).await?;
+            received += chunk.len() as u64;
+            if reported.elapsed() >= PROGRESS_INTERVAL {
+                reported = Instant::now();
+                self.report(received, total, rate(received), false);
+            }
+        }
+        file.flush().await?;
+        self.writing.store(false, Ordering::SeqCst);
+        self.report(received, total, rate(received), true);
+        Ok(head.finish())
+    }
+    pub(crate) fn cancel(&self) -> &ReqxCancel {
+        &self.cancel
+    }
+    //after a cancel, a file that cannot be resumed is only half a body
+    pub(crate) async fn discard(&self) {
+        if self.writing.swap(false, Ordering::SeqCst) && !self.resume {
+            let _ = tokio::fs::remove_file(&self.path).await;
+        }
+    }
+    fn report(&self, received: u64, total: Option<u64>, rate: f64, done: bool) {
+        if let Some(progress) = &self.progress {
+            //nobody listening is not an error, the download goes on
+            let _ = progress.send(DownloadProgress {
+                received,
+                total,
+                rate,
+                done,
+            });
+        }
+    }
+}
+//`bytes 100-199/1000` or `bytes */1000`, the start and the full length when known
+fn content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let start = match range.trim() {
+        "*" => None,
+        range => Some(range.split_once('-')?.0.trim().parse().ok()?),
+    };
+    Some((start, total.trim().parse().ok()))
+}
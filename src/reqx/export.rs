@@ -0,0 +1,337 @@
+use std::fmt::Write;
+
+use http::{HeaderValue, Method};
+
+use super::{ApiKeyLocation, OAuthError, PartValue, ReqxAuth, ReqxData, ReqxError};
+use crate::environment::{mask_header, mask_url};
+
+//secrets are masked unless `reveal`, like har::export
+pub fn curl(
+    method: &Method,
+    url: &str,
+    data: &ReqxData,
+    reveal: bool,
+) -> Result<String, ReqxError> {
+    let (url, auth) = shown(url, &data.authentication, reveal);
+    let mut head = vec![String::from("curl")];
+    if *method != Method::GET {
+        head.push(format!("-X {}", shell_quote(method.as_str())));
+    }
+    head.push(shell_quote(&url));
+    let mut args = vec![head.join(" ")];
+    for (name, value) in &data.headers {
+        let header = format!("{name}: {}", header_value(name.as_str(), value, reveal));
+        args.push(format!("-H {}", shell_quote(&header)));
+    }
+    match &auth {
+        ReqxAuth::None => {}
+        ReqxAuth::Basic { username, password } => {
+            let user = format!("{username}:{}", password.as_deref().unwrap_or_default());
+            args.push(format!("-u {}", shell_quote(&user)));
+        }
+        ReqxAuth::Bearer(token) => args.push(format!("--oauth2-bearer {}", shell_quote(token))),
+        ReqxAuth::OAuth2(_) => return Err(OAuthError::TokenRequired.into()),
+        ReqxAuth::Digest { username, password } => {
+            let user = format!("{username}:{password}");
+            args.push(format!("--digest -u {}", shell_quote(&user)));
+        }
+        ReqxAuth::ApiKey {
+            name,
+            value,
+            location: ApiKeyLocation::Header,
+        } => args.push(format!("-H {}", shell_quote(&format!("{name}: {value}")))),
+        ReqxAuth::ApiKey { .. } => {}
+        ReqxAuth::AwsSigV4(config) => {
+            let provider = format!("aws:amz:{}:{}", config.region, config.service);
+            let user = format!("{}:{}", config.access_key, config.secret_key);
+            args.push(format!("--aws-sigv4 {}", shell_quote(&provider)));
+            args.push(format!("-u {}", shell_quote(&user)));
+            if let Some(token) = &config.session_token {
+                let header = format!("x-amz-security-token: {token}");
+                args.push(format!("-H {}", shell_quote(&header)));
+            }
+        }
+    }
+    if let Some(body) = body_bytes(data)? {
+        args.push(format!("--data-binary {}", shell_quote_bytes(body)));
+    }
+    if let Some(form) = form_fields(data) {
+        for (key, value) in form {
+            let field = encode_form(&[(key, value)]);
+            args.push(format!("--data-raw {}", shell_quote(&field)));
+        }
+    }
+    for part in data.multipart.iter().flatten() {
+        let mut field = match &part.value {
+            //plain text parts use --form-string so `@`, `<` and `;` in them stay literal
+            PartValue::Text(text) if part.filename.is_none() && part.content_type.is_none() => {
+                let field = format!("{}={text}", part.name);
+                args.push(format!("--form-string {}", shell_quote(&field)));
+                continue;
+            }
+            PartValue::Text(text) => format!("{}={text}", part.name),
+            PartValue::File(path) => format!("{}=@{}", part.name, path.display()),
+        };
+        if let Some(content_type) = &part.content_type {
+            field.push_str(&format!(";type={content_type}"));
+        }
+        if let Some(filename) = &part.filename {
+            field.push_str(&format!(";filename={filename}"));
+        }
+        args.push(format!("-F {}", shell_quote(&field)));
+    }
+    Ok(args.join(" \\\n  "))
+}
+pub fn httpie(
+    method: &Method,
+    url: &str,
+    data: &ReqxData,
+    reveal: bool,
+) -> Result<String, ReqxError> {
+    let (url, auth) = shown(url, &data.authentication, reveal);
+    let mut args = vec![String::from(if data.multipart.is_some() {
+        "http --multipart"
+    } else if data.form.is_some() {
+        "http --form"
+    } else {
+        "http"
+    })];
+    match &auth {
+        ReqxAuth::None => {}
+        ReqxAuth::Basic { username, password } => {
+            let user = format!("{username}:{}", password.as_deref().unwrap_or_default());
+            args.push(format!("--auth {}", shell_quote(&user)));
+        }
+        ReqxAuth::Bearer(token) => {
+            args.push(format!("--auth-type bearer --auth {}", shell_quote(token)))
+        }
+        ReqxAuth::OAuth2(_) => return Err(OAuthError::TokenRequired.into()),
+        ReqxAuth::Digest { username, password } => {
+            let user = format!("{username}:{password}");
+            args.push(format!("--auth-type digest --auth {}", shell_quote(&user)));
+        }
+        ReqxAuth::ApiKey { .. } => {}
+        //only available as a plugin
+        ReqxAuth::AwsSigV4(_) => return Err(ReqxError::SendOnlyAuth),
+    }
+    if let Some(body) = body_bytes(data)? {
+        args.push(format!("--raw {}", shell_quote_bytes(body)));
+    }
+    let head = format!("{} {}", shell_quote(method.as_str()), shell_quote(&url));
+    args.push(head);
+    if let ReqxAuth::ApiKey {
+        name,
+        value,
+        location: ApiKeyLocation::Header,
+    } = &auth
+    {
+        args.push(shell_quote(&format!("{name}:{value}")));
+    }
+    for (name, value) in &data.headers {
+        let value = header_value(name.as_str(), value, reveal);
+        //httpie drops headers given as `Name:`, an empty value is written `Name;`
+        let item = if value.is_empty() {
+            format!("{name};")
+        } else {
+            format!("{name}:{value}")
+        };
+        args.push(shell_quote(&item));
+    }
+    if let Some(form) = form_fields(data) {
+        for (key, value) in form {
+            args.push(shell_quote(&format!("{key}={value}")));
+        }
+    }
+    //httpie has no way to rename an uploaded file, a filename override is dropped
+    for part in data.multipart.iter().flatten() {
+        let mut item = match &part.value {
+            PartValue::Text(text) => format!("{}={text}", part.name),
+            PartValue::File(path) => format!("{}@{}", part.name, path.display()),
+        };
+        if let (PartValue::File(_), Some(content_type)) = (&part.value, &part.content_type) {
+            item.push_str(&format!(";type={content_type}"));
+        }
+        args.push(shell_quote(&item));
+    }
+    Ok(args.join(" \\\n  "))
+}
+pub fn reqwest(
+    method: &Method,
+    url: &str,
+    data: &ReqxData,
+    reveal: bool,
+) -> Result<String, ReqxError> {
+    let (url, auth) = shown(url, &data.authentication, reveal);
+    let method = match *method {
+        Method::GET => String::from("reqwest::Method::GET"),
+        Method::POST => String::from("reqwest::Method::POST"),
+        Method::PUT => String::from("reqwest::Method::PUT"),
+        Method::PATCH => String::from("reqwest::Method::PATCH"),
+        Method::DELETE => String::from("reqwest::Method::DELETE"),
+        Method::HEAD => String::from("reqwest::Method::HEAD"),
+        Method::OPTIONS => String::from("reqwest::Method::OPTIONS"),
+        ref other => format!(
+            "reqwest::Method::from_bytes(b{:?}).unwrap()",
+            other.as_str()
+        ),
+    };
+    let mut out = String::new();
+    out.push_str("#[tokio::main]\n");
+    out.push_str("async fn main() -> Result<(), reqwest::Error> {\n");
+    out.push_str("    let response = reqwest::Client::new()\n");
+    let _ = writeln!(out, "        .request({method}, {url:?})");
+    for (name, value) in &data.headers {
+        let value = header_value(name.as_str(), value, reveal);
+        let _ = writeln!(out, "        .header({:?}, {value:?})", name.as_str());
+    }
+    match &auth {
+        ReqxAuth::None => {}
+        ReqxAuth::Basic { username, password } => {
+            let password = match password {
+                Some(password) => format!("Some({password:?})"),
+                None => String::from("None::<&str>"),
+            };
+            let _ = writeln!(out, "        .basic_auth({username:?}, {password})");
+        }
+        ReqxAuth::Bearer(token) => {
+            let _ = writeln!(out, "        .bearer_auth({token:?})");
+        }
+        ReqxAuth::OAuth2(_) => return Err(OAuthError::TokenRequired.into()),
+        ReqxAuth::ApiKey {
+            name,
+            value,
+            location: ApiKeyLocation::Header,
+        } => {
+            let _ = writeln!(out, "        .header({name:?}, {value:?})");
+        }
+        ReqxAuth::ApiKey { .. } => {}
+        ReqxAuth::Digest { .. } | ReqxAuth::AwsSigV4(_) => return Err(ReqxError::SendOnlyAuth),
+    }
+    if let Some(body) = body_bytes(data)? {
+        match std::str::from_utf8(body) {
+            Ok(text) => {
+                let _ = writeln!(out, "        .body({text:?})");
+            }
+            Err(_) => {
+                let _ = writeln!(out, "        .body(vec!{body:?})");
+            }
+        }
+    }
+    if let Some(form) = form_fields(data) {
+        let _ = writeln!(out, "        .form(&{form:?})");
+    }
+    if let Some(parts) = &data.multipart {
+        out.push_str("        .multipart(\n");
+        out.push_str("            reqwest::multipart::Form::new()\n");
+        for part in parts {
+            let name = &part.name;
+            let mut value = match &part.value {
+                PartValue::Text(text) => format!("reqwest::multipart::Part::text({text:?})"),
+                PartValue::File(path) => format!(
+                    "reqwest::multipart::Part::bytes(std::fs::read({:?}).unwrap())",
+                    path.display().to_string()
+                ),
+            };
+            let (filename, content_type) = part.resolved();
+            if let Some(filename) = filename {
+                value.push_str(&format!(".file_name({filename:?})"));
+            }
+            if let Some(content_type) = content_type {
+                value.push_str(&format!(".mime_str({content_type:?})?"));
+            }
+            let _ = writeln!(out, "                .part({name:?}, {value})");
+        }
+        out.push_str("        )\n");
+    }
+    out.push_str("        .send()\n");
+    out.push_str("        .await?;\n");
+    out.push_str("    println!(\"{}\", response.status());\n");
+    out.push_str("    println!(\"{}\", response.text().await?);\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n");
+    Ok(out)
+}
+//the url and auth an export writes out, credentials masked unless `reveal`
+fn shown(url: &str, auth: &ReqxAuth, reveal: bool) -> (String, ReqxAuth) {
+    match reveal {
+        true => (api_key_url(url, auth), auth.clone()),
+        false => {
+            let auth = auth.masked();
+            (mask_url(&api_key_url(url, &auth)), auth)
+        }
+    }
+}
+fn header_value(name: &str, value: &HeaderValue, reveal: bool) -> String {
+    let value = String::from_utf8_lossy(value.as_bytes());
+    match reveal {
+        true => value.into_owned(),
+        false => mask_header(name, &value),
+    }
+}
+//query api keys are written into the url itself, the way reqwest's .query() sends them
+pub(crate) fn api_key_url(url: &str, auth: &ReqxAuth) -> String {
+    let ReqxAuth::ApiKey {
+        name,
+        value,
+        location: ApiKeyLocation::Query,
+    } = auth
+    else {
+        return url.to_string();
+    };
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.query_pairs_mut().append_pair(name, value);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+fn body_bytes(data: &ReqxData) -> Result<Option<&[u8]>, ReqxError> {
+    match &data.body {
+        Some(body) => body.as_bytes().map(Some).ok_or(ReqxError::StreamingBody),
+        None => Ok(None),
+    }
+}
+fn form_fields(data: &ReqxData) -> Option<Vec<(&str, &str)>> {
+    let form = data.form.as_ref()?;
+    Some(form.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect())
+}
+fn encode_form(fields: &[(&str, &str)]) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(fields)
+        .finish()
+}
+pub fn shell_quote(s: &str) -> String {
+    let safe = !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"@%+=:,./_-".contains(&b));
+    if safe {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+//bodies that are not printable text use bash ANSI-C quoting so every byte survives
+fn shell_quote_bytes(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if !text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\t' | '\r'))
+        {
+            return shell_quote(text);
+        }
+    }
+    let mut out = String::from("$'");
+    for &b in bytes {
+        match b {
+            b'\'' => out.push_str(r"\'"),
+            b'\\' => out.push_str(r"\\"),
+            0x20..=0x7e => out.push(b as char),
+            _ => {
+                let _ = write!(out, "\\x{b:02x}");
+            }
+        }
+    }
+    out.push('\'');
+    out
+}
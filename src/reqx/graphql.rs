@@ -0,0 +1,1019 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use graphql_parser::{
+    query::{
+        self as ast, Definition, Directive, FragmentDefinition, OperationDefinition, Selection,
+        SelectionSet, TypeCondition, VariableDefinition,
+    },
+    Pos,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::ReqxResponse;
+
+//the usual introspection query, seven levels of ofType cover wrappings like [[Int!]!]!
+pub const INTROSPECTION_QUERY: &str = r#"query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { name description type { ...TypeRef } defaultValue }
+        type { ...TypeRef }
+        isDeprecated
+      }
+      inputFields { name description type { ...TypeRef } defaultValue }
+      interfaces { ...TypeRef }
+      enumValues(includeDeprecated: true) { name }
+      possibleTypes { ...TypeRef }
+    }
+  }
+}
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name
+    ofType { kind name ofType { kind name ofType { kind name } } } } } } }
+}"#;
+
+//sent as the json POST every server understands: {"query", "variables", "operationName"}
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlQuery {
+    pub query: String,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "variables_serde"
+    )]
+    pub variables: Option<Value>,
+    //picks the operation to run when the document has several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_name: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlSchema {
+    pub query_type: String,
+    pub mutation_type: Option<String>,
+    pub subscription_type: Option<String>,
+    pub types: BTreeMap<String, GraphQlType>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlType {
+    pub name: String,
+    pub kind: TypeKind,
+    pub description: Option<String>,
+    //objects and interfaces
+    pub fields: Vec<GraphQlField>,
+    //input objects
+    pub input_fields: Vec<GraphQlArgument>,
+    pub enum_values: Vec<String>,
+    //the objects a union or interface can be
+    pub possible_types: Vec<String>,
+    pub interfaces: Vec<String>,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TypeKind {
+    Scalar,
+    Object,
+    Interface,
+    Union,
+    Enum,
+    InputObject,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlField {
+    pub name: String,
+    pub description: Option<String>,
+    pub args: Vec<GraphQlArgument>,
+    pub ty: TypeRef,
+    pub deprecated: bool,
+}
+//an argument of a field or a field of an input object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlArgument {
+    pub name: String,
+    pub description: Option<String>,
+    pub ty: TypeRef,
+    //in graphql syntax, as the server reports it
+    pub default_value: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypeRef {
+    Named(String),
+    List(Box<TypeRef>),
+    NonNull(Box<TypeRef>),
+}
+//something wrong with a query found before sending it, with the line and column when known
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQlIssue {
+    pub message: String,
+    pub position: Option<(usize, usize)>,
+}
+//schemas by endpoint url, shared by every introspection of a Reqx
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCache(Arc<Mutex<HashMap<String, Arc<GraphQlSchema>>>>);
+#[derive(Debug)]
+pub enum GraphQlError {
+    //the introspection query was answered with neither a schema nor graphql errors
+    Status(u16),
+    //errors the server sent instead of the schema, introspection may be turned off
+    Server(Vec<String>),
+    InvalidSchema(String),
+}
+impl Display for GraphQlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Status(status) => write!(f, "introspection returned {status}"),
+            Self::Server(errors) => write!(f, "introspection failed: {}", errors.join("; ")),
+            Self::InvalidSchema(e) => write!(f, "invalid introspection result: {e}"),
+        }
+    }
+}
+impl std::error::Error for GraphQlError {}
+impl Display for TypeRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Named(name) => write!(f, "{name}"),
+            Self::List(of) => write!(f, "[{of}]"),
+            Self::NonNull(of) => write!(f, "{of}!"),
+        }
+    }
+}
+impl Display for GraphQlIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{line}:{column}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+impl GraphQlQuery {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            ..Default::default()
+        }
+    }
+    pub fn introspection() -> Self {
+        Self::new(INTROSPECTION_QUERY).with_operation("IntrospectionQuery")
+    }
+    pub fn with_variables(mut self, variables: Value) -> Self {
+        self.variables = Some(variables);
+        self
+    }
+    pub fn with_operation(mut self, name: impl Into<String>) -> Self {
+        self.operation_name = Some(name.into());
+        self
+    }
+    //null variables and a missing operation name are left out
+    pub fn payload(&self) -> String {
+        let mut payload = serde_json::Map::new();
+        payload.insert("query".into(), Value::String(self.query.clone()));
+        if let Some(variables) = self.variables.as_ref().filter(|v| !v.is_null()) {
+            payload.insert("variables".into(), variables.clone());
+        }
+        if let Some(name) = &self.operation_name {
+            payload.insert("operationName".into(), Value::String(name.clone()));
+        }
+        Value::Object(payload).to_string()
+    }
+}
+//variables are stored as a json string, toml has no null and a collection could not hold
+//`{"after": null}`; values stored before that as plain json are still read
+mod variables_serde {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use serde_json::Value;
+
+    pub fn serialize<S: Serializer>(variables: &Option<Value>, s: S) -> Result<S::Ok, S::Error> {
+        match variables {
+            Some(variables) => s.serialize_some(&variables.to_string()),
+            None => s.serialize_none(),
+        }
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Value>, D::Error> {
+        match Option::<Value>::deserialize(d)? {
+            Some(Value::String(json)) => serde_json::from_str(&json).map_err(D::Error::custom),
+            variables => Ok(variables),
+        }
+    }
+}
+impl TypeRef {
+    //the named type under the list and non-null wrappers
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Named(name) => name,
+            Self::List(of) | Self::NonNull(of) => of.name(),
+        }
+    }
+    pub fn is_required(&self) -> bool {
+        matches!(self, Self::NonNull(_))
+    }
+}
+impl GraphQlType {
+    pub fn is_leaf(&self) -> bool {
+        matches!(self.kind, TypeKind::Scalar | TypeKind::Enum)
+    }
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self.kind,
+            TypeKind::Scalar | TypeKind::Enum | TypeKind::InputObject
+        )
+    }
+    fn is_composite(&self) -> bool {
+        matches!(
+            self.kind,
+            TypeKind::Object | TypeKind::Interface | TypeKind::Union
+        )
+    }
+}
+impl GraphQlSchema {
+    //the body of an introspection response, `{"data": {"__schema": ...}}`
+    pub fn from_introspection(json: &[u8]) -> Result<Self, GraphQlError> {
+        let response: Introspection =
+            serde_json::from_slice(json).map_err(|e| GraphQlError::InvalidSchema(e.to_string()))?;
+        match response.data {
+            Some(data) => data.schema.try_into(),
+            None if !response.errors.is_empty() => Err(GraphQlError::Server(
+                response.errors.into_iter().map(|e| e.message).collect(),
+            )),
+            None => Err(GraphQlError::InvalidSchema(String::from("no data"))),
+        }
+    }
+    //servers answer a failed query with 200 or 400 depending on the spec version they follow
+    pub fn from_response(response: &ReqxResponse) -> Result<Self, GraphQlError> {
+        match Self::from_introspection(&response.body) {
+            Err(GraphQlError::InvalidSchema(_)) if !(200..300).contains(&response.status) => {
+                Err(GraphQlError::Status(response.status))
+            }
+            result => result,
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<&GraphQlType> {
+        self.types.get(name)
+    }
+    //empty for unknown types and for the ones without fields
+    pub fn fields(&self, type_name: &str) -> &[GraphQlField] {
+        self.get(type_name).map_or(&[], |ty| &ty.fields)
+    }
+    //the fields after following `path` from the type `root`, e.g. "Query" and ["user", "posts"]
+    pub fn fields_at(&self, root: &str, path: &[&str]) -> Option<&[GraphQlField]> {
+        let mut ty = self.get(root)?;
+        for name in path {
+            let field = ty.fields.iter().find(|f| f.name == *name)?;
+            ty = self.get(field.ty.name())?;
+        }
+        Some(&ty.fields)
+    }
+    //checks the query against the schema the way the server would before running it,
+    //and the given variables against the operation that runs
+    pub fn validate(&self, query: &GraphQlQuery) -> Vec<GraphQlIssue> {
+        let document = match ast::parse_query::<&str>(&query.query) {
+            Ok(document) => document,
+            Err(e) => return vec![syntax_issue(&e.to_string())],
+        };
+        let mut validator = Validator {
+            schema: self,
+            fragments: HashMap::new(),
+            issues: Vec::new(),
+        };
+        validator.document(&document, query);
+        validator.issues
+    }
+    fn root(&self, kind: &str) -> Option<&GraphQlType> {
+        let name = match kind {
+            "query" => Some(&self.query_type),
+            "mutation" => self.mutation_type.as_ref(),
+            _ => self.subscription_type.as_ref(),
+        };
+        self.get(name?)
+    }
+    //the objects a value of `ty` can be
+    fn possible(&self, ty: &GraphQlType) -> BTreeSet<String> {
+        match ty.kind {
+            TypeKind::Object => BTreeSet::from([ty.name.clone()]),
+            _ => ty.possible_types.iter().cloned().collect(),
+        }
+    }
+}
+impl SchemaCache {
+    pub fn get(&self, url: &str) -> Option<Arc<GraphQlSchema>> {
+        self.0.lock().ok()?.get(url).cloned()
+    }
+    pub fn insert(&self, url: &str, schema: Arc<GraphQlSchema>) {
+        if let Ok(mut schemas) = self.0.lock() {
+            schemas.insert(url.to_string(), schema);
+        }
+    }
+    pub fn remove(&self, url: &str) {
+        if let Ok(mut schemas) = self.0.lock() {
+            schemas.remove(url);
+        }
+    }
+    pub fn clear(&self) {
+        if let Ok(mut schemas) = self.0.lock() {
+            schemas.clear();
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Introspection {
+    data: Option<IntrospectionData>,
+    #[serde(default)]
+    errors: Vec<ServerError>,
+}
+#[derive(Deserialize)]
+struct ServerError {
+    message: String,
+}
+#[derive(Deserialize)]
+struct IntrospectionData {
+    #[serde(rename = "__schema")]
+    schema: RawSchema,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSchema {
+    query_type: RawName,
+    mutation_type: Option<RawName>,
+    subscription_type: Option<RawName>,
+    types: Vec<RawType>,
+}
+#[derive(Deserialize)]
+struct RawName {
+    name: String,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawType {
+    kind: TypeKind,
+    name: String,
+    description: Option<String>,
+    fields: Option<Vec<RawField>>,
+    input_fields: Option<Vec<RawArgument>>,
+    interfaces: Option<Vec<RawTypeRef>>,
+    enum_values: Option<Vec<RawName>>,
+    possible_types: Option<Vec<RawTypeRef>>,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawField {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    args: Vec<RawArgument>,
+    #[serde(rename = "type")]
+    ty: RawTypeRef,
+    #[serde(default)]
+    is_deprecated: bool,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawArgument {
+    name: String,
+    description: Option<String>,
+    #[serde(rename = "type")]
+    ty: RawTypeRef,
+    default_value: Option<String>,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTypeRef {
+    kind: String,
+    name: Option<String>,
+    of_type: Option<Box<RawTypeRef>>,
+}
+impl TryFrom<RawSchema> for GraphQlSchema {
+    type Error = GraphQlError;
+    fn try_from(raw: RawSchema) -> Result<Self, Self::Error> {
+        let mut types = BTreeMap::new();
+        for ty in raw.types {
+            let fields = ty
+                .fields
+                .unwrap_or_default()
+                .into_iter()
+                .map(|field| {
+                    Ok(GraphQlField {
+                        args: arguments(field.args)?,
+                        ty: field.ty.try_into()?,
+                        name: field.name,
+                        description: field.description,
+                        deprecated: field.is_deprecated,
+                    })
+                })
+                .collect::<Result<_, GraphQlError>>()?;
+            let names = |refs: Option<Vec<RawTypeRef>>| {
+                refs.unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|r| r.name)
+                    .collect()
+            };
+            types.insert(
+                ty.name.clone(),
+                GraphQlType {
+                    fields,
+                    input_fields: arguments(ty.input_fields.unwrap_or_default())?,
+                    enum_values: ty
+                        .enum_values
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|v| v.name)
+                        .collect(),
+                    possible_types: names(ty.possible_types),
+                    interfaces: names(ty.interfaces),
+                    name: ty.name,
+                    kind: ty.kind,
+                    description: ty.description,
+                },
+            );
+        }
+        if !types.contains_key(&raw.query_type.name) {
+            return Err(GraphQlError::InvalidSchema(format!(
+                "query type `{}` is not in the schema",
+                raw.query_type.name
+            )));
+        }
+        Ok(Self {
+            query_type: raw.query_type.name,
+            mutation_type: raw.mutation_type.map(|t| t.name),
+            subscription_type: raw.subscription_type.map(|t| t.name),
+            types,
+        })
+    }
+}
+impl TryFrom<RawTypeRef> for TypeRef {
+    type Error = GraphQlError;
+    fn try_from(raw: RawTypeRef) -> Result<Self, Self::Error> {
+        let invalid = || GraphQlError::InvalidSchema(format!("incomplete `{}` type", raw.kind));
+        match raw.kind.as_str() {
+            "NON_NULL" | "LIST" => {
+                let of = Box::new((*raw.of_type.ok_or_else(invalid)?).try_into()?);
+                Ok(match raw.kind.as_str() {
+                    "LIST" => Self::List(of),
+                    _ => Self::NonNull(of),
+                })
+            }
+            _ => raw.name.map(Self::Named).ok_or_else(invalid),
+        }
+    }
+}
+fn arguments(raw: Vec<RawArgument>) -> Result<Vec<GraphQlArgument>, GraphQlError> {
+    raw.into_iter()
+        .map(|arg| {
+            Ok(GraphQlArgument {
+                ty: arg.ty.try_into()?,
+                name: arg.name,
+                description: arg.description,
+                default_value: arg.default_value,
+            })
+        })
+        .collect()
+}
+
+type Document<'q> = ast::Document<'q, &'q str>;
+type AstValue<'q> = ast::Value<'q, &'q str>;
+//an operation with the shorthand `{ ... }` form spelled out
+struct Operation<'d, 'q> {
+    kind: &'static str,
+    name: Option<&'q str>,
+    position: Pos,
+    variables: &'d [VariableDefinition<'q, &'q str>],
+    directives: &'d [Directive<'q, &'q str>],
+    selection_set: &'d SelectionSet<'q, &'q str>,
+}
+struct Validator<'d, 'q> {
+    schema: &'d GraphQlSchema,
+    fragments: HashMap<&'q str, &'d FragmentDefinition<'q, &'q str>>,
+    issues: Vec<GraphQlIssue>,
+}
+impl<'d, 'q> Validator<'d, 'q> {
+    fn issue(&mut self, position: Pos, message: String) {
+        self.issues.push(GraphQlIssue {
+            message,
+            position: Some((position.line, position.column)),
+        });
+    }
+    fn document(&mut self, document: &'d Document<'q>, query: &GraphQlQuery) {
+        let mut operations = Vec::new();
+        for definition in &document.definitions {
+            match definition {
+                Definition::Operation(operation) => operations.push(operation_of(operation)),
+                Definition::Fragment(fragment) => {
+                    if self.fragments.insert(fragment.name, fragment).is_some() {
+                        let message = format!("fragment `{}` is defined twice", fragment.name);
+                        self.issue(fragment.position, message);
+                    }
+                }
+            }
+        }
+        if operations.is_empty() {
+            self.issues.push(GraphQlIssue {
+                message: String::from("the document has no operation"),
+                position: None,
+            });
+        }
+        let mut names = BTreeSet::new();
+        for operation in &operations {
+            match operation.name {
+                None if operations.len() > 1 => self.issue(
+                    operation.position,
+                    String::from("an anonymous operation has to be the only one in the document"),
+                ),
+                Some(name) if !names.insert(name) => {
+                    self.issue(
+                        operation.position,
+                        format!("operation `{name}` is defined twice"),
+                    );
+                }
+                _ => {}
+            }
+        }
+        let selected = match query.operation_name.as_deref() {
+            Some(name) => {
+                let found = operations.iter().find(|op| op.name == Some(name));
+                if found.is_none() {
+                    self.issues.push(GraphQlIssue {
+                        message: format!("the document has no operation `{name}`"),
+                        position: None,
+                    });
+                }
+                found
+            }
+            None if operations.len() > 1 => {
+                self.issues.push(GraphQlIssue {
+                    message: String::from(
+                        "the document has several operations, an operation name is required",
+                    ),
+                    position: None,
+                });
+                None
+            }
+            None => operations.first(),
+        };
+        self.cycles();
+        let mut used = BTreeSet::new();
+        for operation in &operations {
+            self.operation(operation, &mut used);
+        }
+        let fragments: Vec<_> = self.fragments.values().copied().collect();
+        for fragment in fragments {
+            let TypeCondition::On(on) = fragment.type_condition;
+            if let Some(ty) = self.condition(on, fragment.position) {
+                self.selection_set(ty, &fragment.selection_set);
+            }
+            if !used.contains(fragment.name) {
+                let message = format!("fragment `{}` is never used", fragment.name);
+                self.issue(fragment.position, message);
+            }
+        }
+        if let Some(operation) = selected {
+            self.given_variables(operation, query.variables.as_ref());
+        }
+    }
+    fn operation(&mut self, operation: &Operation<'d, 'q>, used: &mut BTreeSet<&'q str>) {
+        let Some(root) = self.schema.root(operation.kind) else {
+            let message = format!("the schema has no {} type", operation.kind);
+            return self.issue(operation.position, message);
+        };
+        let mut defined = BTreeSet::new();
+        for variable in operation.variables {
+            if !defined.insert(variable.name) {
+                let message = format!("variable `${}` is defined twice", variable.name);
+                self.issue(variable.position, message);
+            }
+            let ty = type_ref(&variable.var_type);
+            match self.schema.get(ty.name()) {
+                None => self.issue(
+                    variable.position,
+                    format!("unknown type `{ty}` for variable `${}`", variable.name),
+                ),
+                Some(named) if !named.is_input() => self.issue(
+                    variable.position,
+                    format!(
+                        "variable `${}` cannot be of the output type `{ty}`",
+                        variable.name
+                    ),
+                ),
+                _ => {}
+            }
+        }
+        self.selection_set(root, operation.selection_set);
+        //variables used by the operation and every fragment it reaches
+        let mut usages = Vec::new();
+        let mut spread = BTreeSet::new();
+        directive_variables(operation.directives, operation.position, &mut usages);
+        self.variables(operation.selection_set, &mut spread, &mut usages);
+        let name = match operation.name {
+            Some(name) => format!("operation `{name}`"),
+            None => format!("the anonymous {}", operation.kind),
+        };
+        for (variable, position) in &usages {
+            if !defined.contains(variable) {
+                self.issue(
+                    *position,
+                    format!("variable `${variable}` is not defined by {name}"),
+                );
+            }
+        }
+        for variable in operation.variables {
+            if !usages.iter().any(|(name, _)| *name == variable.name) {
+                let message = format!("variable `${}` is never used", variable.name);
+                self.issue(variable.position, message);
+            }
+        }
+        used.extend(spread);
+    }
+    fn selection_set(&mut self, parent: &'d GraphQlType, set: &'d SelectionSet<'q, &'q str>) {
+        for selection in &set.items {
+            match selection {
+                Selection::Field(field) => self.field(parent, field),
+                Selection::FragmentSpread(spread) => {
+                    let Some(fragment) = self.fragments.get(spread.fragment_name).copied() else {
+                        let message = format!("unknown fragment `{}`", spread.fragment_name);
+                        self.issue(spread.position, message);
+                        continue;
+                    };
+                    let TypeCondition::On(on) = fragment.type_condition;
+                    if let Some(ty) = self.schema.get(on) {
+                        self.applies(parent, ty, spread.position);
+                    }
+                }
+                Selection::InlineFragment(inline) => {
+                    let ty = match &inline.type_condition {
+                        Some(TypeCondition::On(on)) => match self.condition(on, inline.position) {
+                            Some(ty) => ty,
+                            None => continue,
+                        },
+                        None => parent,
+                    };
+                    self.applies(parent, ty, inline.position);
+                    self.selection_set(ty, &inline.selection_set);
+                }
+            }
+        }
+    }
+    fn field(&mut self, parent: &'d GraphQlType, field: &'d ast::Field<'q, &'q str>) {
+        if field.name == "__typename" {
+            return;
+        }
+        //the introspection entry points are on the query root without being in its fields
+        if parent.name == self.schema.query_type && ["__schema", "__type"].contains(&field.name) {
+            return;
+        }
+        let Some(definition) = parent.fields.iter().find(|f| f.name == field.name) else {
+            let message = match parent.kind {
+                TypeKind::Union => format!(
+                    "cannot select `{}` on the union `{}`, use a fragment on one of its types",
+                    field.name, parent.name
+                ),
+                _ => format!("type `{}` has no field `{}`", parent.name, field.name),
+            };
+            return self.issue(field.position, message);
+        };
+        let path = format!("{}.{}", parent.name, field.name);
+        for (name, value) in &field.arguments {
+            match definition.args.iter().find(|arg| arg.name == *name) {
+                Some(arg) => self.value(&arg.ty, value, field.position, name),
+                None => {
+                    let message = format!("field `{path}` has no argument `{name}`");
+                    self.issue(field.position, message);
+                }
+            }
+        }
+        for arg in &definition.args {
+            let given = field.arguments.iter().any(|(name, _)| *name == arg.name);
+            if arg.ty.is_required() && arg.default_value.is_none() && !given {
+                self.issue(
+                    field.position,
+                    format!(
+                        "field `{path}` needs the argument `{}` of type `{}`",
+                        arg.name, arg.ty
+                    ),
+                );
+            }
+        }
+        let Some(ty) = self.schema.get(definition.ty.name()) else {
+            return;
+        };
+        let selected = !field.selection_set.items.is_empty();
+        match (ty.is_leaf(), selected) {
+            (true, true) => self.issue(
+                field.position,
+                format!(
+                    "field `{path}` is of the leaf type `{}` and cannot have a selection",
+                    definition.ty
+                ),
+            ),
+            (false, false) => self.issue(
+                field.position,
+                format!(
+                    "field `{path}` of type `{}` needs a selection of subfields",
+                    definition.ty
+                ),
+            ),
+            (false, true) => self.selection_set(ty, &field.selection_set),
+            (true, false) => {}
+        }
+    }
+    //literal values only, variables are checked against the values given with the query
+    fn value(&mut self, ty: &TypeRef, value: &AstValue<'q>, position: Pos, name: &str) {
+        let mismatch =
+            |expected: &TypeRef| format!("argument `{name}` expects `{expected}`, got `{value}`");
+        match (ty, value) {
+            (_, AstValue::Variable(_)) => {}
+            (TypeRef::NonNull(_), AstValue::Null) => self.issue(position, mismatch(ty)),
+            (TypeRef::NonNull(of), value) => self.value(of, value, position, name),
+            (_, AstValue::Null) => {}
+            (TypeRef::List(of), AstValue::List(items)) => {
+                for item in items {
+                    self.value(of, item, position, name);
+                }
+            }
+            //a single value is taken as a list of one
+            (TypeRef::List(of), value) => self.value(of, value, position, name),
+            (TypeRef::Named(_), AstValue::List(_)) => self.issue(position, mismatch(ty)),
+            (TypeRef::Named(named), value) => {
+                let Some(input) = self.schema.get(named) else {
+                    return;
+                };
+                let accepted = match (input.kind, value) {
+                    (TypeKind::Enum, AstValue::Enum(v)) => input.enum_values.iter().any(|e| e == v),
+                    (TypeKind::InputObject, AstValue::Object(fields)) => {
+                        return self.object(input, fields, position, name);
+                    }
+                    (TypeKind::Scalar, value) => match (named.as_str(), value) {
+                        ("Int", AstValue::Int(_)) => true,
+                        ("Float", AstValue::Int(_) | AstValue::Float(_)) => true,
+                        ("String", AstValue::String(_)) => true,
+                        ("Boolean", AstValue::Boolean(_)) => true,
+                        ("ID", AstValue::String(_) | AstValue::Int(_)) => true,
+                        ("Int" | "Float" | "String" | "Boolean" | "ID", _) => false,
+                        //custom scalars take whatever the server makes of them
+                        _ => true,
+                    },
+                    _ => false,
+                };
+                if !accepted {
+                    self.issue(position, mismatch(ty));
+                }
+            }
+        }
+    }
+    fn object(
+        &mut self,
+        input: &GraphQlType,
+        fields: &BTreeMap<&'q str, AstValue<'q>>,
+        position: Pos,
+        name: &str,
+    ) {
+        for (field, value) in fields {
+            match input.input_fields.iter().find(|f| f.name == *field) {
+                Some(definition) => {
+                    self.value(&definition.ty, value, position, &format!("{name}.{field}"))
+                }
+                None => self.issue(
+                    position,
+                    format!("input `{}` has no field `{field}`", input.name),
+                ),
+            }
+        }
+        for definition in &input.input_fields {
+            let required = definition.ty.is_required() && definition.default_value.is_none();
+            if required && !fields.contains_key(definition.name.as_str()) {
+                self.issue(
+                    position,
+                    format!(
+                        "argument `{name}` is missing the field `{}` of type `{}`",
+                        definition.name, definition.ty
+                    ),
+                );
+            }
+        }
+    }
+    //the type a fragment is on, which has to be an object, interface or union
+    fn condition(&mut self, on: &str, position: Pos) -> Option<&'d GraphQlType> {
+        match self.schema.get(on) {
+            Some(ty) if ty.is_composite() => Some(ty),
+            Some(_) => {
+                let message = format!("fragments cannot be on the leaf or input type `{on}`");
+                self.issue(position, message);
+                None
+            }
+            None => {
+                self.issue(position, format!("unknown type `{on}`"));
+                None
+            }
+        }
+    }
+    //a fragment on a type that can never be the parent would select nothing
+    fn applies(&mut self, parent: &GraphQlType, ty: &GraphQlType, position: Pos) {
+        let possible = self.schema.possible(parent);
+        if possible.is_disjoint(&self.schema.possible(ty)) {
+            self.issue(
+                position,
+                format!(
+                    "a fragment on `{}` can never apply to `{}`",
+                    ty.name, parent.name
+                ),
+            );
+        }
+    }
+    fn variables(
+        &self,
+        set: &'d SelectionSet<'q, &'q str>,
+        spread: &mut BTreeSet<&'q str>,
+        usages: &mut Vec<(&'q str, Pos)>,
+    ) {
+        for selection in &set.items {
+            match selection {
+                Selection::Field(field) => {
+                    for (_, value) in &field.arguments {
+                        value_variables(value, field.position, usages);
+                    }
+                    directive_variables(&field.directives, field.position, usages);
+                    self.variables(&field.selection_set, spread, usages);
+                }
+                Selection::FragmentSpread(fragment) => {
+                    directive_variables(&fragment.directives, fragment.position, usages);
+                    //each fragment is walked once, which also stops fragments that spread themselves
+                    if spread.insert(fragment.fragment_name) {
+                        if let Some(definition) = self.fragments.get(fragment.fragment_name) {
+                            self.variables(&definition.selection_set, spread, usages);
+                        }
+                    }
+                }
+                Selection::InlineFragment(inline) => {
+                    directive_variables(&inline.directives, inline.position, usages);
+                    self.variables(&inline.selection_set, spread, usages);
+                }
+            }
+        }
+    }
+    //a fragment spreading itself, directly or through others, would expand forever;
+    //each cycle is reported once, on the first of its fragments by name
+    fn cycles(&mut self) {
+        let mut names: Vec<_> = self.fragments.keys().copied().collect();
+        names.sort();
+        let mut reported = BTreeSet::new();
+        for name in names {
+            if reported.contains(name) {
+                continue;
+            }
+            let mut path = vec![name];
+            if self.cycle(name, name, &mut path, &mut BTreeSet::new()) {
+                reported.extend(path.iter().copied());
+                let message = format!("fragment `{name}` spreads itself: {}", path.join(" -> "));
+                self.issue(self.fragments[name].position, message);
+            }
+        }
+    }
+    fn cycle(
+        &self,
+        target: &'q str,
+        name: &'q str,
+        path: &mut Vec<&'q str>,
+        seen: &mut BTreeSet<&'q str>,
+    ) -> bool {
+        let Some(fragment) = self.fragments.get(name) else {
+            return false;
+        };
+        let mut spreads = Vec::new();
+        fragment_spreads(&fragment.selection_set, &mut spreads);
+        for next in spreads {
+            if next == target {
+                path.push(next);
+                return true;
+            }
+            if seen.insert(next) {
+                path.push(next);
+                if self.cycle(target, next, path, seen) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+    //required variables of the operation that runs have to come with the query
+    fn given_variables(&mut self, operation: &Operation<'d, 'q>, given: Option<&Value>) {
+        let given = given.and_then(Value::as_object);
+        for variable in operation.variables {
+            let ty = type_ref(&variable.var_type);
+            let value = given.and_then(|given| given.get(variable.name));
+            let missing = value.is_none_or(Value::is_null);
+            if ty.is_required() && variable.default_value.is_none() && missing {
+                let message = format!("variable `${}` of type `{ty}` is not given", variable.name);
+                self.issue(variable.position, message);
+            }
+        }
+    }
+}
+fn operation_of<'d, 'q>(operation: &'d OperationDefinition<'q, &'q str>) -> Operation<'d, 'q> {
+    match operation {
+        OperationDefinition::SelectionSet(set) => Operation {
+            kind: "query",
+            name: None,
+            position: set.span.0,
+            variables: &[],
+            directives: &[],
+            selection_set: set,
+        },
+        OperationDefinition::Query(q) => Operation {
+            kind: "query",
+            name: q.name,
+            position: q.position,
+            variables: &q.variable_definitions,
+            directives: &q.directives,
+            selection_set: &q.selection_set,
+        },
+        OperationDefinition::Mutation(m) => Operation {
+            kind: "mutation",
+            name: m.name,
+            position: m.position,
+            variables: &m.variable_definitions,
+            directives: &m.directives,
+            selection_set: &m.selection_set,
+        },
+        OperationDefinition::Subscription(s) => Operation {
+            kind: "subscription",
+            name: s.name,
+            position: s.position,
+            variables: &s.variable_definitions,
+            directives: &s.directives,
+            selection_set: &s.selection_set,
+        },
+    }
+}
+//the fragments a selection spreads, without following them
+fn fragment_spreads<'q>(set: &SelectionSet<'q, &'q str>, spreads: &mut Vec<&'q str>) {
+    for selection in &set.items {
+        match selection {
+            Selection::Field(field) => fragment_spreads(&field.selection_set, spreads),
+            Selection::FragmentSpread(spread) => spreads.push(spread.fragment_name),
+            Selection::InlineFragment(inline) => fragment_spreads(&inline.selection_set, spreads),
+        }
+    }
+}
+fn type_ref<'q>(ty: &ast::Type<'q, &'q str>) -> TypeRef {
+    match ty {
+        ast::Type::NamedType(name) => TypeRef::Named(name.to_string()),
+        ast::Type::ListType(of) => TypeRef::List(Box::new(type_ref(of))),
+        ast::Type::NonNullType(of) => TypeRef::NonNull(Box::new(type_ref(of))),
+    }
+}
+fn value_variables<'q>(value: &AstValue<'q>, position: Pos, usages: &mut Vec<(&'q str, Pos)>) {
+    match value {
+        AstValue::Variable(name) => usages.push((name, position)),
+        AstValue::List(items) => {
+            for item in items {
+                value_variables(item, position, usages);
+            }
+        }
+        AstValue::Object(fields) => {
+            for value in fields.values() {
+                value_variables(value, position, usages);
+            }
+        }
+        _ => {}
+    }
+}
+fn directive_variables<'q>(
+    directives: &[Directive<'q, &'q str>],
+    position: Pos,
+    usages: &mut Vec<(&'q str, Pos)>,
+) {
+    for directive in directives {
+        for (_, value) in &directive.arguments {
+            value_variables(value, position, usages);
+        }
+    }
+}
+//graphql-parser puts the position in the message: `query parse error: Parse error at 2:5\n...`
+fn syntax_issue(error: &str) -> GraphQlIssue {
+    let mut lines = error.lines();
+    let first = lines.next().unwrap_or_default();
+    let position = first.rsplit_once(" at ").and_then(|(_, at)| {
+        let (line, column) = at.trim().split_once(':')?;
+        Some((line.parse().ok()?, column.parse().ok()?))
+    });
+    let detail: Vec<_> = lines.filter(|line| !line.trim().is_empty()).collect();
+    GraphQlIssue {
+        message: match detail.is_empty() {
+            true => first.to_string(),
+            false => format!("syntax error: {}", detail.join(", ")),
+        },
+        position,
+    }
+}
@@ -0,0 +1,219 @@
+use std::{net::SocketAddr, time::Duration};
+
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+
+use super::{FormPart, GraphQlQuery, ReqxAuth, ReqxData, ReqxError};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReqxBody {
+    #[default]
+    Empty,
+    Text(String),
+    Bytes(Vec<u8>),
+    Form(Vec<(String, String)>),
+    Multipart(Vec<FormPart>),
+    GraphQl(GraphQlQuery),
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReqxRequest {
+    #[serde(with = "method_serde")]
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub authentication: ReqxAuth,
+    pub body: ReqxBody,
+}
+//phases are None when they did not happen on this request, e.g. tls on plain http
+//or every connection phase when the request went through a proxy
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReqxTiming {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    pub ttfb: Duration,
+    pub download: Duration,
+    pub total: Duration,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReqxResponse {
+    pub status: u16,
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub remote_addr: Option<SocketAddr>,
+    //timing of the last hop only, every redirect before it has its own
+    pub timing: ReqxTiming,
+    //redirect responses that were followed to get here, in the order they came
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<ReqxRedirect>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReqxRedirect {
+    #[serde(with = "method_serde")]
+    pub method: Method,
+    pub url: String,
+    //as sent on this hop, cookies from the jar included
+    pub request_headers: Vec<(String, String)>,
+    pub status: u16,
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+    //absolute url the next hop went to
+    pub location: String,
+    pub remote_addr: Option<SocketAddr>,
+    pub timing: ReqxTiming,
+}
+impl ReqxRequest {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            authentication: ReqxAuth::None,
+            body: ReqxBody::Empty,
+        }
+    }
+    pub fn graphql(url: impl Into<String>, query: GraphQlQuery) -> Self {
+        Self {
+            body: ReqxBody::GraphQl(query),
+            ..Self::new(Method::POST, url)
+        }
+    }
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+    pub fn header_map(&self) -> Result<HeaderMap<HeaderValue>, ReqxError> {
+        let mut map = HeaderMap::with_capacity(self.headers.len());
+        for (name, value) in &self.headers {
+            let key = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ReqxError::InvalidHeader(name.clone()))?;
+            let value =
+                HeaderValue::from_str(value).map_err(|_| ReqxError::InvalidHeader(name.clone()))?;
+            map.append(key, value);
+        }
+        Ok(map)
+    }
+    pub fn to_data(&self) -> Result<ReqxData, ReqxError> {
+        let mut data = ReqxData {
+            headers: self.header_map()?,
+            authentication: self.authentication.clone(),
+            ..Default::default()
+        };
+        match &self.body {
+            ReqxBody::Empty => {}
+            ReqxBody::Text(text) => data.body = Some(text.clone().into()),
+            ReqxBody::Bytes(bytes) => data.body = Some(bytes.clone().into()),
+            ReqxBody::Form(fields) => data.form = Some(fields.clone()),
+            ReqxBody::Multipart(parts) => data.multipart = Some(parts.clone()),
+            ReqxBody::GraphQl(query) => {
+                if !data.headers.contains_key(header::CONTENT_TYPE) {
+                    data.headers.insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                }
+                data.body = Some(query.payload().into());
+            }
+        }
+        Ok(data)
+    }
+}
+impl ReqxTiming {
+    //(phase name, offset from the request start, phase duration) in the order they happened
+    pub fn waterfall(&self) -> Vec<(&'static str, Duration, Duration)> {
+        let mut offset = Duration::ZERO;
+        let mut phases = Vec::with_capacity(5);
+        let all = [
+            ("dns", self.dns),
+            ("connect", self.connect),
+            ("tls", self.tls),
+            ("ttfb", Some(self.ttfb)),
+            ("download", Some(self.download)),
+        ];
+        for (name, duration) in all {
+            if let Some(duration) = duration {
+                phases.push((name, offset, duration));
+                offset += duration;
+            }
+        }
+        phases
+    }
+}
+impl ReqxResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+    //where the response came from after redirects, None when none were followed
+    pub fn final_url(&self) -> Option<&str> {
+        self.redirects.last().map(|hop| hop.location.as_str())
+    }
+    //time from the first byte sent to the end of the last body, redirects included
+    pub fn elapsed(&self) -> Duration {
+        self.redirects
+            .iter()
+            .map(|hop| hop.timing.total)
+            .sum::<Duration>()
+            + self.timing.total
+    }
+}
+//one line per hop for a redirect chain view, `GET http://a/ -> 302 http://b/ (12ms)`
+impl std::fmt::Display for ReqxRedirect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} -> {} {} ({:?})",
+            self.method, self.url, self.status, self.location, self.timing.total
+        )
+    }
+}
+//headers that are not carried over when a redirect leaves the origin that got them
+pub(crate) const ORIGIN_BOUND: [HeaderName; 4] = [
+    header::AUTHORIZATION,
+    header::PROXY_AUTHORIZATION,
+    header::COOKIE,
+    header::HOST,
+];
+//the method a redirect is followed with: 303 turns everything but HEAD into GET and 301
+//and 302 turn POST into GET like browsers do, 307 and 308 keep it
+pub(crate) fn redirect_method(status: u16, method: &Method) -> Method {
+    match status {
+        303 if *method != Method::HEAD => Method::GET,
+        301 | 302 if *method == Method::POST => Method::GET,
+        _ => method.clone(),
+    }
+}
+pub(crate) fn header_pairs(headers: &HeaderMap<HeaderValue>) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+pub(crate) mod method_serde {
+    use http::Method;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(method: &Method, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(method.as_str())
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Method, D::Error> {
+        let method = String::deserialize(d)?;
+        Method::from_bytes(method.as_bytes()).map_err(D::Error::custom)
+    }
+}
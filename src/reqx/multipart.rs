@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{oauth::random_token, ReqxError};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormPart {
+    pub name: String,
+    pub value: PartValue,
+    //files default to their file name and a type guessed from the extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PartValue {
+    Text(String),
+    //read when the request is built, so a resend picks up the current file
+    File(PathBuf),
+}
+pub(crate) struct Encoded {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+impl FormPart {
+    pub fn text(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: PartValue::Text(value.into()),
+            filename: None,
+            content_type: None,
+        }
+    }
+    pub fn file(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            value: PartValue::File(path.into()),
+            filename: None,
+            content_type: None,
+        }
+    }
+    pub fn filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+    //the filename and content type that will be sent, with the defaults for files applied
+    pub fn resolved(&self) -> (Option<String>, Option<String>) {
+        match &self.value {
+            PartValue::Text(_) => (self.filename.clone(), self.content_type.clone()),
+            PartValue::File(path) => (
+                self.filename.clone().or_else(|| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                }),
+                self.content_type.clone().or_else(|| {
+                    Some(
+                        mime_guess::from_path(path)
+                            .first_or_octet_stream()
+                            .to_string(),
+                    )
+                }),
+            ),
+        }
+    }
+}
+//built in memory rather than streamed, so redirects, digest and sigv4 can send the body again
+//or read it back; the send path reads the files without blocking
+pub(crate) async fn encode(parts: &[FormPart]) -> Result<Encoded, ReqxError> {
+    let mut files = Vec::new();
+    for part in parts {
+        if let PartValue::File(path) = &part.value {
+            files.push(tokio::fs::read(path).await?);
+        }
+    }
+    Ok(frame(parts, files))
+}
+//fetch builds its request synchronously, its files are read on the calling thread
+pub(crate) fn encode_blocking(parts: &[FormPart]) -> Result<Encoded, ReqxError> {
+    let mut files = Vec::new();
+    for part in parts {
+        if let PartValue::File(path) = &part.value {
+            files.push(std::fs::read(path)?);
+        }
+    }
+    Ok(frame(parts, files))
+}
+fn frame(parts: &[FormPart], files: Vec<Vec<u8>>) -> Encoded {
+    let boundary = format!("argus-{}", random_token(18));
+    let mut files = files.into_iter();
+    let mut body = Vec::new();
+    for part in parts {
+        let (filename, content_type) = part.resolved();
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        let mut disposition = format!("form-data; name=\"{}\"", escape(&part.name));
+        if let Some(filename) = filename {
+            disposition.push_str(&format!("; filename=\"{}\"", escape(&filename)));
+        }
+        body.extend_from_slice(format!("Content-Disposition: {disposition}\r\n").as_bytes());
+        if let Some(content_type) = content_type {
+            body.extend_from_slice(format!("Content-Type: {content_type}\r\n").as_bytes());
+        }
+        body.extend_from_slice(b"\r\n");
+        match &part.value {
+            PartValue::Text(text) => body.extend_from_slice(text.as_bytes()),
+            PartValue::File(_) => body.extend(files.next().unwrap_or_default()),
+        }
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    Encoded {
+        content_type: format!("multipart/form-data; boundary={boundary}"),
+        body,
+    }
+}
+//what browsers do with quotes and line breaks in names, per the html form encoding algorithm
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
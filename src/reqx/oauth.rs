@@ -0,0 +1,462 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use super::ReqxError;
+use crate::environment::MASK;
+
+//tokens this close to their expiry are refreshed before use rather than risking a 401 mid-flight
+const EXPIRY_SKEW: i64 = 30;
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+const CALLBACK_PATH: &str = "/callback";
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuth2Config {
+    pub grant: OAuth2Grant,
+    pub token_url: String,
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum OAuth2Grant {
+    ClientCredentials,
+    Password {
+        username: String,
+        password: String,
+    },
+    //pkce with a loopback listener on 127.0.0.1, port 0 picks any free port
+    AuthorizationCode {
+        auth_url: String,
+        redirect_port: u16,
+    },
+}
+#[derive(Clone, PartialEq)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    pub token_type: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scope: Option<String>,
+}
+//shared between clones of Reqx, keyed by environment and the settings that identify a grant
+#[derive(Debug, Clone, Default)]
+pub struct TokenCache(Arc<Mutex<HashMap<String, OAuth2Token>>>);
+//called with the authorization url, opens the system browser unless replaced
+#[derive(Clone)]
+pub struct AuthorizeHook(Arc<dyn Fn(&str) + Send + Sync>);
+#[derive(Debug)]
+pub enum OAuthError {
+    //the token endpoint answered with an error
+    TokenEndpoint {
+        status: u16,
+        error: String,
+        description: Option<String>,
+    },
+    InvalidTokenResponse(String),
+    AuthorizationDenied(String),
+    //the redirect came back with another state, it was not started by this flow
+    StateMismatch,
+    CallbackTimeout,
+    //the request needs a token, which only Reqx::send and friends can obtain
+    TokenRequired,
+}
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+impl std::fmt::Debug for OAuth2Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuth2Config")
+            .field("grant", &self.grant)
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &self.client_secret.as_ref().map(|_| MASK))
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+impl std::fmt::Debug for OAuth2Grant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ClientCredentials => write!(f, "ClientCredentials"),
+            Self::Password { username, .. } => f
+                .debug_struct("Password")
+                .field("username", username)
+                .field("password", &MASK)
+                .finish(),
+            Self::AuthorizationCode {
+                auth_url,
+                redirect_port,
+            } => f
+                .debug_struct("AuthorizationCode")
+                .field("auth_url", auth_url)
+                .field("redirect_port", redirect_port)
+                .finish(),
+        }
+    }
+}
+impl std::fmt::Debug for OAuth2Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuth2Token")
+            .field("access_token", &MASK)
+            .field("token_type", &self.token_type)
+            .field("refresh_token", &self.refresh_token.as_ref().map(|_| MASK))
+            .field("expires_at", &self.expires_at)
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+impl std::fmt::Debug for AuthorizeHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AuthorizeHook")
+    }
+}
+impl Default for AuthorizeHook {
+    fn default() -> Self {
+        Self::new(|url| {
+            let _ = open::that_detached(url);
+        })
+    }
+}
+impl Display for OAuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TokenEndpoint {
+                status,
+                error,
+                description,
+            } => {
+                write!(f, "token endpoint returned {status}: {error}")?;
+                if let Some(description) = description {
+                    write!(f, " ({description})")?;
+                }
+                Ok(())
+            }
+            Self::InvalidTokenResponse(e) => write!(f, "invalid token response: {e}"),
+            Self::AuthorizationDenied(e) => write!(f, "authorization denied: {e}"),
+            Self::StateMismatch => write!(f, "authorization callback has an unexpected state"),
+            Self::CallbackTimeout => write!(f, "timed out waiting for the authorization callback"),
+            Self::TokenRequired => write!(f, "oauth2 requests have to be sent to obtain a token"),
+        }
+    }
+}
+impl std::error::Error for OAuthError {}
+impl AuthorizeHook {
+    pub fn new(hook: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+}
+impl OAuth2Token {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|at| at - chrono::Duration::seconds(EXPIRY_SKEW) <= Utc::now())
+    }
+}
+impl TokenCache {
+    pub fn get(&self, environment: Option<&str>, config: &OAuth2Config) -> Option<OAuth2Token> {
+        self.0
+            .lock()
+            .ok()?
+            .get(&cache_key(environment, config))
+            .cloned()
+    }
+    pub fn insert(&self, environment: Option<&str>, config: &OAuth2Config, token: OAuth2Token) {
+        if let Ok(mut tokens) = self.0.lock() {
+            tokens.insert(cache_key(environment, config), token);
+        }
+    }
+    pub fn remove(&self, environment: Option<&str>, config: &OAuth2Config) {
+        if let Ok(mut tokens) = self.0.lock() {
+            tokens.remove(&cache_key(environment, config));
+        }
+    }
+    pub fn clear(&self) {
+        if let Ok(mut tokens) = self.0.lock() {
+            tokens.clear();
+        }
+    }
+}
+//secrets only go in hashed, a changed secret needs its own token but must not sit in the key
+fn cache_key(environment: Option<&str>, config: &OAuth2Config) -> String {
+    let (grant, user, password) = match &config.grant {
+        OAuth2Grant::ClientCredentials => ("client_credentials", "", ""),
+        OAuth2Grant::Password { username, password } => {
+            ("password", username.as_str(), password.as_str())
+        }
+        OAuth2Grant::AuthorizationCode { auth_url, .. } => {
+            ("authorization_code", auth_url.as_str(), "")
+        }
+    };
+    let mut secrets = Sha256::new();
+    secrets.update(config.client_secret.as_deref().unwrap_or_default());
+    secrets.update([0]);
+    secrets.update(password);
+    [
+        environment.unwrap_or_default(),
+        &config.token_url,
+        &config.client_id,
+        grant,
+        user,
+        config.scope.as_deref().unwrap_or_default(),
+        &URL_SAFE_NO_PAD.encode(secrets.finalize()),
+    ]
+    .join("\n")
+}
+
+//a cached token is used while valid, then refreshed, and the full grant runs only when that fails
+pub(crate) async fn authorize(
+    client: &Client,
+    timeout: Option<Duration>,
+    cache: &TokenCache,
+    hook: &AuthorizeHook,
+    environment: Option<&str>,
+    config: &OAuth2Config,
+) -> Result<OAuth2Token, ReqxError> {
+    let cached = cache.get(environment, config);
+    if let Some(token) = &cached {
+        if !token.is_expired() {
+            return Ok(token.clone());
+        }
+    }
+    let refreshed = match cached.and_then(|t| t.refresh_token) {
+        Some(refresh_token) => refresh(client, timeout, config, &refresh_token).await.ok(),
+        None => None,
+    };
+    let token = match refreshed {
+        Some(token) => token,
+        None => request_token(client, timeout, hook, config).await?,
+    };
+    cache.insert(environment, config, token.clone());
+    Ok(token)
+}
+async fn request_token(
+    client: &Client,
+    timeout: Option<Duration>,
+    hook: &AuthorizeHook,
+    config: &OAuth2Config,
+) -> Result<OAuth2Token, ReqxError> {
+    match &config.grant {
+        OAuth2Grant::ClientCredentials => {
+            let mut params = vec![("grant_type", "client_credentials".into())];
+            params.extend(config.scope.clone().map(|scope| ("scope", scope)));
+            token_request(client, timeout, config, params).await
+        }
+        OAuth2Grant::Password { username, password } => {
+            let mut params = vec![
+                ("grant_type", "password".into()),
+                ("username", username.clone()),
+                ("password", password.clone()),
+            ];
+            params.extend(config.scope.clone().map(|scope| ("scope", scope)));
+            token_request(client, timeout, config, params).await
+        }
+        OAuth2Grant::AuthorizationCode {
+            auth_url,
+            redirect_port,
+        } => authorization_code(client, timeout, hook, config, auth_url, *redirect_port).await,
+    }
+}
+async fn refresh(
+    client: &Client,
+    timeout: Option<Duration>,
+    config: &OAuth2Config,
+    refresh_token: &str,
+) -> Result<OAuth2Token, ReqxError> {
+    let params = vec![
+        ("grant_type", "refresh_token".into()),
+        ("refresh_token", refresh_token.into()),
+    ];
+    let mut token = token_request(client, timeout, config, params).await?;
+    //servers that do not rotate refresh tokens leave it out of the response
+    token
+        .refresh_token
+        .get_or_insert_with(|| refresh_token.into());
+    Ok(token)
+}
+async fn authorization_code(
+    client: &Client,
+    timeout: Option<Duration>,
+    hook: &AuthorizeHook,
+    config: &OAuth2Config,
+    auth_url: &str,
+    redirect_port: u16,
+) -> Result<OAuth2Token, ReqxError> {
+    let listener = TcpListener::bind(("127.0.0.1", redirect_port)).await?;
+    let port = listener.local_addr()?.port();
+    let redirect_uri = format!("http://127.0.0.1:{port}{CALLBACK_PATH}");
+    let verifier = random_token(32);
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    let state = random_token(16);
+    let mut url =
+        url::Url::parse(auth_url).map_err(|_| ReqxError::InvalidUrl(auth_url.to_string()))?;
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+        if let Some(scope) = &config.scope {
+            query.append_pair("scope", scope);
+        }
+    }
+    (hook.0)(url.as_str());
+    let code = tokio::time::timeout(CALLBACK_TIMEOUT, callback(&listener, &state))
+        .await
+        .map_err(|_| OAuthError::CallbackTimeout)??;
+    let params = vec![
+        ("grant_type", "authorization_code".into()),
+        ("code", code),
+        ("redirect_uri", redirect_uri),
+        ("code_verifier", verifier),
+    ];
+    token_request(client, timeout, config, params).await
+}
+//answers whatever the browser asks for until the redirect with our state arrives
+async fn callback(listener: &TcpListener, state: &str) -> Result<String, ReqxError> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut buf = vec![0u8; 8192];
+        let mut len = 0;
+        while len < buf.len() && !buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+            match stream.read(&mut buf[len..]).await? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        let head = String::from_utf8_lossy(&buf[..len]);
+        let target = head.split_whitespace().nth(1).unwrap_or_default();
+        let Ok(url) = url::Url::parse(&format!("http://127.0.0.1{target}")) else {
+            respond(&mut stream, "400 Bad Request", "Bad request.").await;
+            continue;
+        };
+        if url.path() != CALLBACK_PATH {
+            respond(&mut stream, "404 Not Found", "Not found.").await;
+            continue;
+        }
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        if let Some(error) = query.get("error") {
+            respond(&mut stream, "200 OK", "Authorization was denied.").await;
+            let description = query.get("error_description").unwrap_or(error);
+            return Err(OAuthError::AuthorizationDenied(description.clone()).into());
+        }
+        if query.get("state").map(String::as_str) != Some(state) {
+            respond(
+                &mut stream,
+                "400 Bad Request",
+                "Unexpected authorization state.",
+            )
+            .await;
+            return Err(OAuthError::StateMismatch.into());
+        }
+        let Some(code) = query.get("code") else {
+            respond(
+                &mut stream,
+                "400 Bad Request",
+                "Missing authorization code.",
+            )
+            .await;
+            continue;
+        };
+        respond(
+            &mut stream,
+            "200 OK",
+            "Authorization complete, you can close this window.",
+        )
+        .await;
+        return Ok(code.clone());
+    }
+}
+async fn respond(stream: &mut tokio::net::TcpStream, status: &str, message: &str) {
+    let body = format!("<!doctype html><html><body><p>{message}</p></body></html>");
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+//the client secret goes in a basic auth header, public clients only send their id in the body;
+//the client has no timeout of its own, sends apply it themselves
+async fn token_request(
+    client: &Client,
+    timeout: Option<Duration>,
+    config: &OAuth2Config,
+    mut params: Vec<(&str, String)>,
+) -> Result<OAuth2Token, ReqxError> {
+    let mut req = client
+        .post(&config.token_url)
+        .header(http::header::ACCEPT, "application/json");
+    if let Some(timeout) = timeout {
+        req = req.timeout(timeout);
+    }
+    match &config.client_secret {
+        Some(secret) => req = req.basic_auth(&config.client_id, Some(secret)),
+        None => params.push(("client_id", config.client_id.clone())),
+    }
+    let response = req.form(&params).send().await?;
+    let status = response.status();
+    let body = response.bytes().await?;
+    if !status.is_success() {
+        let error = serde_json::from_slice::<ErrorResponse>(&body).unwrap_or(ErrorResponse {
+            error: String::from_utf8_lossy(&body).into_owned(),
+            error_description: None,
+        });
+        return Err(OAuthError::TokenEndpoint {
+            status: status.as_u16(),
+            error: error.error,
+            description: error.error_description,
+        }
+        .into());
+    }
+    let token: TokenResponse = serde_json::from_slice(&body)
+        .map_err(|e| OAuthError::InvalidTokenResponse(e.to_string()))?;
+    Ok(OAuth2Token {
+        access_token: token.access_token,
+        token_type: token.token_type.unwrap_or_else(|| "Bearer".into()),
+        refresh_token: token.refresh_token,
+        //an expiry too far out to represent is treated as none
+        expires_at: token
+            .expires_in
+            .and_then(TimeDelta::try_seconds)
+            .and_then(|delta| Utc::now().checked_add_signed(delta)),
+        scope: token.scope,
+    })
+}
+pub(crate) fn random_token(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut aes_gcm::aead::OsRng, &mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -0,0 +1,198 @@
+use std::net::IpAddr;
+
+use reqwest::Proxy;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::ReqxError;
+use crate::environment::MASK;
+
+const SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+//proxy urls are `http://`, `https://`, `socks5://` or `socks5h://` (dns resolved by the proxy)
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxySettings {
+    //used for http:// requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<String>,
+    //used for https:// requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https: Option<String>,
+    //credentials can also be in the proxy url, these win when both are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    //hosts sent directly: `example.com` also covers its subdomains, `10.0.0.0/8` a range,
+    //`*` everything
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_proxy: Vec<String>,
+    //fills in what is not set here from http_proxy, HTTPS_PROXY, ALL_PROXY and NO_PROXY
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub use_system: bool,
+}
+impl std::fmt::Debug for ProxySettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxySettings")
+            .field("http", &self.http.as_deref().map(mask_url))
+            .field("https", &self.https.as_deref().map(mask_url))
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| MASK))
+            .field("no_proxy", &self.no_proxy)
+            .field("use_system", &self.use_system)
+            .finish()
+    }
+}
+impl ProxySettings {
+    pub fn all(url: impl Into<String>) -> Self {
+        let url = url.into();
+        Self {
+            http: Some(url.clone()),
+            https: Some(url),
+            ..Default::default()
+        }
+    }
+    pub fn system() -> Self {
+        Self {
+            use_system: true,
+            ..Default::default()
+        }
+    }
+    pub fn is_direct(&self) -> bool {
+        self.http.is_none() && self.https.is_none() && !self.use_system
+    }
+    //the system variables merged in, read once so a running client does not change under a send
+    pub fn resolved(&self) -> Self {
+        if !self.use_system {
+            return self.clone();
+        }
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| std::env::var(name).ok().filter(|v| !v.trim().is_empty()))
+        };
+        let all = var(&["ALL_PROXY", "all_proxy"]);
+        let mut no_proxy = self.no_proxy.clone();
+        if let Some(system) = var(&["NO_PROXY", "no_proxy"]) {
+            no_proxy.extend(
+                system
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|entry| !entry.is_empty())
+                    .map(String::from),
+            );
+        }
+        Self {
+            //HTTP_PROXY is ignored in cgi style environments, curl only reads the lowercase one
+            http: self
+                .http
+                .clone()
+                .or_else(|| var(&["http_proxy"]))
+                .or_else(|| all.clone()),
+            https: self
+                .https
+                .clone()
+                .or_else(|| var(&["HTTPS_PROXY", "https_proxy"]))
+                .or(all),
+            username: self.username.clone(),
+            password: self.password.clone(),
+            no_proxy,
+            use_system: false,
+        }
+    }
+    //the proxy a request to `url` goes through with its credentials, None when it is sent directly
+    pub fn proxy_for(&self, url: &Url) -> Option<Url> {
+        let proxy = match url.scheme() {
+            "http" | "ws" => self.http.as_deref()?,
+            "https" | "wss" => self.https.as_deref()?,
+            _ => return None,
+        };
+        let host = url.host_str()?;
+        if self.bypasses(host) {
+            return None;
+        }
+        let mut proxy = parse_proxy(proxy).ok()?;
+        if let Some(username) = &self.username {
+            proxy.set_username(username).ok()?;
+            proxy.set_password(self.password.as_deref()).ok()?;
+        }
+        Some(proxy)
+    }
+    pub fn bypasses(&self, host: &str) -> bool {
+        let host = host.trim_matches(['[', ']']).to_ascii_lowercase();
+        let ip = host.parse::<IpAddr>().ok();
+        self.no_proxy.iter().any(|entry| {
+            let entry = entry.trim().to_ascii_lowercase();
+            if entry == "*" {
+                return true;
+            }
+            if let (Some(ip), Some((range, bits))) = (ip, entry.split_once('/')) {
+                return match (range.parse::<IpAddr>(), bits.parse::<u32>()) {
+                    (Ok(range), Ok(bits)) => in_range(ip, range, bits),
+                    _ => false,
+                };
+            }
+            //a port on the entry is not compared, `host:8080` bypasses every port of host
+            let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+            let entry = match entry.rsplit_once(':') {
+                Some((name, port)) if port.parse::<u16>().is_ok() && !name.contains(':') => name,
+                _ => entry,
+            };
+            let entry = entry.trim_matches(['[', ']']);
+            host == entry || host.ends_with(&format!(".{entry}"))
+        })
+    }
+    //every proxy url has to parse, so a typo fails when the client is built and not on each send
+    pub(crate) fn validate(&self) -> Result<(), ReqxError> {
+        for proxy in [&self.http, &self.https].into_iter().flatten() {
+            parse_proxy(proxy)?;
+        }
+        Ok(())
+    }
+    //expects resolved settings
+    pub(crate) fn reqwest(&self) -> Result<Option<Proxy>, ReqxError> {
+        let settings = self.clone();
+        settings.validate()?;
+        if settings.http.is_none() && settings.https.is_none() {
+            return Ok(None);
+        }
+        //reqwest reads the credentials out of the url, for socks5 as well as http proxies
+        Ok(Some(Proxy::custom(move |url| settings.proxy_for(url))))
+    }
+}
+fn parse_proxy(proxy: &str) -> Result<Url, ReqxError> {
+    let invalid = || ReqxError::Config(format!("invalid proxy `{}`", mask_url(proxy)));
+    //like curl, a bare `host:port` is an http proxy
+    let url = match proxy.contains("://") {
+        true => Url::parse(proxy),
+        false => Url::parse(&format!("http://{proxy}")),
+    }
+    .map_err(|_| invalid())?;
+    if !SCHEMES.contains(&url.scheme()) || url.host_str().is_none() {
+        return Err(invalid());
+    }
+    Ok(url)
+}
+//proxy urls can carry a password, which must not end up in logs or errors
+fn mask_url(proxy: &str) -> String {
+    match Url::parse(proxy) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some(MASK));
+            url.to_string()
+        }
+        _ => proxy.to_string(),
+    }
+}
+fn in_range(ip: IpAddr, range: IpAddr, bits: u32) -> bool {
+    match (ip, range) {
+        (IpAddr::V4(ip), IpAddr::V4(range)) if bits <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(range) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(range)) if bits <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - bits).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(range) & mask
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use http::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{digest::hex, ReqxError};
+use crate::environment::MASK;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct AwsSigV4 {
+    pub access_key: String,
+    pub secret_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    pub region: String,
+    pub service: String,
+}
+impl std::fmt::Debug for AwsSigV4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsSigV4")
+            .field("access_key", &self.access_key)
+            .field("secret_key", &MASK)
+            .field("session_token", &self.session_token.as_ref().map(|_| MASK))
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+impl AwsSigV4 {
+    //signs the built request in place, every header it already carries is signed along with host
+    pub fn sign(&self, req: &mut reqwest::Request, now: DateTime<Utc>) -> Result<(), ReqxError> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let body = match req.body() {
+            Some(body) => body.as_bytes().ok_or(ReqxError::StreamingBody)?,
+            None => &[],
+        };
+        let payload_hash = hex(&Sha256::digest(body));
+        let url = req.url().clone();
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(ReqxError::InvalidUrl(url.to_string())),
+        };
+        let mut add = |name: &'static str, value: &str| -> Result<(), ReqxError> {
+            let value =
+                HeaderValue::from_str(value).map_err(|_| ReqxError::InvalidHeader(name.into()))?;
+            req.headers_mut()
+                .insert(HeaderName::from_static(name), value);
+            Ok(())
+        };
+        add("host", &host)?;
+        add("x-amz-date", &amz_date)?;
+        //s3 refuses requests without it, other services do not need it
+        if self.service == "s3" {
+            add("x-amz-content-sha256", &payload_hash)?;
+        }
+        if let Some(token) = &self.session_token {
+            add("x-amz-security-token", token)?;
+        }
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for (name, value) in req.headers() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            match headers.iter_mut().find(|(n, _)| n == name.as_str()) {
+                Some((_, existing)) => {
+                    existing.push(',');
+                    existing.push_str(&value);
+                }
+                None => headers.push((name.as_str().to_string(), value)),
+            }
+        }
+        headers.sort();
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let mut query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| (uri_encode(&k, true), uri_encode(&v, true)))
+            .collect();
+        query.sort();
+        let canonical_query = query
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        //the path is already percent encoded once, every service but s3 wants it encoded twice
+        let canonical_uri = match url.path() {
+            "" => String::from("/"),
+            path if self.service == "s3" => path.to_string(),
+            path => uri_encode(path, false),
+        };
+        let canonical_request = format!(
+            "{}\n{canonical_uri}\n{canonical_query}\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+            req.method().as_str()
+        );
+
+        let scope = format!("{date}/{}/{}/aws4_request", self.region, self.service);
+        let string_to_sign = format!(
+            "{ALGORITHM}\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        for part in [self.region.as_str(), self.service.as_str(), "aws4_request"] {
+            key = hmac(&key, part);
+        }
+        let signature = hex(&hmac(&key, &string_to_sign));
+        let authorization = format!(
+            "{ALGORITHM} Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key
+        );
+        let authorization = HeaderValue::from_str(&authorization)
+            .map_err(|_| ReqxError::InvalidHeader("authorization".into()))?;
+        req.headers_mut()
+            .insert(http::header::AUTHORIZATION, authorization);
+        Ok(())
+    }
+}
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+//aws encoding: only unreserved characters stay as they are, `/` too when encoding a path
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
@@ -0,0 +1,261 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+
+use super::{body::Head, Reqx, ReqxError, ReqxRequest, ReqxResponse, Route, Target};
+
+//what browsers wait before reconnecting until the server sends `retry:`
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SseEvent {
+    //the last id the stream set, it carries over to the events after it
+    pub id: Option<String>,
+    //`message` when the server names none
+    pub event: String,
+    pub data: String,
+    pub received: DateTime<Utc>,
+}
+#[derive(Debug)]
+pub enum SseUpdate {
+    //the stream is open, the response has no body
+    Opened(ReqxResponse),
+    Event(SseEvent),
+    //the server set a new reconnection delay
+    Retry(Duration),
+    //the stream dropped or could not be opened, another attempt follows after the delay
+    Reconnecting {
+        after: Duration,
+        error: Option<ReqxError>,
+    },
+    //a response that is not an event stream ends it for good, 204 included
+    Rejected(ReqxResponse),
+}
+//turns the bytes of a text/event-stream into events as the html spec reads them,
+//chunks can split lines and events anywhere
+#[derive(Debug, Default)]
+pub struct SseParser {
+    line: Vec<u8>,
+    //a chunk ended in \r, a \n starting the next one belongs to the same line break
+    after_cr: bool,
+    started: bool,
+    data: String,
+    event: String,
+    last_event_id: String,
+    retry: Option<Duration>,
+}
+//an event stream kept open by a task, reconnecting with Last-Event-ID until it is closed
+//or dropped
+#[derive(Debug)]
+pub struct SseStream {
+    updates: UnboundedReceiver<SseUpdate>,
+    task: JoinHandle<()>,
+}
+impl SseParser {
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Vec<SseEvent> {
+        if !self.started && !chunk.is_empty() {
+            self.started = true;
+            chunk = chunk.strip_prefix("\u{feff}".as_bytes()).unwrap_or(chunk);
+        }
+        let mut events = Vec::new();
+        for &byte in chunk {
+            let after_cr = std::mem::take(&mut self.after_cr);
+            match byte {
+                b'\n' if after_cr => {}
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    events.extend(self.line_done(&String::from_utf8_lossy(&line)));
+                }
+                byte => self.line.push(byte),
+            }
+        }
+        events
+    }
+    pub fn last_event_id(&self) -> Option<&str> {
+        Some(self.last_event_id.as_str()).filter(|id| !id.is_empty())
+    }
+    //the delay from the last `retry:` field, taken once
+    pub fn take_retry(&mut self) -> Option<Duration> {
+        self.retry.take()
+    }
+    //a dropped connection loses the event it was in the middle of, the last id stays
+    pub fn reset(&mut self) {
+        self.line.clear();
+        self.after_cr = false;
+        self.started = false;
+        self.data.clear();
+        self.event.clear();
+    }
+    fn line_done(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_string(),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = std::mem::take(&mut self.event);
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(SseEvent {
+            id: self.last_event_id().map(String::from),
+            event: match event.is_empty() {
+                true => String::from("message"),
+                false => event,
+            },
+            data,
+            received: Utc::now(),
+        })
+    }
+}
+impl SseStream {
+    pub(super) fn spawn(
+        reqx: Arc<Reqx>,
+        request: ReqxRequest,
+        environment: Option<String>,
+        route: Route,
+    ) -> Self {
+        let (sender, updates) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run(reqx, request, environment, route, sender));
+        Self { updates, task }
+    }
+    pub async fn recv(&mut self) -> Option<SseUpdate> {
+        self.updates.recv().await
+    }
+    pub fn try_recv(&mut self) -> Option<SseUpdate> {
+        self.updates.try_recv().ok()
+    }
+    pub fn close(&self) {
+        self.task.abort();
+    }
+    pub fn is_closed(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+impl Drop for SseStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+async fn run(
+    reqx: Arc<Reqx>,
+    mut request: ReqxRequest,
+    environment: Option<String>,
+    route: Route,
+    updates: UnboundedSender<SseUpdate>,
+) {
+    let target = Target {
+        environment: environment.as_deref(),
+        route,
+    };
+    for (name, value) in [
+        ("Accept", "text/event-stream"),
+        ("Cache-Control", "no-cache"),
+    ] {
+        if !request
+            .headers
+            .iter()
+            .any(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            request.headers.push((name.to_string(), value.to_string()));
+        }
+    }
+    let mut parser = SseParser::default();
+    let mut retry = DEFAULT_RETRY;
+    loop {
+        let mut attempt = request.clone();
+        if let Some(id) = parser.last_event_id() {
+            attempt
+                .headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("last-event-id"));
+            attempt
+                .headers
+                .push((String::from("Last-Event-ID"), id.to_string()));
+        }
+        //dispatch follows redirects like a send, every reconnect starts from the original url
+        let error = match reqx.dispatch(&attempt, &target).await {
+            Ok(mut head) => {
+                let is_stream = head
+                    .response
+                    .header("content-type")
+                    .and_then(|ct| ct.split(';').next())
+                    .is_some_and(|ct| ct.trim().eq_ignore_ascii_case("text/event-stream"));
+                if head.response.status == 200 && is_stream {
+                    let _ = updates.send(SseUpdate::Opened(head.response.clone()));
+                    parser.reset();
+                    read(&mut head, &mut parser, &mut retry, &updates).await
+                } else {
+                    match head.read().await {
+                        Ok(response) => {
+                            let _ = updates.send(SseUpdate::Rejected(response));
+                            return;
+                        }
+                        Err(e) => Some(e),
+                    }
+                }
+            }
+            Err(e) => Some(e),
+        };
+        if updates.is_closed() {
+            return;
+        }
+        let _ = updates.send(SseUpdate::Reconnecting {
+            after: retry,
+            error,
+        });
+        tokio::time::sleep(retry).await;
+    }
+}
+//until the server ends the stream or the connection drops, with the error when it dropped;
+//a stream can stay quiet for long, so there is no timeout between chunks
+async fn read(
+    head: &mut Head,
+    parser: &mut SseParser,
+    retry: &mut Duration,
+    updates: &UnboundedSender<SseUpdate>,
+) -> Option<ReqxError> {
+    loop {
+        match head.body.chunk().await {
+            Ok(Some(chunk)) => {
+                for event in parser.feed(&chunk) {
+                    let _ = updates.send(SseUpdate::Event(event));
+                }
+                if let Some(delay) = parser.take_retry() {
+                    *retry = delay;
+                    let _ = updates.send(SseUpdate::Retry(delay));
+                }
+            }
+            Ok(None) => return None,
+            Err(e) => return Some(e),
+        }
+    }
+}
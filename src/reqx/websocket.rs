@@ -0,0 +1,372 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
+    },
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+
+use super::{model, ReqxError, ReqxRequest};
+
+//headers tungstenite writes for the upgrade itself, a copy from the request would be sent twice
+const HANDSHAKE_HEADERS: [&str; 5] = [
+    "host",
+    "connection",
+    "upgrade",
+    "sec-websocket-version",
+    "sec-websocket-key",
+];
+
+//how long a close waits for the server's answer when the config has no timeout
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Stream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+//the upgrade request: url with ws:// or wss://, headers and authentication as on any request,
+//the body is not used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsRequest {
+    pub request: ReqxRequest,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subprotocols: Vec<String>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WsFrame {
+    Text(String),
+    Binary(#[serde(with = "base64_bytes")] Vec<u8>),
+    Ping(#[serde(with = "base64_bytes")] Vec<u8>),
+    Pong(#[serde(with = "base64_bytes")] Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WsDirection {
+    Sent,
+    Received,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsMessage {
+    pub at: DateTime<Utc>,
+    pub direction: WsDirection,
+    pub frame: WsFrame,
+}
+//everything that went over one session, in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsLog {
+    pub url: String,
+    pub protocol: Option<String>,
+    pub connected: DateTime<Utc>,
+    pub messages: Vec<WsMessage>,
+}
+#[derive(Debug)]
+pub enum WsEvent {
+    Message(WsMessage),
+    //the connection is gone, after a close handshake or because the peer dropped it
+    Closed,
+    Failed(ReqxError),
+}
+//a connected socket run by a task on the runtime; sends are queued without waiting, so the
+//window loop can call them directly, and received frames come back through recv or try_recv
+#[derive(Debug)]
+pub struct WsSession {
+    pub url: String,
+    //the subprotocol the server picked
+    pub protocol: Option<String>,
+    //of the 101 response
+    pub headers: Vec<(String, String)>,
+    commands: UnboundedSender<WsFrame>,
+    events: UnboundedReceiver<WsEvent>,
+    log: Arc<Mutex<WsLog>>,
+    task: JoinHandle<()>,
+}
+impl WsRequest {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            request: ReqxRequest::new(http::Method::GET, url),
+            subprotocols: Vec::new(),
+        }
+    }
+}
+impl WsFrame {
+    fn from_message(message: Message) -> Option<Self> {
+        match message {
+            Message::Text(text) => Some(Self::Text(text)),
+            Message::Binary(bytes) => Some(Self::Binary(bytes)),
+            Message::Ping(bytes) => Some(Self::Ping(bytes)),
+            Message::Pong(bytes) => Some(Self::Pong(bytes)),
+            Message::Close(frame) => Some(Self::Close(
+                frame.map(|frame| (frame.code.into(), frame.reason.into_owned())),
+            )),
+            //raw frames only show up when writing, never from a read
+            Message::Frame(_) => None,
+        }
+    }
+    fn into_message(self) -> Message {
+        match self {
+            Self::Text(text) => Message::Text(text),
+            Self::Binary(bytes) => Message::Binary(bytes),
+            Self::Ping(bytes) => Message::Ping(bytes),
+            Self::Pong(bytes) => Message::Pong(bytes),
+            Self::Close(frame) => Message::Close(frame.map(|(code, reason)| CloseFrame {
+                code: CloseCode::from(code),
+                reason: reason.into(),
+            })),
+        }
+    }
+}
+impl std::fmt::Display for WsMessage {
+    //`2024-05-01T10:00:00.123Z > text hello`, > for sent and < for received
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arrow = match self.direction {
+            WsDirection::Sent => '>',
+            WsDirection::Received => '<',
+        };
+        let at = self.at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        write!(f, "{at} {arrow} ")?;
+        match &self.frame {
+            WsFrame::Text(text) => write!(f, "text {text}"),
+            WsFrame::Binary(bytes) => write!(f, "binary {}", BASE64.encode(bytes)),
+            WsFrame::Ping(bytes) => write!(f, "ping {}", BASE64.encode(bytes)),
+            WsFrame::Pong(bytes) => write!(f, "pong {}", BASE64.encode(bytes)),
+            WsFrame::Close(Some((code, reason))) => write!(f, "close {code} {reason}"),
+            WsFrame::Close(None) => write!(f, "close"),
+        }
+    }
+}
+impl WsLog {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+    //one line per frame, binary payloads in base64
+    pub fn to_text(&self) -> String {
+        self.messages
+            .iter()
+            .map(|message| format!("{message}\n"))
+            .collect()
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReqxError> {
+        Ok(std::fs::write(path, self.to_json())?)
+    }
+    fn push(&mut self, direction: WsDirection, frame: WsFrame) -> WsMessage {
+        let message = WsMessage {
+            at: Utc::now(),
+            direction,
+            frame,
+        };
+        self.messages.push(message.clone());
+        message
+    }
+}
+impl WsSession {
+    pub fn send(&self, frame: WsFrame) -> Result<(), ReqxError> {
+        self.commands
+            .send(frame)
+            .map_err(|_| ReqxError::WebSocket(Box::new(tungstenite::Error::AlreadyClosed)))
+    }
+    pub fn send_text(&self, text: impl Into<String>) -> Result<(), ReqxError> {
+        self.send(WsFrame::Text(text.into()))
+    }
+    pub fn send_binary(&self, bytes: impl Into<Vec<u8>>) -> Result<(), ReqxError> {
+        self.send(WsFrame::Binary(bytes.into()))
+    }
+    pub fn ping(&self, payload: impl Into<Vec<u8>>) -> Result<(), ReqxError> {
+        self.send(WsFrame::Ping(payload.into()))
+    }
+    //starts the close handshake, WsEvent::Closed follows once the server answers or, when it
+    //does not, once the connection is dropped after the timeout
+    pub fn close(&self, code: u16, reason: impl Into<String>) -> Result<(), ReqxError> {
+        self.send(WsFrame::Close(Some((code, reason.into()))))
+    }
+    pub async fn recv(&mut self) -> Option<WsEvent> {
+        self.events.recv().await
+    }
+    pub fn try_recv(&mut self) -> Option<WsEvent> {
+        self.events.try_recv().ok()
+    }
+    pub fn is_closed(&self) -> bool {
+        self.task.is_finished()
+    }
+    //a copy of the log so far
+    pub fn log(&self) -> WsLog {
+        self.log.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+impl Drop for WsSession {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+//runs the upgrade for `req`, whose url is still http:// or https://; `shown` is the url
+//kept on the session and its log, with secrets masked
+pub(crate) async fn connect(
+    req: &reqwest::Request,
+    shown: &str,
+    subprotocols: &[String],
+    tls: native_tls::TlsConnector,
+    timeout: Option<Duration>,
+) -> Result<WsSession, ReqxError> {
+    let mut url = req.url().clone();
+    let scheme = match url.scheme() {
+        "https" => "wss",
+        _ => "ws",
+    };
+    let _ = url.set_scheme(scheme);
+    let mut request = url.as_str().into_client_request()?;
+    let headers = request.headers_mut();
+    for (name, value) in req.headers() {
+        if !HANDSHAKE_HEADERS.contains(&name.as_str()) {
+            headers.append(name, value.clone());
+        }
+    }
+    if !subprotocols.is_empty() {
+        //tungstenite splits the header on `,` without trimming, so no spaces
+        let value = http::HeaderValue::from_str(&subprotocols.join(","))
+            .map_err(|_| ReqxError::InvalidHeader(String::from("sec-websocket-protocol")))?;
+        headers.insert(http::header::SEC_WEBSOCKET_PROTOCOL, value);
+    }
+    let handshake = tokio_tungstenite::connect_async_tls_with_config(
+        request,
+        None,
+        true,
+        Some(Connector::NativeTls(tls)),
+    );
+    let (stream, response) = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, handshake)
+            .await
+            .map_err(|_| ReqxError::Timeout(timeout))??,
+        None => handshake.await?,
+    };
+    let protocol = response
+        .headers()
+        .get(http::header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let log = Arc::new(Mutex::new(WsLog {
+        url: shown.to_string(),
+        protocol: protocol.clone(),
+        connected: Utc::now(),
+        messages: Vec::new(),
+    }));
+    let (commands, commands_rx) = mpsc::unbounded_channel();
+    let (events_tx, events) = mpsc::unbounded_channel();
+    let close_timeout = timeout.unwrap_or(CLOSE_TIMEOUT);
+    let task = tokio::spawn(run(
+        stream,
+        commands_rx,
+        events_tx,
+        log.clone(),
+        close_timeout,
+    ));
+    Ok(WsSession {
+        url: shown.to_string(),
+        protocol,
+        headers: model::header_pairs(response.headers()),
+        commands,
+        events,
+        log,
+        task,
+    })
+}
+//the headers of a refused upgrade, to answer a digest challenge
+pub(crate) fn rejection(error: &ReqxError) -> Option<(u16, Vec<(String, String)>)> {
+    match error {
+        ReqxError::WebSocket(e) => match e.as_ref() {
+            tungstenite::Error::Http(response) => Some((
+                response.status().as_u16(),
+                model::header_pairs(response.headers()),
+            )),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+async fn run(
+    stream: Stream,
+    mut commands: UnboundedReceiver<WsFrame>,
+    events: UnboundedSender<WsEvent>,
+    log: Arc<Mutex<WsLog>>,
+    close_timeout: Duration,
+) {
+    let (mut sink, mut source) = stream.split();
+    //set once a close is sent, the server has until then to answer it
+    let mut closing = None;
+    let push = |direction, frame| {
+        log.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(direction, frame)
+    };
+    loop {
+        tokio::select! {
+            command = commands.recv() => {
+                //the session was dropped
+                let Some(frame) = command else {
+                    let _ = sink.close().await;
+                    return;
+                };
+                let close = matches!(frame, WsFrame::Close(_));
+                push(WsDirection::Sent, frame.clone());
+                if let Err(e) = sink.send(frame.into_message()).await {
+                    let _ = events.send(WsEvent::Failed(e.into()));
+                    return;
+                }
+                if close && closing.is_none() {
+                    closing = Some(tokio::time::Instant::now() + close_timeout);
+                }
+            }
+            _ = tokio::time::sleep_until(closing.unwrap_or_else(tokio::time::Instant::now)),
+                if closing.is_some() =>
+            {
+                let _ = events.send(WsEvent::Closed);
+                return;
+            }
+            incoming = source.next() => match incoming {
+                Some(Ok(message)) => {
+                    if let Some(frame) = WsFrame::from_message(message) {
+                        let _ = events.send(WsEvent::Message(push(WsDirection::Received, frame)));
+                    }
+                }
+                None
+                | Some(Err(tungstenite::Error::ConnectionClosed))
+                | Some(Err(tungstenite::Error::AlreadyClosed)) => {
+                    let _ = events.send(WsEvent::Closed);
+                    return;
+                }
+                Some(Err(e)) => {
+                    let _ = events.send(WsEvent::Failed(e.into()));
+                    return;
+                }
+            }
+        }
+    }
+}
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&BASE64.encode(bytes))
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(d)?;
+        BASE64.decode(text).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,411 @@
+use std::collections::BTreeSet;
+
+use crate::reqx::ReqxResponse;
+
+const INDENT: &str = "  ";
+const HEX_WIDTH: usize = 16;
+//html elements that never have an end tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "keygen", "link", "meta", "param",
+    "source", "wbr",
+];
+//html elements closed by the start of a sibling, `<li>one<li>two`
+const SIBLING_ELEMENTS: [&str; 8] = ["dd", "dt", "li", "option", "p", "td", "th", "tr"];
+//html elements whose content is not markup
+const RAW_ELEMENTS: [&str; 2] = ["script", "style"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    Json,
+    Xml,
+    Html,
+    Text,
+    Image,
+    Binary,
+}
+//a response body laid out for display, independent of how the ui draws it
+#[derive(Debug, Clone, PartialEq)]
+pub enum BodyView {
+    Json(Document),
+    Xml(Document),
+    Html(Document),
+    Text(String),
+    Hex(String),
+    //left for the window to decode, see yrl::ImagePreview
+    Image,
+}
+//formatted lines, indentation is kept as a depth so the ui can draw guides
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    pub lines: Vec<Line>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub depth: usize,
+    pub text: String,
+    //index of the line that closes this one, set on lines that can be folded
+    pub fold_end: Option<usize>,
+}
+enum Token<'a> {
+    Start(&'a str, &'a str),
+    SelfClosing(&'a str),
+    End(&'a str, &'a str),
+    Text(&'a str),
+    Other(&'a str),
+}
+impl Document {
+    //indexes of the lines that can be folded
+    pub fn foldable(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.fold_end.is_some())
+            .map(|(i, _)| i)
+    }
+    pub fn text(&self) -> String {
+        self.render(&BTreeSet::new())
+    }
+    //a folded line is joined with the line closing it, `"a": { … },`
+    pub fn render(&self, folded: &BTreeSet<usize>) -> String {
+        let mut out = String::new();
+        let mut i = 0;
+        while let Some(line) = self.lines.get(i) {
+            for _ in 0..line.depth {
+                out.push_str(INDENT);
+            }
+            out.push_str(&line.text);
+            match line.fold_end.filter(|_| folded.contains(&i)) {
+                Some(end) => {
+                    out.push_str(" … ");
+                    out.push_str(&self.lines[end].text);
+                    i = end + 1;
+                }
+                None => i += 1,
+            }
+            out.push('\n');
+        }
+        out
+    }
+    fn push(&mut self, depth: usize, text: impl Into<String>) -> usize {
+        self.lines.push(Line {
+            depth,
+            text: text.into(),
+            fold_end: None,
+        });
+        self.lines.len() - 1
+    }
+}
+pub fn view(response: &ReqxResponse) -> BodyView {
+    view_body(response.header("content-type"), &response.body)
+}
+//formatters that fail on a body fall back to text, or to hex when it is not utf8
+pub fn view_body(content_type: Option<&str>, body: &[u8]) -> BodyView {
+    let fallback = || match std::str::from_utf8(body) {
+        Ok(text) => BodyView::Text(text.to_string()),
+        Err(_) => BodyView::Hex(hex_dump(body)),
+    };
+    match detect(content_type, body) {
+        BodyKind::Json => json(body).map(BodyView::Json).unwrap_or_else(fallback),
+        BodyKind::Xml => xml(body).map(BodyView::Xml).unwrap_or_else(fallback),
+        BodyKind::Html => html(body).map(BodyView::Html).unwrap_or_else(fallback),
+        BodyKind::Image => BodyView::Image,
+        BodyKind::Text => fallback(),
+        BodyKind::Binary => BodyView::Hex(hex_dump(body)),
+    }
+}
+//the content type decides when it is specific, the body is sniffed otherwise
+pub fn detect(content_type: Option<&str>, body: &[u8]) -> BodyKind {
+    let essence = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    match essence.as_str() {
+        "application/json" | "text/json" => return BodyKind::Json,
+        "text/html" | "application/xhtml+xml" => return BodyKind::Html,
+        "application/xml" | "text/xml" | "image/svg+xml" => return BodyKind::Xml,
+        ct if ct.ends_with("+json") => return BodyKind::Json,
+        ct if ct.ends_with("+xml") => return BodyKind::Xml,
+        ct if ct.starts_with("image/") && is_image(body) => return BodyKind::Image,
+        ct if ct.starts_with("text/") => return BodyKind::Text,
+        _ => {}
+    }
+    if is_image(body) {
+        return BodyKind::Image;
+    }
+    let Ok(text) = std::str::from_utf8(body) else {
+        return BodyKind::Binary;
+    };
+    let start = text.trim_start();
+    let lower = start.get(..14).unwrap_or(start).to_ascii_lowercase();
+    if (start.starts_with('{') || start.starts_with('['))
+        && serde_json::from_str::<serde::de::IgnoredAny>(text).is_ok()
+    {
+        BodyKind::Json
+    } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        BodyKind::Html
+    } else if start.starts_with('<') {
+        BodyKind::Xml
+    } else if text
+        .chars()
+        .any(|c| c.is_control() && !c.is_ascii_whitespace())
+    {
+        BodyKind::Binary
+    } else {
+        BodyKind::Text
+    }
+}
+//the formats the image crate decodes, by their magic bytes
+fn is_image(body: &[u8]) -> bool {
+    body.starts_with(b"\x89PNG\r\n\x1a\n")
+        || body.starts_with(&[0xff, 0xd8, 0xff])
+        || body.starts_with(b"GIF87a")
+        || body.starts_with(b"GIF89a")
+        || (body.starts_with(b"RIFF") && body.get(8..12) == Some(b"WEBP"))
+        || body.starts_with(b"BM")
+        || body.starts_with(&[0, 0, 1, 0])
+}
+//pretty printed with keys in their original order and numbers as they were written,
+//None when the body is not valid json
+pub fn json(body: &[u8]) -> Option<Document> {
+    let text = std::str::from_utf8(body).ok()?;
+    serde_json::from_str::<serde::de::IgnoredAny>(text).ok()?;
+    let mut doc = Document::default();
+    let mut line = String::new();
+    let mut depth = 0;
+    let mut open = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                line.push(c);
+                while let Some(c) = chars.next() {
+                    line.push(c);
+                    match c {
+                        '\\' => line.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                let close = if c == '{' { '}' } else { ']' };
+                while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+                line.push(c);
+                //empty containers stay on one line
+                if chars.next_if_eq(&close).is_some() {
+                    line.push(close);
+                    continue;
+                }
+                open.push(doc.push(depth, std::mem::take(&mut line)));
+                depth += 1;
+            }
+            '}' | ']' => {
+                if !line.is_empty() {
+                    doc.push(depth, std::mem::take(&mut line));
+                }
+                depth = depth.saturating_sub(1);
+                if let Some(start) = open.pop() {
+                    doc.lines[start].fold_end = Some(doc.lines.len());
+                }
+                line.push(c);
+            }
+            ',' => {
+                line.push(c);
+                doc.push(depth, std::mem::take(&mut line));
+            }
+            ':' => line.push_str(": "),
+            c if c.is_ascii_whitespace() => {}
+            c => line.push(c),
+        }
+    }
+    if !line.is_empty() {
+        doc.push(depth, line);
+    }
+    Some(doc)
+}
+//indented one node per line, an element holding only text stays on one line;
+//None when the body is not utf8
+pub fn xml(body: &[u8]) -> Option<Document> {
+    markup(std::str::from_utf8(body).ok()?, false)
+}
+//like xml, but knows void elements, raw script and style content and unclosed tags
+pub fn html(body: &[u8]) -> Option<Document> {
+    markup(std::str::from_utf8(body).ok()?, true)
+}
+fn markup(text: &str, html: bool) -> Option<Document> {
+    let tokens = tokenize(text, html);
+    let mut doc = Document::default();
+    let mut open: Vec<(&str, usize)> = Vec::new();
+    let mut i = 0;
+    while let Some(token) = tokens.get(i) {
+        let depth = open.len();
+        match token {
+            Token::Start(name, tag) if html && is_void(name) => {
+                doc.push(depth, *tag);
+            }
+            Token::Start(name, tag) => {
+                match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Some(Token::End(end, close)), _) if end.eq_ignore_ascii_case(name) => {
+                        doc.push(depth, format!("{tag}{close}"));
+                        i += 2;
+                        continue;
+                    }
+                    (Some(Token::Text(inner)), Some(Token::End(end, close)))
+                        if end.eq_ignore_ascii_case(name) && !inner.trim().contains('\n') =>
+                    {
+                        doc.push(depth, format!("{tag}{}{close}", inner.trim()));
+                        i += 3;
+                        continue;
+                    }
+                    _ => {}
+                }
+                let closes_sibling = html
+                    && SIBLING_ELEMENTS
+                        .iter()
+                        .any(|s| s.eq_ignore_ascii_case(name))
+                    && open
+                        .last()
+                        .is_some_and(|(n, _)| n.eq_ignore_ascii_case(name));
+                if closes_sibling {
+                    open.pop();
+                }
+                open.push((name, doc.push(open.len(), *tag)));
+            }
+            Token::End(name, tag) => {
+                //html leaves elements like <p> and <li> open, they close with their parent
+                let Some(at) = open.iter().rposition(|(n, _)| n.eq_ignore_ascii_case(name)) else {
+                    doc.push(depth, *tag);
+                    i += 1;
+                    continue;
+                };
+                let end = doc.push(at, *tag);
+                doc.lines[open[at].1].fold_end = Some(end);
+                open.truncate(at);
+            }
+            Token::SelfClosing(tag) | Token::Other(tag) => {
+                doc.push(depth, *tag);
+            }
+            Token::Text(text) => {
+                for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    doc.push(depth, line);
+                }
+            }
+        }
+        i += 1;
+    }
+    Some(doc)
+}
+fn tokenize(text: &str, html: bool) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if start > 0 {
+            if !rest[..start].trim().is_empty() {
+                tokens.push(Token::Text(&rest[..start]));
+            }
+            rest = &rest[start..];
+        }
+        let (token, len) = if rest.starts_with("<!--") {
+            let len = rest.find("-->").map_or(rest.len(), |end| end + 3);
+            (Token::Other(&rest[..len]), len)
+        } else if rest.starts_with("<![CDATA[") {
+            let len = rest.find("]]>").map_or(rest.len(), |end| end + 3);
+            (Token::Other(&rest[..len]), len)
+        } else if rest.starts_with("<?") {
+            let len = rest.find("?>").map_or(rest.len(), |end| end + 2);
+            (Token::Other(&rest[..len]), len)
+        } else if rest.starts_with("<!") {
+            let len = tag_end(rest, true);
+            (Token::Other(&rest[..len]), len)
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let len = tag_end(rest, false);
+            let name = tag_name(after);
+            (Token::End(name, &rest[..len]), len)
+        } else {
+            let len = tag_end(rest, false);
+            let tag = &rest[..len];
+            let name = tag_name(&rest[1..]);
+            if name.is_empty() {
+                //a lone `<` in text, not a tag
+                (Token::Text(&rest[..1]), 1)
+            } else if tag.ends_with("/>") {
+                (Token::SelfClosing(tag), len)
+            } else {
+                (Token::Start(name, tag), len)
+            }
+        };
+        tokens.push(token);
+        rest = &rest[len..];
+        //script and style hold code, which is kept as written up to the end tag
+        if let (true, Some(Token::Start(name, _))) = (html, tokens.last()) {
+            if RAW_ELEMENTS
+                .iter()
+                .any(|raw| raw.eq_ignore_ascii_case(name))
+            {
+                let close = format!("</{}", name.to_ascii_lowercase());
+                let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                if !rest[..end].trim().is_empty() {
+                    tokens.push(Token::Text(&rest[..end]));
+                }
+                rest = &rest[end..];
+            }
+        }
+    }
+    tokens
+}
+//length of the tag at the start of `text`, `>` inside quoted attribute values does not end
+//it and neither does one inside a doctype internal subset
+fn tag_end(text: &str, brackets: bool) -> usize {
+    let mut quote = None;
+    let mut nested = 0;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') if brackets => nested += 1,
+            (None, ']') if brackets => nested -= 1,
+            (None, '>') if nested <= 0 => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+fn tag_name(text: &str) -> &str {
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(text.len());
+    &text[..end]
+}
+fn is_void(name: &str) -> bool {
+    VOID_ELEMENTS
+        .iter()
+        .any(|void| void.eq_ignore_ascii_case(name))
+}
+//`00000010  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 0a 00 00 00  |Hello, world....|`
+pub fn hex_dump(body: &[u8]) -> String {
+    let mut out = String::with_capacity(body.len() * 4 + body.len() / HEX_WIDTH * 12);
+    for (row, chunk) in body.chunks(HEX_WIDTH).enumerate() {
+        out.push_str(&format!("{:08x} ", row * HEX_WIDTH));
+        for i in 0..HEX_WIDTH {
+            if i % 8 == 0 {
+                out.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => out.push_str(&format!("{byte:02x} ")),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str(" |");
+        out.extend(chunk.iter().map(|&b| match b {
+            0x20..=0x7e => b as char,
+            _ => '.',
+        }));
+        out.push_str("|\n");
+    }
+    out
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use argus::reqx::{AwsSigV4, DigestChallenge, Reqx, ReqxAuth, ReqxBody, ReqxRequest};
+use chrono::{TimeZone, Utc};
+use http::Method;
+use sha2::{Digest, Sha256};
+
+//the credentials and date of the aws sigv4 test suite
+fn suite() -> AwsSigV4 {
+    AwsSigV4 {
+        access_key: "AKIDEXAMPLE".into(),
+        secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+        session_token: None,
+        region: "us-east-1".into(),
+        service: "service".into(),
+    }
+}
+
+fn signed(mut req: reqwest::Request) -> String {
+    let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+    suite().sign(&mut req, now).unwrap();
+    assert_eq!(req.headers()["x-amz-date"], "20150830T123600Z");
+    req.headers()["authorization"].to_str().unwrap().to_string()
+}
+
+fn get(url: &str) -> reqwest::Request {
+    reqwest::Request::new(Method::GET, url.parse().unwrap())
+}
+
+fn authorization(signed_headers: &str, signature: &str) -> String {
+    format!(
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders={signed_headers}, Signature={signature}"
+    )
+}
+
+#[test]
+fn sigv4_get_vanilla() {
+    assert_eq!(
+        signed(get("https://example.amazonaws.com/")),
+        authorization(
+            "host;x-amz-date",
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        )
+    );
+}
+
+#[test]
+fn sigv4_get_vanilla_query_order() {
+    assert_eq!(
+        signed(get(
+            "https://example.amazonaws.com/?Param1=value2&Param1=Value1"
+        )),
+        authorization(
+            "host;x-amz-date",
+            "eedbc4e291e521cf13422ffca22be7d2eb8146eecf653089df300a15b2382bd1"
+        )
+    );
+    assert_eq!(
+        signed(get(
+            "https://example.amazonaws.com/?Param2=value2&Param1=value1"
+        )),
+        authorization(
+            "host;x-amz-date",
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        )
+    );
+}
+
+#[test]
+fn sigv4_post_x_www_form_urlencoded() {
+    let mut req = reqwest::Request::new(
+        Method::POST,
+        "https://example.amazonaws.com/".parse().unwrap(),
+    );
+    req.headers_mut().insert(
+        "content-type",
+        "application/x-www-form-urlencoded".parse().unwrap(),
+    );
+    *req.body_mut() = Some("Param1=value1".into());
+    assert_eq!(
+        signed(req),
+        authorization(
+            "content-type;host;x-amz-date",
+            "ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a"
+        )
+    );
+}
+
+//rfc 7616 section 3.9.1
+const RFC_CHALLENGE: &str = r#"realm="http-auth@example.org", qop="auth, auth-int", nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+const RFC_CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+fn rfc_response(algorithm: &str) -> String {
+    let header = format!("Digest {RFC_CHALLENGE}, algorithm={algorithm}");
+    let challenge = DigestChallenge::find([header.as_str()]).unwrap();
+    challenge.respond(
+        "Mufasa",
+        "Circle of Life",
+        "GET",
+        "/dir/index.html",
+        b"",
+        RFC_CNONCE,
+    )
+}
+
+#[test]
+fn digest_rfc_7616_md5() {
+    assert_eq!(
+        rfc_response("MD5"),
+        concat!(
+            r#"Digest username="Mufasa", realm="http-auth@example.org", "#,
+            r#"nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", uri="/dir/index.html", "#,
+            r#"algorithm=MD5, response="8ca523f5e9506fed4657c9700eebdbec", qop=auth, nc=00000001, "#,
+            r#"cnonce="f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ", "#,
+            r#"opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#,
+        )
+    );
+}
+
+#[test]
+fn digest_rfc_7616_sha256() {
+    assert!(rfc_response("SHA-256").contains(
+        r#"algorithm=SHA-256, response="753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1""#
+    ));
+}
+
+#[test]
+fn digest_challenges_are_found_among_others() {
+    let header = r#"Basic realm="a, b", Digest realm="r", nonce="n", algorithm=UNKNOWN"#;
+    assert!(DigestChallenge::find([header]).is_none());
+    let header = r#"Basic realm="x", Digest realm="r", nonce="n""#;
+    let response = DigestChallenge::find([header])
+        .unwrap()
+        .respond("u", "p", "GET", "/", b"", "c");
+    assert!(response.starts_with(r#"Digest username="u", realm="r", nonce="n", uri="/""#));
+}
+
+fn param<'a>(header: &'a str, name: &str) -> &'a str {
+    header
+        .split(", ")
+        .find_map(|p| p.strip_prefix(&format!("{name}=")))
+        .map(|v| v.trim_matches('"'))
+        .unwrap_or_default()
+}
+
+fn hash(data: String) -> String {
+    Sha256::digest(data.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[tokio::test]
+async fn digest_answers_a_401_challenge() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counted = attempts.clone();
+    let addr = common::serve(move |req| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        let Some(auth) = req.headers().get("authorization") else {
+            return http::Response::builder()
+                .status(401)
+                .header(
+                    "www-authenticate",
+                    r#"Digest realm="argus", qop="auth", nonce="NONCE", algorithm=SHA-256, opaque="OPQ""#,
+                )
+                .body(Default::default())
+                .unwrap();
+        };
+        let auth = auth.to_str().unwrap();
+        let ha1 = hash(String::from("ann:argus:s3cret"));
+        let ha2 = hash(format!("{}:{}", req.method(), req.uri()));
+        let expected = hash(format!(
+            "{ha1}:NONCE:{}:{}:auth:{ha2}",
+            param(auth, "nc"),
+            param(auth, "cnonce")
+        ));
+        let ok = param(auth, "response") == expected
+            && req.uri() == param(auth, "uri")
+            && param(auth, "opaque") == "OPQ";
+        common::response(if ok { 200 } else { 403 }, "text/plain", req.into_body())
+    })
+    .await;
+    let mut request = ReqxRequest::new(Method::POST, format!("http://{addr}/dir?a=1"));
+    request.authentication = ReqxAuth::Digest {
+        username: "ann".into(),
+        password: "s3cret".into(),
+    };
+    request.body = ReqxBody::Text("payload".into());
+    let response = Reqx::default().send(&request).await.unwrap();
+    assert_eq!(response.status, 200);
+    //the body goes again with the retry
+    assert_eq!(response.body, b"payload");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
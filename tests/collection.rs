@@ -0,0 +1,221 @@
+mod common;
+
+use std::fs;
+
+use argus::{
+    collection::{Collection, CollectionError},
+    reqx::{
+        ApiKeyLocation, AwsSigV4, FormPart, GraphQlQuery, OAuth2Config, OAuth2Grant, ReqxAuth,
+        ReqxBody, ReqxRequest,
+    },
+};
+use http::Method;
+use serde_json::json;
+
+fn sample() -> Collection {
+    let mut collection = Collection::new("api");
+    collection.add_folder("", "users").unwrap();
+    let get = ReqxRequest::new(Method::GET, "https://example.com/users");
+    let post = ReqxRequest::new(Method::POST, "https://example.com/users");
+    collection.add_request("users", "list", get).unwrap();
+    collection.add_request("users", "create", post).unwrap();
+    collection
+        .add_request(
+            "",
+            "health",
+            ReqxRequest::new(Method::GET, "https://example.com"),
+        )
+        .unwrap();
+    collection
+}
+
+fn oauth(grant: OAuth2Grant, client_secret: Option<&str>) -> ReqxAuth {
+    ReqxAuth::OAuth2(OAuth2Config {
+        grant,
+        token_url: "https://auth.test/token".into(),
+        client_id: "cli".into(),
+        client_secret: client_secret.map(String::from),
+        scope: Some("read write".into()),
+    })
+}
+
+#[test]
+fn round_trips() {
+    let dir = common::temp_dir("collection-round-trip");
+    let mut collection = sample();
+    let auths = [
+        ReqxAuth::None,
+        ReqxAuth::Basic {
+            username: "ann".into(),
+            password: Some("{{password}}".into()),
+        },
+        ReqxAuth::Basic {
+            username: "ann".into(),
+            password: None,
+        },
+        ReqxAuth::Bearer("{{token}}".into()),
+        oauth(OAuth2Grant::ClientCredentials, Some("{{secret}}")),
+        oauth(
+            OAuth2Grant::Password {
+                username: "ann".into(),
+                password: "pw".into(),
+            },
+            None,
+        ),
+        oauth(
+            OAuth2Grant::AuthorizationCode {
+                auth_url: "https://auth.test/authorize".into(),
+                redirect_port: 8912,
+            },
+            None,
+        ),
+        ReqxAuth::Digest {
+            username: "ann".into(),
+            password: "pw".into(),
+        },
+        ReqxAuth::ApiKey {
+            name: "X-Key".into(),
+            value: "k".into(),
+            location: ApiKeyLocation::Header,
+        },
+        ReqxAuth::ApiKey {
+            name: "key".into(),
+            value: "k".into(),
+            location: ApiKeyLocation::Query,
+        },
+        ReqxAuth::AwsSigV4(AwsSigV4 {
+            access_key: "AK".into(),
+            secret_key: "SK".into(),
+            session_token: Some("ST".into()),
+            region: "eu-west-1".into(),
+            service: "s3".into(),
+        }),
+    ];
+    let bodies = [
+        ReqxBody::Empty,
+        ReqxBody::Text("{\"a\": \"multi\nline\"}".into()),
+        ReqxBody::Bytes(vec![0, 1, 254, 255]),
+        ReqxBody::Form(vec![("b".into(), "2".into()), ("a".into(), "1".into())]),
+        ReqxBody::Multipart(vec![
+            FormPart::text("title", "cat"),
+            FormPart::file("photo", "/tmp/cat.png")
+                .filename("c.png")
+                .content_type("image/png"),
+        ]),
+        ReqxBody::GraphQl(GraphQlQuery::new(
+            "query Q($after: ID) { items(after: $after) { id } }",
+        )),
+        //toml has no null, the variables have to survive it
+        ReqxBody::GraphQl(
+            GraphQlQuery::new(
+                "query Q($after: ID, $n: Int) { items(after: $after, first: $n) { id } }",
+            )
+            .with_variables(json!({"after": null, "n": 10, "tags": ["a", null], "f": 1.5}))
+            .with_operation("Q"),
+        ),
+    ];
+    collection.add_folder("", "kinds").unwrap();
+    for (i, authentication) in auths.into_iter().enumerate() {
+        let mut request = ReqxRequest::new(Method::PUT, format!("https://example.com/auth/{i}"));
+        request.authentication = authentication;
+        request.headers = vec![
+            ("X-Dup".into(), "1".into()),
+            ("x-dup".into(), "2".into()),
+            ("Accept".into(), "*/*".into()),
+        ];
+        collection
+            .add_request("kinds", &format!("auth {i}"), request)
+            .unwrap();
+    }
+    for (i, body) in bodies.into_iter().enumerate() {
+        let mut request = ReqxRequest::new(Method::POST, "{{base}}/body");
+        request.body = body;
+        collection
+            .add_request("kinds", &format!("body {i}"), request)
+            .unwrap();
+    }
+    collection.save(&dir).unwrap();
+    assert_eq!(Collection::load(&dir).unwrap(), collection);
+}
+
+#[test]
+fn a_folder_and_a_request_cannot_share_a_file() {
+    let mut collection = sample();
+    let request = || ReqxRequest::new(Method::GET, "https://example.com");
+    collection.add_request("", "a", request()).unwrap();
+    assert!(matches!(
+        collection.add_folder("", "a.toml"),
+        Err(CollectionError::AlreadyExists(_))
+    ));
+    collection.add_folder("", "b.toml").unwrap();
+    assert!(matches!(
+        collection.add_request("", "b", request()),
+        Err(CollectionError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        collection.rename("a", "b.toml"),
+        Err(CollectionError::AlreadyExists(_))
+    ));
+    assert_eq!(collection.root.free_name("b"), "b (2)");
+
+    //the maps are public, save refuses what add_folder would have
+    collection.root.requests.insert("b".into(), request());
+    let error = collection
+        .save(common::temp_dir("collection-clash"))
+        .unwrap_err();
+    assert!(matches!(error, CollectionError::AlreadyExists(path) if path == "b.toml"));
+}
+
+#[test]
+fn refuses_a_non_empty_directory_that_is_not_a_collection() {
+    let dir = common::temp_dir("collection-foreign");
+    fs::write(dir.join("notes.toml"), "keep = true").unwrap();
+    let error = sample().save(&dir).unwrap_err();
+    assert!(matches!(error, CollectionError::NotACollection(_)));
+    assert!(dir.join("notes.toml").exists());
+    assert!(!dir.join("collection.toml").exists());
+}
+
+#[test]
+fn only_deletes_what_it_wrote() {
+    let dir = common::temp_dir("collection-manifest");
+    let mut collection = sample();
+    collection.save(&dir).unwrap();
+    //files that were never part of the collection
+    fs::write(dir.join("unrelated.toml"), "keep = true").unwrap();
+    fs::create_dir(dir.join("docs")).unwrap();
+    fs::write(dir.join("docs/readme.md"), "keep").unwrap();
+    fs::write(dir.join("users/extra.txt"), "keep").unwrap();
+    collection.remove("health").unwrap();
+    collection.remove("users").unwrap();
+    collection.save(&dir).unwrap();
+    assert!(!dir.join("health.toml").exists());
+    assert!(!dir.join("users/list.toml").exists());
+    assert!(!dir.join("users/create.toml").exists());
+    //the folder holds a file the collection did not write, so it stays
+    assert!(dir.join("users/extra.txt").exists());
+    assert!(dir.join("unrelated.toml").exists());
+    assert!(dir.join("docs/readme.md").exists());
+    fs::remove_file(dir.join("users/extra.txt")).unwrap();
+    collection
+        .add_request(
+            "",
+            "health",
+            ReqxRequest::new(Method::GET, "https://example.com"),
+        )
+        .unwrap();
+    collection.save(&dir).unwrap();
+    collection.remove("health").unwrap();
+    collection.save(&dir).unwrap();
+    assert!(!dir.join("health.toml").exists());
+}
+
+#[test]
+fn empty_folders_it_wrote_are_removed() {
+    let dir = common::temp_dir("collection-folders");
+    let mut collection = sample();
+    collection.save(&dir).unwrap();
+    collection.remove("users").unwrap();
+    collection.save(&dir).unwrap();
+    assert!(!dir.join("users").exists());
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use std::{net::SocketAddr, time::Duration};
+
+use argus::reqx::{HttpVersion, ProxySettings, Reqx, ReqxConfig, ReqxError, ReqxRequest};
+use http::Method;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+fn config(http_version: HttpVersion) -> ReqxConfig {
+    ReqxConfig {
+        accept_invalid_certs: true,
+        http_version,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn invalid_certs_are_accepted_for_another_hostname() {
+    let addr = common::tls_server().await;
+    //the certificate is for localhost and self signed
+    let request = ReqxRequest::new(Method::GET, format!("https://{addr}/"));
+    let response = Reqx::new(config(HttpVersion::Auto))
+        .unwrap()
+        .send(&request)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.version, "HTTP/1.1");
+    assert_eq!(response.body, b"ok");
+    assert!(Reqx::default().send(&request).await.is_err());
+}
+
+#[tokio::test]
+async fn required_http2_fails_when_alpn_does_not_pick_it() {
+    let addr = common::tls_server().await;
+    let request = ReqxRequest::new(Method::GET, format!("https://{addr}/"));
+    let error = Reqx::new(config(HttpVersion::Http2))
+        .unwrap()
+        .send(&request)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ReqxError::Http2NotNegotiated(_)), "{error}");
+}
+
+fn proxied(proxy: SocketAddr, timeout: Option<Duration>) -> Reqx {
+    Reqx::new(ReqxConfig {
+        timeout,
+        proxy: ProxySettings {
+            http: Some(format!("http://{proxy}")),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn proxied_sends_use_the_configured_timeout() {
+    let proxy = common::serve(common::echo).await;
+    let request = ReqxRequest::new(Method::GET, "http://argus.test/through");
+    let response = proxied(proxy, Some(Duration::from_secs(5)))
+        .send(&request)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    let echo: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(echo["uri"], "http://argus.test/through");
+
+    //a proxy that accepts and never answers
+    let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = silent.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = silent.accept().await {
+            held.push(stream);
+        }
+    });
+    let timeout = Duration::from_millis(300);
+    let error = proxied(addr, Some(timeout))
+        .send(&request)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, ReqxError::Timeout(t) if t == timeout),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn the_proxy_can_be_switched_on_a_shared_reqx() {
+    let proxy =
+        common::serve(|req| common::response(200, "text/plain", req.uri().to_string())).await;
+    let direct = common::serve(|_| common::response(200, "text/plain", "direct")).await;
+    let reqx = std::sync::Arc::new(Reqx::default());
+    let request = ReqxRequest::new(Method::GET, format!("http://{direct}/x"));
+    let body = |response: argus::reqx::ReqxResponse| String::from_utf8(response.body).unwrap();
+    assert_eq!(body(reqx.send(&request).await.unwrap()), "direct");
+    let shared = reqx.clone();
+    tokio::spawn(async move {
+        shared
+            .set_proxy(ProxySettings {
+                http: Some(format!("http://{proxy}")),
+                ..Default::default()
+            })
+            .unwrap();
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        body(reqx.send(&request).await.unwrap()),
+        format!("http://{direct}/x")
+    );
+    reqx.set_proxy(ProxySettings::default()).unwrap();
+    assert_eq!(body(reqx.send(&request).await.unwrap()), "direct");
+}
+
+#[tokio::test]
+async fn the_timeout_covers_the_whole_redirect_chain() {
+    //every hop takes 200ms and redirects to the next, the fourth answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]).into_owned();
+                let hop: u32 = head
+                    .split(' ')
+                    .nth(1)
+                    .and_then(|path| path.trim_start_matches('/').parse().ok())
+                    .unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                let response = match hop {
+                    4 => String::from("HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok"),
+                    _ => format!(
+                        "HTTP/1.1 302 Found\r\nlocation: /{}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                        hop + 1
+                    ),
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    let request = ReqxRequest::new(Method::GET, format!("http://{addr}/1"));
+    let reqx = |timeout| {
+        Reqx::new(ReqxConfig {
+            timeout: Some(timeout),
+            ..Default::default()
+        })
+        .unwrap()
+    };
+    let response = reqx(Duration::from_secs(5)).send(&request).await.unwrap();
+    assert_eq!(response.redirects.len(), 3);
+
+    //each hop is well inside the timeout, the chain is not
+    let timeout = Duration::from_millis(500);
+    let started = std::time::Instant::now();
+    let error = reqx(timeout).send(&request).await.unwrap_err();
+    assert!(
+        matches!(error, ReqxError::Timeout(t) if t == timeout),
+        "{error}"
+    );
+    assert!(started.elapsed() < Duration::from_millis(700));
+}
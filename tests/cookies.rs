@@ -0,0 +1,314 @@
+mod common;
+
+use argus::{
+    cookies::{Cookie, CookieJar, CookieStore, SameSite},
+    reqx::{Reqx, ReqxRequest},
+};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use http::Method;
+use url::Url;
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+}
+
+fn parse(set_cookie: &str, from: &str) -> Option<Cookie> {
+    Cookie::parse(set_cookie, &url(from), now())
+}
+
+fn sent(cookie: &Cookie, to: &str) -> bool {
+    cookie.matches(&url(to), &Method::GET, false, now())
+}
+
+#[test]
+fn set_cookie_attributes_are_parsed() {
+    let cookie = parse(
+        " sid = a=b ; Path=/api; HttpOnly; SameSite=Lax; unknown=1",
+        "http://Example.com/x/y",
+    )
+    .unwrap();
+    assert_eq!(
+        (cookie.name.as_str(), cookie.value.as_str()),
+        ("sid", "a=b")
+    );
+    assert_eq!(cookie.domain, "example.com");
+    assert!(cookie.host_only);
+    assert_eq!(cookie.path, "/api");
+    assert!(cookie.http_only && !cookie.secure);
+    assert_eq!(cookie.same_site, Some(SameSite::Lax));
+    assert_eq!(cookie.expires, None);
+
+    //without a path the directory of the request path is used, a path not starting with /
+    //is ignored
+    assert_eq!(parse("a=1", "http://h.test/x/y").unwrap().path, "/x");
+    assert_eq!(parse("a=1; Path=rel", "http://h.test/").unwrap().path, "/");
+    for malformed in ["novalue", "=1", " =1; Path=/"] {
+        assert_eq!(parse(malformed, "http://h.test/"), None, "{malformed}");
+    }
+}
+
+#[test]
+fn domains_match_subdomains_unless_host_only() {
+    let host_only = parse("a=1", "http://example.com/").unwrap();
+    assert!(sent(&host_only, "http://example.com/"));
+    assert!(!sent(&host_only, "http://www.example.com/"));
+
+    let domain = parse("a=1; Domain=.Example.com", "http://www.example.com/").unwrap();
+    assert!(!domain.host_only);
+    assert_eq!(domain.domain, "example.com");
+    assert!(sent(&domain, "http://example.com/"));
+    assert!(sent(&domain, "http://api.example.com/"));
+    assert!(!sent(&domain, "http://badexample.com/"));
+    assert!(!sent(&domain, "http://example.org/"));
+
+    //a domain the host is not in, a bare top level domain, and a parent of an ip
+    assert_eq!(parse("a=1; Domain=other.com", "http://example.com/"), None);
+    assert_eq!(parse("a=1; Domain=com", "http://example.com/"), None);
+    assert_eq!(parse("a=1; Domain=0.1", "http://127.0.0.1/"), None);
+    assert!(parse("a=1; Domain=localhost", "http://localhost/").is_some());
+}
+
+#[test]
+fn paths_match_on_segment_boundaries() {
+    let cookie = parse("a=1; Path=/api", "http://h.test/").unwrap();
+    for path in ["/api", "/api/", "/api/users"] {
+        assert!(sent(&cookie, &format!("http://h.test{path}")), "{path}");
+    }
+    for path in ["/", "/apis", "/other/api"] {
+        assert!(!sent(&cookie, &format!("http://h.test{path}")), "{path}");
+    }
+    let slash = parse("a=1; Path=/api/", "http://h.test/").unwrap();
+    assert!(sent(&slash, "http://h.test/api/x"));
+    assert!(!sent(&slash, "http://h.test/api"));
+}
+
+#[test]
+fn secure_cookies_need_a_secure_origin() {
+    assert_eq!(parse("a=1; Secure", "http://h.test/"), None);
+    let secure = parse("a=1; Secure", "https://h.test/").unwrap();
+    assert!(sent(&secure, "https://h.test/"));
+    assert!(sent(&secure, "wss://h.test/"));
+    assert!(!sent(&secure, "http://h.test/"));
+
+    assert_eq!(parse("__Secure-a=1", "https://h.test/"), None);
+    assert!(parse("__Secure-a=1; Secure", "https://h.test/").is_some());
+    assert!(parse("__Host-a=1; Secure; Path=/", "https://h.test/").is_some());
+    assert_eq!(
+        parse(
+            "__Host-a=1; Secure; Path=/; Domain=h.test",
+            "https://h.test/"
+        ),
+        None
+    );
+    assert_eq!(
+        parse("__Host-a=1; Secure; Path=/x", "https://h.test/"),
+        None
+    );
+
+    //an insecure origin cannot replace a secure cookie of the same name
+    let mut jar = CookieJar::default();
+    jar.store(&url("https://h.test/"), ["a=secure; Secure"]);
+    jar.store(&url("http://h.test/"), ["a=plain"]);
+    assert_eq!(jar.get("h.test", "/", "a").unwrap().value, "secure");
+}
+
+#[test]
+fn same_site_limits_cross_site_requests() {
+    assert_eq!(parse("a=1; SameSite=None", "https://h.test/"), None);
+    let to = url("https://h.test/");
+    let cross = |set_cookie: &str, method: Method| {
+        parse(set_cookie, "https://h.test/")
+            .unwrap()
+            .matches(&to, &method, true, now())
+    };
+    assert!(!cross("a=1; SameSite=Strict", Method::GET));
+    assert!(cross("a=1; SameSite=Lax", Method::GET));
+    assert!(!cross("a=1; SameSite=Lax", Method::POST));
+    assert!(!cross("a=1", Method::POST));
+    assert!(cross("a=1; SameSite=None; Secure", Method::POST));
+    //same site requests get them all
+    let strict = parse("a=1; SameSite=Strict", "https://h.test/").unwrap();
+    assert!(strict.matches(&to, &Method::POST, false, now()));
+}
+
+#[test]
+fn max_age_wins_over_expires() {
+    let expires = parse(
+        "a=1; Expires=Wed, 21 Oct 2026 07:28:00 GMT",
+        "http://h.test/",
+    )
+    .unwrap();
+    assert_eq!(
+        expires.expires,
+        Some(Utc.with_ymd_and_hms(2026, 10, 21, 7, 28, 0).unwrap())
+    );
+    for date in [
+        "Wednesday, 21-Oct-26 07:28:00 GMT",
+        "Wed, 21-Oct-2026 07:28:00 GMT",
+        "Wed Oct 21 07:28:00 2026",
+    ] {
+        let cookie = parse(&format!("a=1; Expires={date}"), "http://h.test/").unwrap();
+        assert_eq!(cookie.expires, expires.expires, "{date}");
+    }
+    let both = parse(
+        "a=1; Max-Age=60; Expires=Wed, 21 Oct 2026 07:28:00 GMT",
+        "http://h.test/",
+    )
+    .unwrap();
+    assert_eq!(both.expires, Some(now() + Duration::seconds(60)));
+    assert!(!both.is_expired(now()));
+    assert!(both.is_expired(now() + Duration::seconds(60)));
+    assert!(!both.matches(
+        &url("http://h.test/"),
+        &Method::GET,
+        false,
+        now() + Duration::seconds(61)
+    ));
+    let gone = parse("a=1; Max-Age=0", "http://h.test/").unwrap();
+    assert!(gone.is_expired(now()));
+    //an unparsable expiry leaves a session cookie
+    let session = parse("a=1; Expires=soon; Max-Age=x", "http://h.test/").unwrap();
+    assert_eq!(session.expires, None);
+}
+
+#[test]
+fn expired_cookies_are_evicted() {
+    let mut jar = CookieJar::default();
+    let to = url("http://h.test/");
+    jar.store(&to, ["a=1", "b=2; Max-Age=3600"]);
+    assert_eq!(jar.header(&to, &Method::GET, false).unwrap(), "a=1; b=2");
+    //a server deletes a cookie by sending it already expired
+    jar.store(&to, ["a=; Max-Age=0"]);
+    assert_eq!(jar.header(&to, &Method::GET, false).unwrap(), "b=2");
+    jar.store(&to, ["b=; Expires=Thu, 01 Jan 1970 00:00:00 GMT"]);
+    assert!(jar.is_empty());
+    assert_eq!(jar.header(&to, &Method::GET, false), None);
+
+    let mut stale = Cookie::new("old", "1", "h.test");
+    stale.expires = Some(Utc::now() - Duration::seconds(1));
+    let mut jar = CookieJar::default();
+    jar.insert(stale);
+    assert!(jar.is_empty());
+
+    let mut jar = CookieJar::default();
+    jar.insert(Cookie::new("c", "1", "h.test"));
+    jar.insert(Cookie::new("c", "1", "api.h.test"));
+    jar.insert(Cookie::new("c", "1", "other.test"));
+    jar.remove_domain("h.test");
+    assert_eq!(
+        jar.iter().map(|c| c.domain.as_str()).collect::<Vec<_>>(),
+        ["other.test"]
+    );
+}
+
+#[test]
+fn longer_paths_and_older_cookies_go_first() {
+    let mut jar = CookieJar::default();
+    let from = url("http://h.test/");
+    jar.store(&from, ["a=1", "b=2; Path=/x"]);
+    jar.store(&from, ["c=3"]);
+    let mut late = Cookie::new("d", "4", "h.test");
+    late.created = Utc::now() + Duration::seconds(10);
+    jar.insert(late);
+    assert_eq!(
+        jar.header(&url("http://h.test/x/y"), &Method::GET, false)
+            .unwrap(),
+        "b=2; a=1; c=3; d=4"
+    );
+    //a replaced cookie keeps its place
+    jar.store(&from, ["a=5"]);
+    assert_eq!(
+        jar.header(&from, &Method::GET, false).unwrap(),
+        "a=5; c=3; d=4"
+    );
+}
+
+#[test]
+fn stores_and_cookies_txt_round_trip() {
+    let mut store = CookieStore::default();
+    store.jar_mut(None).store(
+        &url("https://h.test/"),
+        ["a=1; HttpOnly; Secure", "b=2; Domain=h.test"],
+    );
+    store
+        .jar_mut(Some("dev"))
+        .store(&url("http://dev.test/"), ["c=3; Max-Age=3600"]);
+    store.jar_mut(Some("empty"));
+
+    let dir = common::temp_dir("cookies");
+    let path = dir.join("cookies.toml");
+    assert_eq!(CookieStore::load(&path).unwrap(), CookieStore::default());
+    store.save(&path).unwrap();
+    let loaded = CookieStore::load(&path).unwrap();
+    //empty jars are not written
+    assert!(loaded.jar(Some("empty")).is_none());
+    assert_eq!(loaded.jar(None), store.jar(None));
+    assert_eq!(loaded.jar(Some("dev")), store.jar(Some("dev")));
+
+    let text = store.jar(None).unwrap().to_netscape();
+    assert!(text.contains("#HttpOnly_h.test\tFALSE\t/\tTRUE\t0\ta\t1\n"));
+    assert!(text.contains(".h.test\tTRUE\t/\tFALSE\t0\tb\t2\n"));
+    let mut imported = CookieJar::default();
+    assert_eq!(imported.import_netscape(&text).unwrap(), 2);
+    let names = |jar: &CookieJar| {
+        jar.iter()
+            .map(|c| {
+                (
+                    c.name.clone(),
+                    c.domain.clone(),
+                    c.host_only,
+                    c.secure,
+                    c.http_only,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&imported), names(store.jar(None).unwrap()));
+    assert!(imported.import_netscape("h.test\tFALSE\t/").is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn a_cookie_set_on_a_redirect_is_sent_on_the_next_hop() {
+    let addr = common::serve(|req| match req.uri().path() {
+        "/login" => http::Response::builder()
+            .status(302)
+            .header("location", "/home")
+            .header("set-cookie", "session=abc; Path=/; HttpOnly")
+            .body(Default::default())
+            .unwrap(),
+        _ => common::response(
+            200,
+            "text/plain",
+            req.headers()
+                .get("cookie")
+                .map(|c| c.to_str().unwrap().to_string())
+                .unwrap_or_default(),
+        ),
+    })
+    .await;
+    let reqx = Reqx::default();
+    let response = reqx
+        .send(&ReqxRequest::new(
+            Method::GET,
+            format!("http://{addr}/login"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.redirects.len(), 1);
+    assert_eq!(response.body, b"session=abc");
+    let cookies = reqx.cookies().lock().unwrap();
+    assert_eq!(
+        cookies
+            .jar(None)
+            .unwrap()
+            .get("127.0.0.1", "/", "session")
+            .unwrap()
+            .value,
+        "abc"
+    );
+}
@@ -0,0 +1,47 @@
+use argus::reqx::{curl, ReqxAuth};
+use http::Method;
+
+fn body(command: &curl::CurlCommand) -> String {
+    let body = command.data.body.as_ref().unwrap().as_bytes().unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[test]
+fn ansi_c_quoted_data_is_decoded() {
+    //as chrome writes it with "copy as cURL (bash)"
+    let command = curl::parse(
+        r#"curl 'https://example.com/api' -H 'content-type: application/json' --data-raw $'{"name":"O\'Brien","note":"a\nb\té \303\251 \x41"}'"#,
+    )
+    .unwrap();
+    assert_eq!(command.method, Method::POST);
+    assert_eq!(
+        body(&command),
+        "{\"name\":\"O'Brien\",\"note\":\"a\nb\té é A\"}"
+    );
+}
+
+#[test]
+fn ansi_c_quoting_joins_the_surrounding_word() {
+    let command = curl::parse(r"curl -H X-A:$'one\\two'three https://example.com").unwrap();
+    assert_eq!(command.data.headers["x-a"], r"one\twothree");
+    assert!(curl::parse(r"curl $'unterminated https://example.com").is_err());
+}
+
+#[test]
+fn compressed_is_reported_as_ignored() {
+    let command = curl::parse("curl --compressed https://example.com").unwrap();
+    assert!(command.data.headers.get("accept-encoding").is_none());
+    assert!(command.warnings.iter().any(|w| w.contains("--compressed")));
+}
+
+#[test]
+fn proxy_user_takes_a_value() {
+    let command = curl::parse("curl -U user:pass -u ann:pw https://example.com").unwrap();
+    assert_eq!(command.url, "https://example.com");
+    assert!(matches!(
+        command.data.authentication,
+        ReqxAuth::Basic { ref username, .. } if username == "ann"
+    ));
+    let command = curl::parse("curl -Uuser:pass https://example.com").unwrap();
+    assert_eq!(command.url, "https://example.com");
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use std::time::Duration;
+
+use argus::reqx::{Reqx, ReqxDownload, ReqxError, ReqxRequest};
+use http::Method;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+fn body() -> Vec<u8> {
+    (0..200_000u32).map(|i| (i % 251) as u8).collect()
+}
+
+//answers a Range request with a 206 of the rest when `ranges`, and with the whole body otherwise
+async fn server(ranges: bool) -> std::net::SocketAddr {
+    common::serve(move |req| {
+        let body = body();
+        let start = req
+            .headers()
+            .get("range")
+            .and_then(|range| range.to_str().unwrap().strip_prefix("bytes="))
+            .and_then(|range| range.strip_suffix('-'))
+            .map(|start| start.parse::<usize>().unwrap());
+        match start.filter(|_| ranges) {
+            Some(start) if start >= body.len() => http::Response::builder()
+                .status(416)
+                .header("content-range", format!("bytes */{}", body.len()))
+                .body(Default::default())
+                .unwrap(),
+            Some(start) => http::Response::builder()
+                .status(206)
+                .header(
+                    "content-range",
+                    format!("bytes {start}-{}/{}", body.len() - 1, body.len()),
+                )
+                .body(body[start..].to_vec().into())
+                .unwrap(),
+            None => common::response(200, "application/octet-stream", body),
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn bodies_stream_to_the_file() {
+    let addr = server(true).await;
+    let dir = common::temp_dir("download");
+    let path = dir.join("file.bin");
+    std::fs::write(&path, b"old contents are replaced").unwrap();
+    let mut download = ReqxDownload::new(&path);
+    let progress = download.progress();
+    let request = ReqxRequest::new(Method::GET, format!("http://{addr}/file"));
+    let response = Reqx::default().download(&request, &download).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert!(response.body.is_empty());
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+
+    let reports: Vec<_> = progress.try_iter().collect();
+    assert_eq!(reports[0].received, 0);
+    let last = reports.last().unwrap();
+    assert!(last.done);
+    assert_eq!(last.received, body().len() as u64);
+    assert_eq!(last.total, Some(body().len() as u64));
+    assert_eq!(last.fraction(), Some(1.0));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn resumes_with_a_range_request() {
+    let addr = server(true).await;
+    let dir = common::temp_dir("download-resume");
+    let path = dir.join("file.bin");
+    std::fs::write(&path, &body()[..1234]).unwrap();
+    let mut download = ReqxDownload::new(&path).resumed();
+    let progress = download.progress();
+    let request = ReqxRequest::new(Method::GET, format!("http://{addr}/file"));
+    let response = Reqx::default().download(&request, &download).await.unwrap();
+    assert_eq!(response.status, 206);
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+    let reports: Vec<_> = progress.try_iter().collect();
+    //what was already there counts as received
+    assert_eq!(reports[0].received, 1234);
+    assert_eq!(reports.last().unwrap().total, Some(body().len() as u64));
+
+    //a complete file is answered with 416 and left alone
+    let response = Reqx::default().download(&request, &download).await.unwrap();
+    assert_eq!(response.status, 416);
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn a_server_ignoring_the_range_rewrites_the_file() {
+    let addr = server(false).await;
+    let dir = common::temp_dir("download-no-range");
+    let path = dir.join("file.bin");
+    std::fs::write(&path, &body()[..1234]).unwrap();
+    let download = ReqxDownload::new(&path).resumed();
+    let request = ReqxRequest::new(Method::GET, format!("http://{addr}/file"));
+    let response = Reqx::default().download(&request, &download).await.unwrap();
+    assert_eq!(response.status, 200);
+    //written from the start, not appended
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+//sends the head and part of the body, then holds the connection open
+async fn stalling() -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await;
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 1000\r\n\r\n0123456789")
+                .await
+                .unwrap();
+            held.push(stream);
+        }
+    });
+    addr
+}
+
+async fn cancelled(download: &ReqxDownload, addr: std::net::SocketAddr) -> ReqxError {
+    let request = ReqxRequest::new(Method::GET, format!("http://{addr}/"));
+    let reqx = Reqx::default();
+    let work = reqx.download(&request, download);
+    let cancel = async {
+        //once the first bytes are on disk
+        while std::fs::metadata(&download.path).map_or(0, |m| m.len()) < 10 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        download.canceller().cancel();
+    };
+    let (result, ()) =
+        tokio::time::timeout(Duration::from_secs(5), async { tokio::join!(work, cancel) })
+            .await
+            .unwrap();
+    result.unwrap_err()
+}
+
+#[tokio::test]
+async fn a_cancel_removes_the_partial_file_unless_resumable() {
+    let addr = stalling().await;
+    let dir = common::temp_dir("download-cancel");
+
+    let download = ReqxDownload::new(dir.join("plain.bin"));
+    assert!(matches!(
+        cancelled(&download, addr).await,
+        ReqxError::Cancelled
+    ));
+    assert!(!download.path.exists());
+
+    let download = ReqxDownload::new(dir.join("resumable.bin")).resumed();
+    assert!(matches!(
+        cancelled(&download, addr).await,
+        ReqxError::Cancelled
+    ));
+    assert_eq!(std::fs::read(&download.path).unwrap(), b"0123456789");
+
+    //a cancel before anything was written leaves an existing file alone
+    let kept = dir.join("kept.bin");
+    std::fs::write(&kept, b"mine").unwrap();
+    let download = ReqxDownload::new(&kept);
+    download.canceller().cancel();
+    let request = ReqxRequest::new(Method::GET, format!("http://{addr}/"));
+    assert!(matches!(
+        Reqx::default().download(&request, &download).await,
+        Err(ReqxError::Cancelled)
+    ));
+    assert_eq!(std::fs::read(&kept).unwrap(), b"mine");
+    std::fs::remove_dir_all(dir).unwrap();
+}
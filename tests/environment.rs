@@ -0,0 +1,149 @@
+mod common;
+
+use argus::{
+    environment::{Environment, EnvironmentError, MASK},
+    reqx::{ReqxAuth, ReqxError, ReqxRequest},
+};
+use http::Method;
+
+fn sample() -> Environment {
+    let mut env = Environment::new("staging");
+    env.set("host", "staging.test");
+    env.set("base", "https://{{host}}/v1");
+    env.set_secret("token", "t0ps3cret");
+    env.set_secret("password", "pässwörd with spaces");
+    env
+}
+
+#[test]
+fn secrets_are_encrypted_on_disk_and_decrypted_on_load() {
+    let dir = common::temp_dir("environment");
+    let path = dir.join("staging.toml");
+    let mut env = sample();
+    env.save(&path, Some("passphrase")).unwrap();
+
+    let stored = std::fs::read_to_string(&path).unwrap();
+    assert!(stored.contains("staging.test"));
+    assert!(!stored.contains("t0ps3cret"));
+    assert!(!stored.contains("spaces"));
+    let loaded = Environment::load(&path, Some("passphrase")).unwrap();
+    assert_eq!(loaded, env);
+    assert_eq!(loaded.get("token"), Some("t0ps3cret"));
+
+    //a fresh nonce each save, the salt stays
+    env.save(&path, Some("passphrase")).unwrap();
+    let again = std::fs::read_to_string(&path).unwrap();
+    assert_ne!(again, stored);
+    let salt = |toml: &str| {
+        toml.lines()
+            .find(|l| l.starts_with("salt"))
+            .map(String::from)
+    };
+    assert_eq!(salt(&again), salt(&stored));
+    assert_eq!(Environment::load(&path, Some("passphrase")).unwrap(), env);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn a_wrong_or_missing_passphrase_fails_cleanly() {
+    let dir = common::temp_dir("environment-passphrase");
+    let path = dir.join("staging.toml");
+    let mut env = sample();
+    assert!(matches!(
+        env.save(&path, None),
+        Err(EnvironmentError::PassphraseRequired)
+    ));
+    assert!(!path.exists());
+    env.save(&path, Some("passphrase")).unwrap();
+
+    let error = Environment::load(&path, Some("wrong")).unwrap_err();
+    //the first secret in name order is the one that fails
+    assert!(matches!(&error, EnvironmentError::Decrypt(name) if name == "password"));
+    assert_eq!(
+        error.to_string(),
+        "could not decrypt secret `password`, wrong passphrase?"
+    );
+    assert!(matches!(
+        Environment::load(&path, None),
+        Err(EnvironmentError::PassphraseRequired)
+    ));
+
+    //a tampered value fails the same way instead of loading garbage
+    let stored = std::fs::read_to_string(&path).unwrap();
+    let tampered: String = stored
+        .lines()
+        .map(|line| match line.strip_prefix("value = \"") {
+            Some(value) if !line.contains("staging") => format!("value = \"AAAA{value}"),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    std::fs::write(&path, tampered).unwrap();
+    assert!(matches!(
+        Environment::load(&path, Some("passphrase")),
+        Err(EnvironmentError::Decrypt(_))
+    ));
+
+    //without secrets there is nothing to ask for
+    let mut plain = Environment::new("local");
+    plain.set("host", "localhost");
+    plain.save(&path, None).unwrap();
+    assert_eq!(Environment::load(&path, None).unwrap(), plain);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn variables_expand_inside_variables() {
+    let env = sample();
+    assert_eq!(
+        env.render("{{ base }}/users?t={{token}}").unwrap(),
+        "https://staging.test/v1/users?t=t0ps3cret"
+    );
+    let mut request = ReqxRequest::new(Method::GET, "{{base}}/users");
+    request.authentication = ReqxAuth::Bearer("{{token}}".into());
+    let masked = env.render_request_masked(&request).unwrap();
+    assert_eq!(masked.url, "https://staging.test/v1/users");
+    assert_eq!(masked.authentication, ReqxAuth::Bearer(MASK.into()));
+}
+
+#[test]
+fn unresolved_variables_are_all_listed() {
+    let env = sample();
+    let mut request = ReqxRequest::new(Method::GET, "{{base}}/{{path}}");
+    request.headers.push(("X-Id".into(), "{{id}}".into()));
+    request.authentication = ReqxAuth::Basic {
+        username: "{{user}}".into(),
+        password: Some("{{path}}".into()),
+    };
+    let error = env.render_request(&request).unwrap_err();
+    assert!(
+        matches!(&error, ReqxError::UnresolvedVariables(names) if names == &["id", "path", "user"])
+    );
+    assert_eq!(error.to_string(), "unresolved variables: id, path, user");
+
+    //a missing name inside another variable's value is reported the same
+    let mut env = sample();
+    env.set("host", "{{region}}.test");
+    assert!(matches!(
+        env.render("{{base}}"),
+        Err(ReqxError::UnresolvedVariables(names)) if names == ["region"]
+    ));
+}
+
+#[test]
+fn recursive_variables_are_an_error() {
+    let mut env = sample();
+    env.set("self", "x{{self}}");
+    env.set("a", "{{b}}");
+    env.set("b", "-{{a}}-");
+    let error = env.render("{{self}}").unwrap_err();
+    assert!(matches!(&error, ReqxError::RecursiveVariables(names) if names == &["self"]));
+    assert_eq!(error.to_string(), "variables refer to themselves: self");
+    assert!(matches!(
+        env.render("{{a}} {{b}} {{missing}}"),
+        Err(ReqxError::RecursiveVariables(names)) if names == ["a", "b"]
+    ));
+    //the same variable twice side by side is not a cycle
+    env.set("twice", "{{host}}{{host}}");
+    assert_eq!(env.render("{{twice}}").unwrap(), "staging.teststaging.test");
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use argus::{
+    executor::{Executor, Pending, ReqxEvent},
+    reqx::{Reqx, ReqxError, ReqxRequest},
+};
+use http::Method;
+use tokio::{net::TcpListener, sync::mpsc};
+
+async fn next(events: &mut mpsc::UnboundedReceiver<ReqxEvent>) -> ReqxEvent {
+    tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn a_new_send_cancels_the_one_in_flight() {
+    //accepts and never answers
+    let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let slow = silent.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = silent.accept().await {
+            held.push(stream);
+        }
+    });
+    let fast = common::serve(|_| common::response(200, "text/plain", "fast")).await;
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let executor = Executor::new(Arc::new(Reqx::default()), tx);
+    let mut pending = Pending::default();
+
+    let first = executor.send(ReqxRequest::new(Method::GET, format!("http://{slow}/")));
+    let first_id = first.id;
+    pending.replace(first);
+    pending.replace(executor.send(ReqxRequest::new(Method::GET, format!("http://{fast}/"))));
+
+    let mut finished = Vec::new();
+    for _ in 0..2 {
+        let event = next(&mut events).await;
+        if event.id == first_id {
+            assert!(matches!(event.result, Err(ReqxError::Cancelled)));
+        }
+        finished.push(pending.finish(event));
+    }
+    //only the second request's result comes through
+    let results: Vec<_> = finished.into_iter().flatten().collect();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].as_ref().unwrap().body, b"fast");
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn failures_are_delivered_as_events() {
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let executor = Executor::new(Arc::new(Reqx::default()), tx);
+    let mut pending = Pending::default();
+    pending.replace(executor.send(ReqxRequest::new(Method::GET, "not a url")));
+    let result = pending.finish(next(&mut events).await).unwrap();
+    assert!(result.is_err());
+}
@@ -0,0 +1,141 @@
+use argus::{
+    environment::{Environment, MASK},
+    har::{self, HarEntry},
+    reqx::{export, ApiKeyLocation, AwsSigV4, ReqxAuth, ReqxData, ReqxRequest, ReqxResponse},
+};
+use http::{HeaderMap, HeaderValue, Method};
+
+const URL: &str = "https://api.example.com/items?page=2&access_token=TOKVAL";
+
+fn data(authentication: ReqxAuth) -> ReqxData {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "cookie",
+        HeaderValue::from_static("session=SESSID; theme=dark"),
+    );
+    headers.insert("x-api-key", HeaderValue::from_static("HDRKEY"));
+    headers.insert("accept", HeaderValue::from_static("text/plain"));
+    ReqxData {
+        headers,
+        authentication,
+        ..Default::default()
+    }
+}
+
+fn leaks(out: &str) -> Vec<&'static str> {
+    ["PASSWD", "SEKRET", "SESSID", "HDRKEY", "TOKVAL", "QKEY"]
+        .into_iter()
+        .filter(|secret| out.contains(secret))
+        .collect()
+}
+
+#[test]
+fn exporters_mask_unless_revealed() {
+    let basic = ReqxAuth::Basic {
+        username: "ann".into(),
+        password: Some("PASSWD".into()),
+    };
+    let query_key = ReqxAuth::ApiKey {
+        name: "k".into(),
+        value: "QKEY".into(),
+        location: ApiKeyLocation::Query,
+    };
+    let bearer = ReqxAuth::Bearer("SEKRET".into());
+    for auth in [basic, query_key, bearer] {
+        let data = data(auth);
+        let masked = [
+            export::curl(&Method::GET, URL, &data, false).unwrap(),
+            export::httpie(&Method::GET, URL, &data, false).unwrap(),
+            export::reqwest(&Method::GET, URL, &data, false).unwrap(),
+        ];
+        for out in &masked {
+            assert!(leaks(out).is_empty(), "{out}");
+            assert!(out.contains(MASK), "{out}");
+            assert!(out.contains("session="), "{out}");
+            assert!(out.contains("text/plain"), "{out}");
+            assert!(out.contains("page=2"), "{out}");
+        }
+        let revealed = export::curl(&Method::GET, URL, &data, true).unwrap();
+        assert!(!revealed.contains(MASK), "{revealed}");
+        assert!(
+            revealed.contains("session=SESSID; theme=dark"),
+            "{revealed}"
+        );
+        assert!(revealed.contains("access_token=TOKVAL"), "{revealed}");
+    }
+}
+
+#[test]
+fn sigv4_secrets_are_masked_in_curl() {
+    let auth = ReqxAuth::AwsSigV4(AwsSigV4 {
+        access_key: "AKID".into(),
+        secret_key: "SEKRET".into(),
+        session_token: Some("TOKVAL".into()),
+        region: "eu-west-1".into(),
+        service: "s3".into(),
+    });
+    let out = export::curl(&Method::GET, "https://s3.example.com/", &data(auth), false).unwrap();
+    assert!(leaks(&out).is_empty(), "{out}");
+    assert!(out.contains("AKID:********"), "{out}");
+}
+
+#[test]
+fn har_masks_api_keys_cookies_and_query() {
+    let mut request = ReqxRequest::new(Method::GET, URL);
+    request
+        .headers
+        .push(("Cookie".into(), "session=SESSID".into()));
+    request.authentication = ReqxAuth::ApiKey {
+        name: "X-Key".into(),
+        value: "HDRKEY".into(),
+        location: ApiKeyLocation::Header,
+    };
+    let entry = HarEntry {
+        started: chrono::Utc::now(),
+        request,
+        response: Some(ReqxResponse {
+            status: 200,
+            version: "HTTP/1.1".into(),
+            headers: vec![("Set-Cookie".into(), "session=SESSID; Path=/".into())],
+            body: Vec::new(),
+            remote_addr: None,
+            timing: Default::default(),
+            redirects: Vec::new(),
+        }),
+    };
+    let masked = har::export(std::slice::from_ref(&entry), false);
+    assert!(leaks(&masked).is_empty(), "{masked}");
+    assert!(masked.contains("X-Key"), "{masked}");
+    assert!(masked.contains("Path=/"), "{masked}");
+    let revealed = har::export(&[entry], true);
+    for secret in ["HDRKEY", "session=SESSID", "access_token=TOKVAL"] {
+        assert!(revealed.contains(secret), "{secret}");
+    }
+    //masked values are not imported back as credentials
+    let (imported, issues) = har::import(&masked).unwrap();
+    assert!(issues.is_empty());
+    assert!(imported[0]
+        .request
+        .headers
+        .iter()
+        .all(|(_, value)| !value.contains(MASK)));
+}
+
+#[test]
+fn render_data_masked_shows_secret_variables_as_the_mask() {
+    let mut env = Environment::new("dev");
+    env.set("host", "api.example.com");
+    env.set_secret("token", "SEKRET");
+    let data = || ReqxData {
+        authentication: ReqxAuth::Bearer("{{token}}".into()),
+        body: Some("token={{token}}".into()),
+        ..Default::default()
+    };
+    let url = "https://{{host}}/?t={{token}}";
+    let (shown, masked) = env.render_data_masked(url, data()).unwrap();
+    assert_eq!(shown, format!("https://api.example.com/?t={MASK}"));
+    assert!(matches!(masked.authentication, ReqxAuth::Bearer(t) if t == MASK));
+    let (sent, revealed) = env.render_data(url, data()).unwrap();
+    assert_eq!(sent, "https://api.example.com/?t=SEKRET");
+    assert!(matches!(revealed.authentication, ReqxAuth::Bearer(t) if t == "SEKRET"));
+}
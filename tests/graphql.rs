@@ -0,0 +1,178 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use argus::{
+    environment::Environment,
+    reqx::{GraphQlQuery, GraphQlSchema, Reqx, ReqxBody, ReqxRequest, TypeKind},
+};
+use http::Method;
+use serde_json::json;
+
+const SCHEMA: &str = r#"{"data":{"__schema":{
+    "queryType":{"name":"Query"},"mutationType":null,"subscriptionType":null,
+    "types":[
+        {"kind":"OBJECT","name":"Query","description":null,
+         "fields":[{"name":"hello","description":null,"args":[],
+                    "type":{"kind":"SCALAR","name":"String","ofType":null},"isDeprecated":false}],
+         "inputFields":null,"interfaces":[],"enumValues":null,"possibleTypes":null},
+        {"kind":"SCALAR","name":"String","description":null,"fields":null,
+         "inputFields":null,"interfaces":null,"enumValues":null,"possibleTypes":null}
+    ]}}}"#;
+
+#[tokio::test]
+async fn introspection_ignores_the_body_and_follows_redirects() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counted = hits.clone();
+    let addr = common::serve(move |req| match req.uri().path() {
+        "/old" => http::Response::builder()
+            .status(307)
+            .header("location", "/graphql")
+            .body(Default::default())
+            .unwrap(),
+        _ => {
+            counted.fetch_add(1, Ordering::SeqCst);
+            let body: serde_json::Value = serde_json::from_slice(req.body()).unwrap();
+            assert_eq!(req.method(), Method::POST);
+            assert!(body["query"].as_str().unwrap().contains("__schema"));
+            assert_eq!(req.headers()["x-key"], "KEYVAL");
+            common::response(200, "application/json", SCHEMA)
+        }
+    })
+    .await;
+    let reqx = Reqx::default();
+    let mut env = Environment::new("dev");
+    env.set("host", addr.to_string());
+    env.set("key", "KEYVAL");
+    let mut request = ReqxRequest::new(Method::GET, "http://{{host}}/old");
+    request.headers.push(("X-Key".into(), "{{key}}".into()));
+    //only the query would use this, it need not resolve
+    request.body = ReqxBody::GraphQl(GraphQlQuery::new("{ hello(name: \"{{missing}}\") }"));
+
+    let schema = reqx.schema_in(&env, &request, false).await.unwrap();
+    assert_eq!(schema.get("Query").unwrap().kind, TypeKind::Object);
+    assert_eq!(schema.fields("Query")[0].name, "hello");
+    let url = format!("http://{addr}/old");
+    assert!(reqx.schemas().get(&url).is_some());
+
+    //cached by the rendered url until refreshed
+    reqx.schema_in(&env, &request, false).await.unwrap();
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+    reqx.schema_in(&env, &request, true).await.unwrap();
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    //without an environment the body is dropped just the same
+    let mut plain = ReqxRequest::new(Method::GET, url);
+    plain.headers.push(("X-Key".into(), "KEYVAL".into()));
+    plain.body = ReqxBody::Text("not graphql".into());
+    let schema = reqx.schema(&plain, true).await.unwrap();
+    assert_eq!(schema.fields("Query").len(), 1);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+fn issues(query: GraphQlQuery) -> Vec<String> {
+    GraphQlSchema::from_introspection(include_bytes!("fixtures/graphql_schema.json"))
+        .unwrap()
+        .validate(&query)
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn a_valid_query_has_no_issues() {
+    let query = GraphQlQuery::new(
+        "query Find($id: ID!, $term: String!, $filter: UserFilter) {
+            user(id: $id) { ...Person friends { id } }
+            users(filter: $filter) { name role }
+            search(term: $term) {
+                __typename
+                ... on Post { title author { ...Person } }
+                ...Person
+            }
+        }
+        fragment Person on User { id name }",
+    )
+    .with_variables(json!({"id": "1", "term": "ann"}));
+    assert_eq!(issues(query), Vec::<String>::new());
+}
+
+#[test]
+fn unknown_fields_are_reported() {
+    let query = GraphQlQuery::new(
+        "{
+            user(id: 1) { nmae }
+            nope
+            search(term: \"a\") { id }
+            users(filter: {active: true, nmae: \"x\"}) { id }
+        }",
+    );
+    assert_eq!(
+        issues(query),
+        [
+            "2:27: type `User` has no field `nmae`",
+            "3:13: type `Query` has no field `nope`",
+            "4:33: cannot select `id` on the union `SearchResult`, use a fragment on one of its types",
+            "5:13: input `UserFilter` has no field `nmae`",
+        ]
+    );
+}
+
+#[test]
+fn missing_required_arguments_are_reported() {
+    let query = GraphQlQuery::new(
+        "query Q { user { id } users(first: 5, filter: {name: \"x\"}) { id } }
+        mutation Rename { rename(id: 1) { id } }",
+    )
+    .with_operation("Rename");
+    assert_eq!(
+        issues(query),
+        [
+            "1:11: field `Query.user` needs the argument `id` of type `ID!`",
+            "1:23: argument `filter` is missing the field `active` of type `Boolean!`",
+            "2:27: field `Mutation.rename` needs the argument `name` of type `String!`",
+        ]
+    );
+}
+
+#[test]
+fn undefined_and_missing_variables_are_reported() {
+    let query = GraphQlQuery::new(
+        "query Q($unused: Int, $term: String!) {
+            user(id: $id) { ...Named }
+            search(term: $term) { __typename }
+        }
+        fragment Named on User { name friends @include(if: $deep) { id } }",
+    )
+    //null is as good as missing for a required variable
+    .with_variables(json!({"term": null}));
+    assert_eq!(
+        issues(query),
+        [
+            "2:13: variable `$id` is not defined by operation `Q`",
+            "5:39: variable `$deep` is not defined by operation `Q`",
+            "1:9: variable `$unused` is never used",
+            "1:23: variable `$term` of type `String!` is not given",
+        ]
+    );
+}
+
+#[test]
+fn fragment_cycles_are_reported_once() {
+    let query = GraphQlQuery::new(
+        "{ user(id: 1) { ...A ...Selfish } }
+        fragment A on User { friends { ...B } }
+        fragment B on User { ... on User { ...A } }
+        fragment Selfish on User { id ...Selfish }",
+    );
+    assert_eq!(
+        issues(query),
+        [
+            "2:9: fragment `A` spreads itself: A -> B -> A",
+            "4:9: fragment `Selfish` spreads itself: Selfish -> Selfish",
+        ]
+    );
+}
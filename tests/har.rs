@@ -0,0 +1,191 @@
+mod common;
+
+use std::time::Duration;
+
+use argus::{
+    har::{self, HarEntry},
+    reqx::{FormPart, Reqx, ReqxBody, ReqxRequest, ReqxResponse, ReqxTiming},
+};
+use chrono::{TimeZone, Utc};
+use http::Method;
+use serde_json::Value;
+
+fn response(status: u16, body: &[u8]) -> ReqxResponse {
+    ReqxResponse {
+        status,
+        version: "HTTP/1.1".into(),
+        headers: vec![
+            ("Content-Type".into(), "application/json".into()),
+            ("Set-Cookie".into(), "a=1; Path=/".into()),
+        ],
+        body: body.to_vec(),
+        remote_addr: Some("127.0.0.1:0".parse().unwrap()),
+        timing: ReqxTiming {
+            dns: Some(Duration::from_millis(2)),
+            connect: Some(Duration::from_millis(3)),
+            tls: Some(Duration::from_millis(5)),
+            ttfb: Duration::from_millis(40),
+            download: Duration::from_millis(8),
+            total: Duration::from_millis(58),
+        },
+        redirects: Vec::new(),
+    }
+}
+
+fn entry(seconds: u32, request: ReqxRequest, response: Option<ReqxResponse>) -> HarEntry {
+    HarEntry {
+        started: Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, seconds).unwrap()
+            + chrono::Duration::microseconds(250),
+        request,
+        response,
+    }
+}
+
+#[test]
+fn entries_round_trip() {
+    let mut get = ReqxRequest::new(Method::GET, "https://api.test/items?page=2&q=a%20b");
+    get.headers = vec![
+        ("Accept".into(), "application/json".into()),
+        ("Cookie".into(), "session=abc".into()),
+        ("X-Dup".into(), "1".into()),
+        ("X-Dup".into(), "2".into()),
+    ];
+    let mut text = ReqxRequest::new(Method::POST, "https://api.test/items");
+    text.headers = vec![("Content-Type".into(), "application/json".into())];
+    text.body = ReqxBody::Text("{\"name\":\"ann\"}".into());
+    let mut form = ReqxRequest::new(Method::POST, "https://api.test/login");
+    form.body = ReqxBody::Form(vec![
+        ("user".into(), "ann".into()),
+        ("note".into(), "a&b=c".into()),
+    ]);
+    let mut multipart = ReqxRequest::new(Method::POST, "https://api.test/upload");
+    multipart.body = ReqxBody::Multipart(vec![
+        FormPart::text("title", "cat"),
+        FormPart::text("meta", "{}")
+            .filename("meta.json")
+            .content_type("application/json"),
+    ]);
+    let mut bytes = ReqxRequest::new(Method::PUT, "https://api.test/blob");
+    bytes.headers = vec![("Content-Type".into(), "image/png".into())];
+    bytes.body = ReqxBody::Bytes(vec![0x89, b'P', 0, 0xff]);
+    let entries = vec![
+        entry(0, get, Some(response(200, b"[1,2]"))),
+        entry(1, text, Some(response(201, &[0xff, 0xfe, 0]))),
+        entry(2, form, Some(response(204, b""))),
+        entry(3, multipart, Some(response(500, b"{}"))),
+        //never answered
+        entry(4, bytes, None),
+    ];
+    let (imported, issues) = har::import(&har::export(&entries, true)).unwrap();
+    assert!(issues.is_empty(), "{issues:?}");
+    assert_eq!(imported, entries);
+}
+
+#[tokio::test]
+async fn a_redirect_chain_exports_one_entry_per_hop() {
+    let addr = common::serve(|req| match req.uri().path() {
+        "/a" => http::Response::builder()
+            .status(302)
+            .header("location", "/b")
+            .body(Default::default())
+            .unwrap(),
+        "/b" => http::Response::builder()
+            .status(307)
+            .header("location", "/c?x=1")
+            .body(Default::default())
+            .unwrap(),
+        _ => common::echo(req),
+    })
+    .await;
+    let mut request = ReqxRequest::new(Method::POST, format!("http://{addr}/a"));
+    request.body = ReqxBody::Text("payload".into());
+    let response = Reqx::default().send(&request).await.unwrap();
+    assert_eq!(response.redirects.len(), 2);
+    let exported = har::export(
+        &[HarEntry {
+            started: Utc::now(),
+            request,
+            response: Some(response),
+        }],
+        false,
+    );
+
+    let har: Value = serde_json::from_str(&exported).unwrap();
+    let entries = har["log"]["entries"].as_array().unwrap();
+    let field = |f: fn(&Value) -> &Value| entries.iter().map(f).cloned().collect::<Vec<_>>();
+    assert_eq!(
+        field(|e| &e["request"]["url"]),
+        [
+            format!("http://{addr}/a"),
+            format!("http://{addr}/b"),
+            format!("http://{addr}/c?x=1"),
+        ]
+    );
+    //a 302 turns the post into a get without a body, the 307 keeps it that way
+    assert_eq!(field(|e| &e["request"]["method"]), ["POST", "GET", "GET"]);
+    assert_eq!(
+        field(|e| &e["request"]["postData"]["text"]),
+        [Value::from("payload"), Value::Null, Value::Null]
+    );
+    assert_eq!(field(|e| &e["response"]["status"]), [302, 307, 200]);
+    assert_eq!(
+        field(|e| &e["response"]["redirectURL"]),
+        ["/b", "/c?x=1", ""]
+    );
+    let last: Value =
+        serde_json::from_str(entries[2]["response"]["content"]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(last["uri"], "/c?x=1");
+
+    let (imported, issues) = har::import(&exported).unwrap();
+    assert!(issues.is_empty());
+    let statuses: Vec<_> = imported
+        .iter()
+        .map(|e| e.response.as_ref().unwrap().status)
+        .collect();
+    assert_eq!(statuses, [302, 307, 200]);
+    assert!(imported
+        .iter()
+        .all(|e| e.response.as_ref().unwrap().redirects.is_empty()));
+}
+
+#[test]
+fn invalid_methods_and_statuses_are_reported() {
+    let har = |method: &str, status: i64| {
+        format!(
+            r#"{{"method":"{method}","url":"https://api.test/","httpVersion":"HTTP/1.1",
+                "headersSize":-1,"bodySize":0}},
+               "response":{{"status":{status},"content":{{"size":0}},"headersSize":-1,"bodySize":0}},
+               "timings":{{}}"#
+        )
+    };
+    let entries = [
+        har("GE T", 200),
+        har("PATCH", 70000),
+        har("GET", -1),
+        har("GET", 0),
+        har("GET", 42),
+    ]
+    .map(|entry| {
+        format!(r#"{{"startedDateTime":"2026-03-01T12:00:00Z","time":0,"request":{entry}}}"#)
+    });
+    let json = format!(
+        r#"{{"log":{{"version":"1.2","creator":{{"name":"x","version":"1"}},"entries":[{}]}}}}"#,
+        entries.join(",")
+    );
+    let (imported, issues) = har::import(&json).unwrap();
+    assert_eq!(imported.len(), 5);
+    assert_eq!(imported[0].request.method, Method::GET);
+    assert_eq!(imported[0].response.as_ref().unwrap().status, 200);
+    assert_eq!(imported[1].request.method, Method::PATCH);
+    assert!(imported[1..].iter().all(|e| e.response.is_none()));
+    let issues: Vec<_> = issues.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        issues,
+        [
+            "entry 1: invalid method `GE T`, using GET",
+            "entry 2: invalid status `70000`, response skipped",
+            "entry 3: invalid status `-1`, response skipped",
+            "entry 5: invalid status `42`, response skipped",
+        ]
+    );
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use argus::{
+    environment::Environment,
+    history::{History, HistoryLimit, HistoryQuery},
+    reqx::{Reqx, ReqxAuth, ReqxError, ReqxRequest},
+};
+use http::Method;
+use serde_json::Value;
+
+#[tokio::test]
+async fn history_keeps_templates_and_resends_them_rendered() {
+    let addr = common::serve(common::echo).await;
+    let history = Arc::new(Mutex::new(
+        History::in_memory(HistoryLimit::default()).unwrap(),
+    ));
+    let reqx = Reqx::default().with_history(history.clone());
+    let mut env = Environment::new("dev");
+    env.set("host", addr.to_string());
+    env.set_secret("token", "SEKRET");
+    let mut request = ReqxRequest::new(Method::GET, "http://{{host}}/?t={{token}}");
+    request.authentication = ReqxAuth::Bearer("{{token}}".into());
+    request.headers.push(("X-Token".into(), "{{token}}".into()));
+    reqx.send_in(&env, &request).await.unwrap();
+
+    let entry = {
+        let history = history.lock().unwrap();
+        let entries = history.search(&HistoryQuery::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+        entries.into_iter().next().unwrap()
+    };
+    assert_eq!(entry.environment.as_deref(), Some("dev"));
+    //searches match the url that was sent, secrets masked
+    let found = |url: &str| {
+        let query = HistoryQuery {
+            url: Some(url.to_string()),
+            ..Default::default()
+        };
+        history.lock().unwrap().search(&query).unwrap().len()
+    };
+    assert_eq!(found(&format!("http://{addr}/")), 1);
+    assert_eq!(found("{{host}}"), 0);
+    assert_eq!(found("SEKRET"), 0);
+    assert_eq!(entry.request, request);
+    assert!(!serde_json::to_string(&entry.request)
+        .unwrap()
+        .contains("SEKRET"));
+
+    assert!(matches!(
+        reqx.resend(&entry).await,
+        Err(ReqxError::EnvironmentRequired(name)) if name == "dev"
+    ));
+    let response = reqx.resend_in(&env, &entry).await.unwrap();
+    let echo: Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(echo["uri"], "/?t=SEKRET");
+    assert_eq!(echo["headers"]["authorization"], "Bearer SEKRET");
+    assert_eq!(echo["headers"]["x-token"], "SEKRET");
+}
+
+#[tokio::test]
+async fn requests_without_an_environment_resend_as_they_are() {
+    let addr = common::serve(common::echo).await;
+    let history = Arc::new(Mutex::new(
+        History::in_memory(HistoryLimit::default()).unwrap(),
+    ));
+    let reqx = Reqx::default().with_history(history.clone());
+    let request = ReqxRequest::new(Method::POST, format!("http://{addr}/plain"));
+    reqx.send(&request).await.unwrap();
+    let entry = history
+        .lock()
+        .unwrap()
+        .search(&HistoryQuery::default())
+        .unwrap()
+        .remove(0);
+    assert_eq!(entry.environment, None);
+    let response = reqx.resend(&entry).await.unwrap();
+    let echo: Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(echo["method"], "POST");
+    assert_eq!(echo["uri"], "/plain");
+}
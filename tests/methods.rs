@@ -0,0 +1,157 @@
+mod common;
+
+use argus::reqx::{Reqx, ReqxAuth, ReqxData, ReqxError};
+use http::{header, HeaderValue, Method};
+use serde_json::Value;
+
+async fn fetch(method: Method, data: ReqxData) -> (u16, Value) {
+    let addr = common::serve(common::echo).await;
+    let mut data = ReqxData {
+        authentication: ReqxAuth::Basic {
+            username: "ann".into(),
+            password: Some("pw".into()),
+        },
+        ..data
+    };
+    data.headers
+        .insert("x-argus", HeaderValue::from_static("yes"));
+    let response = Reqx::default()
+        .fetch(&format!("http://{addr}/items/1"), method, Some(data))
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = response.bytes().await.unwrap();
+    let echo = match body.is_empty() {
+        true => Value::Null,
+        false => serde_json::from_slice(&body).unwrap(),
+    };
+    (status, echo)
+}
+
+fn text(body: &'static str) -> ReqxData {
+    ReqxData {
+        body: Some(body.into()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn every_method_carries_headers_auth_and_body() {
+    for method in [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+        Method::OPTIONS,
+    ] {
+        let (status, echo) = fetch(method.clone(), text("payload")).await;
+        assert_eq!(status, 200);
+        assert_eq!(echo["method"], method.as_str());
+        assert_eq!(echo["headers"]["x-argus"], "yes");
+        assert_eq!(echo["headers"]["authorization"], "Basic YW5uOnB3");
+        assert_eq!(echo["body"], "payload", "{method}");
+    }
+}
+
+#[tokio::test]
+async fn options_goes_out_without_a_body_too() {
+    let (status, echo) = fetch(Method::OPTIONS, ReqxData::default()).await;
+    assert_eq!(status, 200);
+    assert_eq!(echo["method"], "OPTIONS");
+    assert_eq!(echo["uri"], "/items/1");
+    assert_eq!(echo["body"], "");
+    assert_eq!(echo["headers"].get("content-type"), None);
+}
+
+#[tokio::test]
+async fn forms_are_sent_with_every_method() {
+    for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+        let data = ReqxData {
+            form: Some(vec![("a".into(), "1".into()), ("b".into(), "x y".into())]),
+            ..Default::default()
+        };
+        let (_, echo) = fetch(method, data).await;
+        assert_eq!(echo["body"], "a=1&b=x+y");
+        assert_eq!(
+            echo["headers"]["content-type"],
+            "application/x-www-form-urlencoded"
+        );
+    }
+}
+
+#[tokio::test]
+async fn head_sends_headers_without_a_body() {
+    let (status, echo) = fetch(Method::HEAD, ReqxData::default()).await;
+    assert_eq!(status, 200);
+    assert_eq!(echo, Value::Null);
+    let error = Reqx::default()
+        .fetch("http://127.0.0.1/", Method::HEAD, Some(text("payload")))
+        .unwrap_err();
+    assert!(matches!(error, ReqxError::PayloadNotAllowed(Method::HEAD)));
+}
+
+#[test]
+fn body_and_form_conflict() {
+    let data = ReqxData {
+        body: Some("x".into()),
+        form: Some(vec![("a".into(), "1".into())]),
+        ..Default::default()
+    };
+    assert!(matches!(
+        data.validate(&Method::POST),
+        Err(ReqxError::BodyWithForm)
+    ));
+    let data = ReqxData {
+        form: Some(Vec::new()),
+        multipart: Some(Vec::new()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        data.validate(&Method::POST),
+        Err(ReqxError::BodyWithForm)
+    ));
+}
+
+#[test]
+fn payload_is_refused_on_methods_without_one() {
+    for method in [Method::HEAD, Method::TRACE, Method::CONNECT] {
+        let data = ReqxData {
+            body: Some("x".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            data.validate(&method),
+            Err(ReqxError::PayloadNotAllowed(m)) if m == method
+        ));
+    }
+    assert!(ReqxData::default().validate(&Method::HEAD).is_ok());
+}
+
+#[test]
+fn auth_conflicts_with_an_authorization_header() {
+    let mut headers = http::HeaderMap::new();
+    headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Token t"));
+    let data = ReqxData {
+        headers: headers.clone(),
+        authentication: ReqxAuth::Bearer("t".into()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        data.validate(&Method::GET),
+        Err(ReqxError::AuthConflict)
+    ));
+    //an api key in the query does not touch the header
+    let data = ReqxData {
+        headers,
+        authentication: ReqxAuth::ApiKey {
+            name: "key".into(),
+            value: "v".into(),
+            location: argus::reqx::ApiKeyLocation::Query,
+        },
+        ..Default::default()
+    };
+    assert!(data.validate(&Method::GET).is_ok());
+}
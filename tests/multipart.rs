@@ -0,0 +1,158 @@
+mod common;
+
+use std::net::SocketAddr;
+
+use argus::reqx::{FormPart, Reqx, ReqxBody, ReqxData, ReqxError, ReqxRequest};
+use http::Method;
+
+//answers with the request's content type and body as they arrived
+async fn mirror() -> SocketAddr {
+    common::serve(|req| {
+        let content_type = req.headers()["content-type"].to_str().unwrap().to_string();
+        common::response(200, &content_type, req.into_body())
+    })
+    .await
+}
+
+struct Part {
+    headers: Vec<String>,
+    body: Vec<u8>,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+//splits a multipart body on its boundary, checking the framing on the way
+fn split(content_type: &str, mut body: &[u8]) -> Vec<Part> {
+    let boundary = content_type
+        .strip_prefix("multipart/form-data; boundary=")
+        .unwrap();
+    assert!(boundary.starts_with("argus-") && boundary.len() > 10);
+    let delimiter = format!("--{boundary}");
+    assert!(body.ends_with(format!("\r\n{delimiter}--\r\n").as_bytes()));
+    let mut parts = Vec::new();
+    body = body
+        .strip_prefix(format!("{delimiter}\r\n").as_bytes())
+        .unwrap();
+    let next = format!("\r\n{delimiter}");
+    while let Some(end) = find(body, next.as_bytes()) {
+        let part = &body[..end];
+        let split = find(part, b"\r\n\r\n").unwrap();
+        parts.push(Part {
+            headers: String::from_utf8(part[..split].to_vec())
+                .unwrap()
+                .split("\r\n")
+                .map(String::from)
+                .collect(),
+            body: part[split + 4..].to_vec(),
+        });
+        body = &body[end + next.len()..];
+        if body == b"--\r\n" {
+            break;
+        }
+        body = body.strip_prefix(b"\r\n").unwrap();
+    }
+    parts
+}
+
+#[tokio::test]
+async fn parts_are_framed_with_their_headers_and_file_bytes() {
+    let dir = common::temp_dir("multipart");
+    //a line break and a boundary-like line inside the file must survive
+    let photo: Vec<u8> = [&[0x89, b'P', b'N', b'G', 0, 0xff][..], b"\r\n--argus-x\r\n"].concat();
+    std::fs::write(dir.join("cat.png"), &photo).unwrap();
+    std::fs::write(dir.join("notes"), "plain").unwrap();
+
+    let addr = mirror().await;
+    let mut request = ReqxRequest::new(Method::POST, format!("http://{addr}/upload"));
+    request
+        .headers
+        .push(("Content-Type".into(), "text/plain".into()));
+    request.body = ReqxBody::Multipart(vec![
+        FormPart::text("title", "a \"quoted\"\nname"),
+        FormPart::text("meta", "{}").content_type("application/json"),
+        FormPart::file("photo", dir.join("cat.png")),
+        FormPart::file("notes", dir.join("notes"))
+            .filename("n.txt")
+            .content_type("text/markdown"),
+    ]);
+    let response = Reqx::default().send(&request).await.unwrap();
+    //the hand set content type is replaced by one carrying the boundary
+    let content_type = response.header("content-type").unwrap().to_string();
+    let parts = split(&content_type, &response.body);
+    assert_eq!(parts.len(), 4);
+
+    assert_eq!(
+        parts[0].headers,
+        ["Content-Disposition: form-data; name=\"title\""]
+    );
+    assert_eq!(parts[0].body, b"a \"quoted\"\nname");
+    assert_eq!(
+        parts[1].headers,
+        [
+            "Content-Disposition: form-data; name=\"meta\"",
+            "Content-Type: application/json"
+        ]
+    );
+    assert_eq!(parts[1].body, b"{}");
+    //files default to their file name and a type guessed from it
+    assert_eq!(
+        parts[2].headers,
+        [
+            "Content-Disposition: form-data; name=\"photo\"; filename=\"cat.png\"",
+            "Content-Type: image/png"
+        ]
+    );
+    assert_eq!(parts[2].body, photo);
+    assert_eq!(
+        parts[3].headers,
+        [
+            "Content-Disposition: form-data; name=\"notes\"; filename=\"n.txt\"",
+            "Content-Type: text/markdown"
+        ]
+    );
+    assert_eq!(parts[3].body, b"plain");
+
+    //fetch builds the same body
+    let data = ReqxData {
+        multipart: Some(vec![FormPart::file("photo", dir.join("cat.png"))]),
+        ..Default::default()
+    };
+    let response = Reqx::default()
+        .fetch(&format!("http://{addr}/"), Method::PUT, Some(data))
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    let content_type = response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let parts = split(&content_type, &response.bytes().await.unwrap());
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].body, photo);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn names_are_escaped_and_missing_files_fail_the_send() {
+    let addr = mirror().await;
+    let mut request = ReqxRequest::new(Method::POST, format!("http://{addr}/"));
+    request.body = ReqxBody::Multipart(vec![FormPart::text("a\"b\r\nc", "1").filename("x\".txt")]);
+    let response = Reqx::default().send(&request).await.unwrap();
+    let content_type = response.header("content-type").unwrap().to_string();
+    assert_eq!(
+        split(&content_type, &response.body)[0].headers,
+        ["Content-Disposition: form-data; name=\"a%22b%0D%0Ac\"; filename=\"x%22.txt\""]
+    );
+
+    request.body = ReqxBody::Multipart(vec![FormPart::file(
+        "f",
+        common::temp_dir("multipart-missing").join("gone"),
+    )]);
+    assert!(matches!(
+        Reqx::default().send(&request).await,
+        Err(ReqxError::Io(_))
+    ));
+}
@@ -0,0 +1,247 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use argus::reqx::{
+    AuthorizeHook, OAuth2Config, OAuth2Grant, OAuthError, Reqx, ReqxAuth, ReqxError, ReqxRequest,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use http::Method;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+type Seen = Arc<Mutex<Vec<(Option<String>, HashMap<String, String>)>>>;
+
+//a token endpoint at /token answering with `reply(n)` for the n-th grant, every other path echoes
+async fn token_endpoint(
+    reply: impl Fn(usize) -> Value + Send + Sync + 'static,
+) -> (SocketAddr, Seen) {
+    let seen: Seen = Arc::default();
+    let log = seen.clone();
+    let addr = common::serve(move |req| {
+        if req.uri().path() != "/token" {
+            return common::echo(req);
+        }
+        let params: HashMap<String, String> = url::form_urlencoded::parse(req.body())
+            .into_owned()
+            .collect();
+        let auth = req
+            .headers()
+            .get("authorization")
+            .map(|v| v.to_str().unwrap().to_string());
+        let mut log = log.lock().unwrap();
+        log.push((auth, params));
+        common::response(200, "application/json", reply(log.len()).to_string())
+    })
+    .await;
+    (addr, seen)
+}
+
+fn config(addr: SocketAddr, grant: OAuth2Grant, secret: Option<&str>) -> OAuth2Config {
+    OAuth2Config {
+        grant,
+        token_url: format!("http://{addr}/token"),
+        client_id: "app".into(),
+        client_secret: secret.map(String::from),
+        scope: Some("read".into()),
+    }
+}
+
+#[tokio::test]
+async fn client_credentials_are_sent_as_basic_auth_and_cached() {
+    let (addr, seen) =
+        token_endpoint(|n| json!({"access_token": format!("t{n}"), "expires_in": 3600})).await;
+    let reqx = Reqx::default();
+    let config = config(addr, OAuth2Grant::ClientCredentials, Some("s3cret"));
+    let token = reqx.authorize(None, &config).await.unwrap();
+    assert_eq!(token.access_token, "t1");
+    assert_eq!(token.token_type, "Bearer");
+    let left = token.expires_at.unwrap() - chrono::Utc::now();
+    assert!(left.num_seconds() > 3500 && left.num_seconds() <= 3600);
+    assert_eq!(
+        reqx.authorize(None, &config).await.unwrap().access_token,
+        "t1"
+    );
+    {
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        let (auth, params) = &seen[0];
+        //app:s3cret
+        assert_eq!(auth.as_deref(), Some("Basic YXBwOnMzY3JldA=="));
+        assert_eq!(params["grant_type"], "client_credentials");
+        assert_eq!(params["scope"], "read");
+        assert!(!params.contains_key("client_id"));
+    }
+    //another secret is another client, its token is not reused
+    let other = OAuth2Config {
+        client_secret: Some("rotated".into()),
+        ..config.clone()
+    };
+    assert_eq!(
+        reqx.authorize(None, &other).await.unwrap().access_token,
+        "t2"
+    );
+    //the token goes out as a bearer on sends
+    let mut request = ReqxRequest::new(Method::GET, format!("http://{addr}/me"));
+    request.authentication = ReqxAuth::OAuth2(config);
+    let response = reqx.send(&request).await.unwrap();
+    let echo: Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(echo["headers"]["authorization"], "Bearer t1");
+}
+
+#[tokio::test]
+async fn password_grant_sends_the_user_and_a_public_client_id() {
+    let (addr, seen) = token_endpoint(|_| json!({"access_token": "pt"})).await;
+    let grant = OAuth2Grant::Password {
+        username: "ann".into(),
+        password: "pw".into(),
+    };
+    let config = config(addr, grant, None);
+    let reqx = Reqx::default();
+    let token = reqx.authorize(Some("dev"), &config).await.unwrap();
+    assert_eq!(token.access_token, "pt");
+    assert_eq!(token.expires_at, None);
+    let changed = OAuth2Config {
+        grant: OAuth2Grant::Password {
+            username: "ann".into(),
+            password: "new".into(),
+        },
+        ..config
+    };
+    reqx.authorize(Some("dev"), &changed).await.unwrap();
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2, "a changed password asks for a new token");
+    let (auth, params) = &seen[0];
+    assert_eq!(auth, &None);
+    assert_eq!(params["grant_type"], "password");
+    assert_eq!(params["username"], "ann");
+    assert_eq!(params["password"], "pw");
+    assert_eq!(params["client_id"], "app");
+}
+
+#[tokio::test]
+async fn expired_tokens_are_refreshed_and_keep_their_refresh_token() {
+    let (addr, seen) = token_endpoint(|n| match n {
+        1 => json!({"access_token": "old", "expires_in": 0, "refresh_token": "r1"}),
+        _ => json!({"access_token": "new", "expires_in": 3600}),
+    })
+    .await;
+    let reqx = Reqx::default();
+    let config = config(addr, OAuth2Grant::ClientCredentials, Some("s"));
+    let token = reqx.authorize(None, &config).await.unwrap();
+    assert!(token.is_expired());
+    let token = reqx.authorize(None, &config).await.unwrap();
+    assert_eq!(token.access_token, "new");
+    assert_eq!(token.refresh_token.as_deref(), Some("r1"));
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[1].1["grant_type"], "refresh_token");
+    assert_eq!(seen[1].1["refresh_token"], "r1");
+}
+
+#[tokio::test]
+async fn an_unrepresentable_expiry_means_no_expiry() {
+    let (addr, _) = token_endpoint(|_| json!({"access_token": "t", "expires_in": i64::MAX})).await;
+    let config = config(addr, OAuth2Grant::ClientCredentials, Some("s"));
+    let token = Reqx::default().authorize(None, &config).await.unwrap();
+    assert_eq!(token.expires_at, None);
+    assert!(!token.is_expired());
+}
+
+type Opened = Arc<Mutex<Option<url::Url>>>;
+
+//stands in for the browser: records the authorization url, then calls the loopback redirect
+//with whatever `answer` makes of the query the flow sent
+fn browser(
+    answer: impl Fn(&HashMap<String, String>) -> String + Send + Sync + 'static,
+) -> (AuthorizeHook, Opened) {
+    let opened: Opened = Arc::default();
+    let record = opened.clone();
+    let hook = AuthorizeHook::new(move |url| {
+        let url = url::Url::parse(url).unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let redirect = query["redirect_uri"].clone();
+        let callback = format!("{redirect}?{}", answer(&query));
+        *record.lock().unwrap() = Some(url);
+        tokio::spawn(async move {
+            //a stray request first, like a browser asking for the favicon
+            let root = redirect.replace("/callback", "/favicon.ico");
+            let _ = reqwest::get(root).await;
+            let _ = reqwest::get(callback).await;
+        });
+    });
+    (hook, opened)
+}
+
+fn code_grant(addr: SocketAddr) -> OAuth2Config {
+    let grant = OAuth2Grant::AuthorizationCode {
+        auth_url: format!("http://{addr}/authorize?audience=api"),
+        redirect_port: 0,
+    };
+    config(addr, grant, None)
+}
+
+#[tokio::test]
+async fn authorization_code_flow_proves_the_verifier() {
+    let (addr, seen) = token_endpoint(|_| json!({"access_token": "ct", "expires_in": 60})).await;
+    let (hook, opened) = browser(|query| format!("code=c0de&state={}", query["state"]));
+    let reqx = Reqx::default().with_authorize_hook(hook);
+    let token = reqx.authorize(None, &code_grant(addr)).await.unwrap();
+    assert_eq!(token.access_token, "ct");
+
+    let url = opened.lock().unwrap().clone().unwrap();
+    assert_eq!(url.path(), "/authorize");
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    assert_eq!(query["audience"], "api");
+    assert_eq!(query["response_type"], "code");
+    assert_eq!(query["client_id"], "app");
+    assert_eq!(query["scope"], "read");
+    assert_eq!(query["code_challenge_method"], "S256");
+    assert!(query["redirect_uri"].starts_with("http://127.0.0.1:"));
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    let (auth, params) = &seen[0];
+    assert_eq!(auth, &None);
+    assert_eq!(params["grant_type"], "authorization_code");
+    assert_eq!(params["code"], "c0de");
+    assert_eq!(params["client_id"], "app");
+    assert_eq!(params["redirect_uri"], query["redirect_uri"]);
+    //s256: the challenge is the unpadded base64url of the verifier's sha-256
+    let verifier = &params["code_verifier"];
+    assert!((43..=128).contains(&verifier.len()), "{verifier}");
+    assert_eq!(
+        URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())),
+        query["code_challenge"]
+    );
+}
+
+#[tokio::test]
+async fn authorization_code_flow_rejects_another_state() {
+    let (addr, seen) = token_endpoint(|_| json!({"access_token": "ct"})).await;
+    let (hook, _) = browser(|query| format!("code=c0de&state={}x", query["state"]));
+    let reqx = Reqx::default().with_authorize_hook(hook);
+    let error = reqx.authorize(None, &code_grant(addr)).await.unwrap_err();
+    assert!(
+        matches!(error, ReqxError::OAuth(OAuthError::StateMismatch)),
+        "{error}"
+    );
+    //the code is never exchanged
+    assert!(seen.lock().unwrap().is_empty());
+
+    let (hook, _) = browser(|query| {
+        format!(
+            "error=access_denied&error_description=no+thanks&state={}",
+            query["state"]
+        )
+    });
+    let reqx = Reqx::default().with_authorize_hook(hook);
+    let error = reqx.authorize(None, &code_grant(addr)).await.unwrap_err();
+    assert!(
+        matches!(&error, ReqxError::OAuth(OAuthError::AuthorizationDenied(d)) if d == "no thanks"),
+        "{error}"
+    );
+}
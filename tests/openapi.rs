@@ -0,0 +1,85 @@
+use argus::{import::openapi, reqx::ReqxAuth};
+use serde_json::json;
+
+fn spec(servers: serde_json::Value) -> String {
+    json!({
+        "openapi": "3.0.3",
+        "info": {"title": "Shop"},
+        "servers": servers,
+        "paths": {
+            "/items/{id}": {
+                "get": {
+                    "operationId": "getItem",
+                    "parameters": [
+                        {"name": "id", "in": "path", "example": 7},
+                        {"name": "password", "in": "query", "example": "plain"},
+                        {"name": "session", "in": "cookie", "example": "s1"},
+                        {"name": "theme", "in": "cookie", "example": "dark"}
+                    ],
+                    "security": [{"login": [], "cookieKey": []}]
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "login": {"type": "http", "scheme": "basic"},
+                "cookieKey": {"type": "apiKey", "in": "cookie", "name": "api"}
+            }
+        }
+    })
+    .to_string()
+}
+
+#[test]
+fn without_servers_the_base_url_is_empty_and_reported() {
+    let import = openapi::import(&spec(json!([]))).unwrap();
+    let env = &import.environments[0];
+    assert_eq!(env.variables["baseUrl"].value, "");
+    let request = &import.collection.root.requests["getItem"];
+    assert!(request.url.starts_with("{{baseUrl}}/items/"));
+    assert!(import
+        .issues
+        .iter()
+        .any(|i| i.message.contains("no servers")));
+}
+
+#[test]
+fn server_urls_lose_their_trailing_slash() {
+    let servers = json!([{"url": "https://{region}.example.com/v1/", "variables": {"region": {"default": "eu"}}}]);
+    let import = openapi::import(&spec(servers)).unwrap();
+    let env = &import.environments[0];
+    assert_eq!(env.variables["baseUrl"].value, "https://eu.example.com/v1");
+}
+
+#[test]
+fn credentials_do_not_share_names_with_parameters() {
+    let import = openapi::import(&spec(json!([{"url": "/"}]))).unwrap();
+    let env = &import.environments[0];
+    let password = &env.variables["password"];
+    assert_eq!(password.value, "plain");
+    assert!(!password.secret);
+    assert!(env.variables["login.password"].secret);
+    assert!(env.variables["cookieKey.key"].secret);
+    let request = &import.collection.root.requests["getItem"];
+    assert!(matches!(
+        &request.authentication,
+        ReqxAuth::Basic { username, password: Some(password) }
+            if username == "{{login.username}}" && password == "{{login.password}}"
+    ));
+}
+
+#[test]
+fn cookies_are_joined_into_one_header() {
+    let import = openapi::import(&spec(json!([{"url": "/"}]))).unwrap();
+    let request = &import.collection.root.requests["getItem"];
+    let cookies: Vec<&str> = request
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+        .map(|(_, value)| value.as_str())
+        .collect();
+    assert_eq!(
+        cookies,
+        ["session={{session}}; theme={{theme}}; api={{cookieKey.key}}"]
+    );
+}
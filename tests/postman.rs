@@ -0,0 +1,173 @@
+use argus::{
+    import::{postman, ImportError},
+    reqx::{ApiKeyLocation, FormPart, ReqxAuth, ReqxBody},
+};
+use http::Method;
+
+fn collection() -> postman::PostmanImport {
+    postman::import_collection(include_str!("fixtures/postman_collection.json")).unwrap()
+}
+
+fn issues(import: &postman::PostmanImport, item: &str) -> Vec<String> {
+    import
+        .issues
+        .iter()
+        .filter(|issue| issue.item == item)
+        .map(|issue| issue.message.clone())
+        .collect()
+}
+
+#[test]
+fn folders_and_inherited_auth() {
+    let import = collection();
+    assert_eq!(import.collection.name, "Shop");
+    let root = &import.collection.root;
+    assert_eq!(root.folders.keys().collect::<Vec<_>>(), ["Users"]);
+    assert_eq!(
+        root.requests.keys().collect::<Vec<_>>(),
+        ["Bad", "Login", "Plain", "Upload"]
+    );
+    let users = &root.folders["Users"];
+    assert_eq!(
+        users.requests.keys().collect::<Vec<_>>(),
+        ["Create user", "Get user"]
+    );
+
+    //the folder's basic auth wins over the collection's bearer token
+    let get = &users.requests["Get user"];
+    assert_eq!(get.method, Method::GET);
+    assert_eq!(
+        get.authentication,
+        ReqxAuth::Basic {
+            username: "ann".into(),
+            password: Some("{{password}}".into()),
+        }
+    );
+    assert_eq!(get.headers, [("Accept".into(), "application/json".into())]);
+    assert_eq!(root.requests["Plain"].url, "https://shop.test/ping");
+    assert_eq!(
+        root.requests["Bad"].authentication,
+        ReqxAuth::Bearer("{{token}}".into())
+    );
+    assert_eq!(root.requests["Upload"].authentication, ReqxAuth::None);
+}
+
+#[test]
+fn path_variables_become_variables() {
+    let import = collection();
+    let get = &import.collection.root.folders["Users"].requests["Get user"];
+    assert_eq!(
+        get.url,
+        "{{baseUrl}}/users/{{id}}/orders/{{order}}?expand=1"
+    );
+    assert_eq!(import.variables.get("order"), Some("7"));
+    //the collection variable is kept and the clash reported
+    assert_eq!(import.variables.get("id"), Some("1"));
+    assert!(issues(&import, "Shop/Users/Get user")
+        .iter()
+        .any(|m| m.contains("`:id` is `42`")));
+}
+
+#[test]
+fn api_keys_in_headers_and_queries() {
+    let import = collection();
+    let create = &import.collection.root.folders["Users"].requests["Create user"];
+    assert_eq!(create.method, Method::POST);
+    assert_eq!(create.url, "https://shop.test:8443/users");
+    assert_eq!(
+        create.authentication,
+        ReqxAuth::ApiKey {
+            name: "X-Api-Key".into(),
+            value: "{{apiKey}}".into(),
+            location: ApiKeyLocation::Header,
+        }
+    );
+    assert_eq!(
+        import.collection.root.requests["Login"].authentication,
+        ReqxAuth::ApiKey {
+            name: "api_key".into(),
+            value: "k".into(),
+            location: ApiKeyLocation::Query,
+        }
+    );
+}
+
+#[test]
+fn raw_urlencoded_and_form_data_bodies() {
+    let import = collection();
+    let root = &import.collection.root;
+    let create = &root.folders["Users"].requests["Create user"];
+    assert_eq!(create.body, ReqxBody::Text("{\"name\":\"ann\"}".into()));
+    assert_eq!(create.header("content-type"), Some("application/json"));
+
+    assert_eq!(
+        root.requests["Login"].body,
+        ReqxBody::Form(vec![
+            ("user".into(), "ann".into()),
+            ("pass".into(), "{{password}}".into()),
+        ])
+    );
+    assert_eq!(
+        root.requests["Upload"].body,
+        ReqxBody::Multipart(vec![
+            FormPart::text("title", "cat"),
+            FormPart::text("meta", "{}").content_type("application/json"),
+            FormPart::file("photos", "/tmp/a.png"),
+            FormPart::file("photos", "/tmp/b.png"),
+        ])
+    );
+    assert_eq!(
+        issues(&import, "Shop/Upload"),
+        ["file part `empty` has no file, skipped"]
+    );
+}
+
+#[test]
+fn collection_variables_keep_secrets_and_skip_disabled() {
+    let import = collection();
+    let variables = &import.variables;
+    assert_eq!(variables.get("baseUrl"), Some("https://shop.test"));
+    assert!(variables.variables["token"].secret);
+    assert!(!variables.variables["baseUrl"].secret);
+    assert_eq!(variables.get("off"), None);
+}
+
+#[test]
+fn scripts_and_broken_items_are_reported_not_fatal() {
+    let import = collection();
+    assert_eq!(
+        issues(&import, "Shop/Users/Get user")
+            .iter()
+            .filter(|m| m.contains("script"))
+            .collect::<Vec<_>>(),
+        ["pre-request script is not supported, skipped"]
+    );
+    assert_eq!(
+        issues(&import, "Shop/Bad"),
+        ["invalid method `GE T`, using GET"]
+    );
+    assert!(issues(&import, "Shop/Broken")[0].starts_with("malformed request, skipped"));
+    assert_eq!(
+        issues(&import, "Shop/Empty"),
+        ["item has neither a request nor children, skipped"]
+    );
+    assert!(matches!(
+        postman::import_collection(r#"{"info":{"name":"x","schema":"v1"}}"#),
+        Err(ImportError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn environments_import_with_secrets() {
+    let (env, issues) =
+        postman::import_environment(include_str!("fixtures/postman_environment.json")).unwrap();
+    assert_eq!(env.name, "Staging");
+    assert_eq!(env.get("baseUrl"), Some("https://staging.shop.test"));
+    assert_eq!(env.get("password"), Some("hunter2"));
+    assert!(env.variables["password"].secret);
+    assert_eq!(env.get("port"), Some("8443"));
+    assert_eq!(env.get("old"), None);
+    assert_eq!(env.variables.len(), 3);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("`old` is disabled"));
+}
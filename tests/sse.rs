@@ -0,0 +1,63 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use argus::reqx::{Reqx, ReqxRequest, SseStream, SseUpdate};
+use http::Method;
+
+async fn next(stream: &mut SseStream) -> SseUpdate {
+    tokio::time::timeout(Duration::from_secs(5), stream.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn streams_follow_redirects_and_resume_from_the_last_id() {
+    let addr = common::serve(|req| match req.uri().path() {
+        "/old" => http::Response::builder()
+            .status(302)
+            .header("location", "/stream")
+            .body(Default::default())
+            .unwrap(),
+        _ => match req.headers().get("last-event-id") {
+            None => common::response(
+                200,
+                "text/event-stream; charset=utf-8",
+                "retry: 10\nid: 1\ndata: hi\n\n",
+            ),
+            Some(id) => common::response(200, "text/plain", id.to_str().unwrap().to_string()),
+        },
+    })
+    .await;
+    let reqx = Arc::new(Reqx::default());
+    let mut stream = reqx.events(&ReqxRequest::new(Method::GET, format!("http://{addr}/old")));
+
+    let SseUpdate::Opened(response) = next(&mut stream).await else {
+        panic!("stream did not open");
+    };
+    assert_eq!(response.status, 200);
+    assert_eq!(response.redirects.len(), 1);
+    assert_eq!(response.redirects[0].status, 302);
+    let SseUpdate::Event(event) = next(&mut stream).await else {
+        panic!("no event");
+    };
+    assert_eq!(
+        (event.id.as_deref(), event.data.as_str()),
+        (Some("1"), "hi")
+    );
+    assert!(
+        matches!(next(&mut stream).await, SseUpdate::Retry(d) if d == Duration::from_millis(10))
+    );
+    assert!(matches!(
+        next(&mut stream).await,
+        SseUpdate::Reconnecting { error: None, .. }
+    ));
+    //the reconnect goes to the original url, is redirected again and carries the id
+    let SseUpdate::Rejected(response) = next(&mut stream).await else {
+        panic!("reconnect was not rejected");
+    };
+    assert_eq!(response.status, 200);
+    assert_eq!(response.redirects.len(), 1);
+    assert_eq!(response.body, b"1");
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::time::Duration;
+
+use argus::reqx::{Reqx, ReqxConfig, ReqxRequest, ReqxTiming};
+use http::Method;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+const WAIT: Duration = Duration::from_millis(60);
+const TRICKLE: Duration = Duration::from_millis(40);
+
+//waits before the head and again halfway through the body
+async fn slow_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request).await;
+                tokio::time::sleep(WAIT).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\nab")
+                    .await;
+                tokio::time::sleep(TRICKLE).await;
+                let _ = stream.write_all(b"cd").await;
+            });
+        }
+    });
+    port
+}
+
+//each phase happens after the one before, so together they fit in the total
+fn assert_ordered(timing: &ReqxTiming) {
+    let phases = timing.dns.unwrap()
+        + timing.connect.unwrap()
+        + timing.tls.unwrap_or_default()
+        + timing.ttfb
+        + timing.download;
+    assert!(phases <= timing.total, "{timing:?}");
+}
+
+#[tokio::test]
+async fn plain_requests_time_dns_connect_ttfb_and_download() {
+    let port = slow_server().await;
+    let request = ReqxRequest::new(Method::GET, format!("http://localhost:{port}/"));
+    let response = Reqx::default().send(&request).await.unwrap();
+    assert_eq!(response.body, b"abcd");
+    let timing = &response.timing;
+    assert!(timing.dns.is_some(), "{timing:?}");
+    assert!(timing.connect.is_some(), "{timing:?}");
+    assert_eq!(timing.tls, None);
+    assert!(timing.ttfb >= WAIT, "{timing:?}");
+    assert!(timing.download >= TRICKLE, "{timing:?}");
+    assert_ordered(timing);
+    assert!(response.remote_addr.unwrap().ip().is_loopback());
+}
+
+#[tokio::test]
+async fn tls_requests_time_the_handshake() {
+    let addr = common::tls_server().await;
+    let config = ReqxConfig {
+        accept_invalid_certs: true,
+        ..Default::default()
+    };
+    let request = ReqxRequest::new(Method::GET, format!("https://localhost:{}/", addr.port()));
+    let response = Reqx::new(config).unwrap().send(&request).await.unwrap();
+    assert_eq!(response.body, b"ok");
+    let timing = &response.timing;
+    assert!(timing.dns.is_some(), "{timing:?}");
+    assert!(timing.connect.is_some(), "{timing:?}");
+    assert!(
+        timing.tls.is_some_and(|tls| tls > Duration::ZERO),
+        "{timing:?}"
+    );
+    assert_ordered(timing);
+}
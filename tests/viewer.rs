@@ -0,0 +1,166 @@
+use std::collections::BTreeSet;
+
+use argus::viewer::{self, BodyKind, BodyView};
+
+#[test]
+fn json_is_indented_in_its_own_order_and_folds() {
+    let doc = viewer::json(br#"{"b":1.50,"a":[1,2],"e":{},"s":"x,{\"y\":[]}"}"#).unwrap();
+    assert_eq!(
+        doc.text(),
+        concat!(
+            "{\n",
+            "  \"b\": 1.50,\n",
+            "  \"a\": [\n",
+            "    1,\n",
+            "    2\n",
+            "  ],\n",
+            "  \"e\": {},\n",
+            "  \"s\": \"x,{\\\"y\\\":[]}\"\n",
+            "}\n",
+        )
+    );
+    assert_eq!(doc.foldable().collect::<Vec<_>>(), [0, 2]);
+    assert_eq!(doc.lines[2].fold_end, Some(5));
+    assert_eq!(
+        doc.render(&BTreeSet::from([2])),
+        "{\n  \"b\": 1.50,\n  \"a\": [ … ],\n  \"e\": {},\n  \"s\": \"x,{\\\"y\\\":[]}\"\n}\n"
+    );
+    assert_eq!(doc.render(&BTreeSet::from([0])), "{ … }\n");
+}
+
+#[test]
+fn malformed_json_is_refused_and_shown_as_text() {
+    for body in [&b"{\"a\":"[..], b"[1,]", b"", b"{} {}"] {
+        assert_eq!(viewer::json(body), None, "{body:?}");
+    }
+    assert_eq!(
+        viewer::view_body(Some("application/json"), b"{\"a\":"),
+        BodyView::Text("{\"a\":".into())
+    );
+    assert!(matches!(
+        viewer::view_body(Some("application/json"), b"\xff{"),
+        BodyView::Hex(_)
+    ));
+}
+
+#[test]
+fn xml_keeps_text_only_elements_on_one_line() {
+    let body = br#"<?xml version="1.0"?><root a="x>y"><item>one</item><empty></empty><self/><!-- c --><![CDATA[<raw>]]></root>"#;
+    let doc = viewer::xml(body).unwrap();
+    assert_eq!(
+        doc.text(),
+        concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<root a=\"x>y\">\n",
+            "  <item>one</item>\n",
+            "  <empty></empty>\n",
+            "  <self/>\n",
+            "  <!-- c -->\n",
+            "  <![CDATA[<raw>]]>\n",
+            "</root>\n",
+        )
+    );
+    assert_eq!(doc.lines[1].fold_end, Some(7));
+}
+
+#[test]
+fn malformed_xml_is_still_laid_out() {
+    let doc = viewer::xml(b"<a><b>text</a></c> 1 < 2").unwrap();
+    //the end tag of `a` closes the unclosed `b`, a stray end tag stays where it is
+    assert_eq!(doc.text(), "<a>\n  <b>\n    text\n</a>\n</c>\n1\n<\n2\n");
+    assert_eq!(doc.lines[0].fold_end, Some(3));
+    assert_eq!(viewer::xml(b"\xff<a/>"), None);
+    assert!(viewer::xml(b"").unwrap().lines.is_empty());
+}
+
+#[test]
+fn html_knows_void_raw_and_unclosed_elements() {
+    let body = b"<!DOCTYPE html><html><head><meta charset=utf-8><script>if (a < b) { x(\"</p>\") }</script></head><body><ul><li>one<li>two</ul><br><p>end</body></html>";
+    let doc = viewer::html(body).unwrap();
+    assert_eq!(
+        doc.text(),
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "  <head>\n",
+            "    <meta charset=utf-8>\n",
+            "    <script>if (a < b) { x(\"</p>\") }</script>\n",
+            "  </head>\n",
+            "  <body>\n",
+            "    <ul>\n",
+            "      <li>\n",
+            "        one\n",
+            "      <li>\n",
+            "        two\n",
+            "    </ul>\n",
+            "    <br>\n",
+            "    <p>\n",
+            "      end\n",
+            "  </body>\n",
+            "</html>\n",
+        )
+    );
+}
+
+#[test]
+fn hex_dump_pads_the_last_row() {
+    assert_eq!(viewer::hex_dump(b""), "");
+    let dump = viewer::hex_dump(b"Hello, world\n\0\0\0\x7fA");
+    assert_eq!(
+        dump,
+        concat!(
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 0a 00 00 00  |Hello, world....|\n",
+            "00000010  7f 41                                             |.A|\n",
+        )
+    );
+}
+
+#[test]
+fn detection_prefers_a_specific_content_type() {
+    let cases: [(Option<&str>, &[u8], BodyKind); 14] = [
+        (
+            Some("application/json; charset=utf-8"),
+            b"nope",
+            BodyKind::Json,
+        ),
+        (Some("application/problem+json"), b"{}", BodyKind::Json),
+        (Some("Text/HTML"), b"x", BodyKind::Html),
+        (Some("application/atom+xml"), b"x", BodyKind::Xml),
+        (Some("image/png"), b"\x89PNG\r\n\x1a\n...", BodyKind::Image),
+        //an image type on something that is not one is sniffed
+        (Some("image/png"), b"{\"a\":1}", BodyKind::Json),
+        (Some("text/plain"), b"{\"a\":1}", BodyKind::Text),
+        (None, b"  [1, 2]", BodyKind::Json),
+        (None, b"{not json", BodyKind::Text),
+        (None, b"<!doctype HTML><p>", BodyKind::Html),
+        (None, b"<feed/>", BodyKind::Xml),
+        (
+            Some("application/octet-stream"),
+            b"GIF89a..",
+            BodyKind::Image,
+        ),
+        (None, b"a\0b", BodyKind::Binary),
+        (None, b"\xff\xfe", BodyKind::Binary),
+    ];
+    for (content_type, body, kind) in cases {
+        assert_eq!(
+            viewer::detect(content_type, body),
+            kind,
+            "{content_type:?} {body:?}"
+        );
+    }
+}
+
+#[test]
+fn empty_bodies_are_empty_text() {
+    assert_eq!(viewer::detect(None, b""), BodyKind::Text);
+    assert_eq!(viewer::view_body(None, b""), BodyView::Text(String::new()));
+    assert_eq!(
+        viewer::view_body(Some("application/json"), b""),
+        BodyView::Text(String::new())
+    );
+    assert_eq!(
+        viewer::view_body(Some("application/octet-stream"), b""),
+        BodyView::Text(String::new())
+    );
+}
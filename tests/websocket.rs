@@ -0,0 +1,204 @@
+use std::{net::SocketAddr, time::Duration};
+
+use argus::reqx::{
+    ApiKeyLocation, Reqx, ReqxAuth, ReqxConfig, ReqxError, WsDirection, WsEvent, WsFrame, WsLog,
+    WsRequest, WsSession,
+};
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+//the handshake callback, its signature is tungstenite's
+#[allow(clippy::result_large_err)]
+fn pick(req: &Request, mut res: Response) -> Result<Response, ErrorResponse> {
+    let offered = req
+        .headers()
+        .get("sec-websocket-protocol")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if offered.split(',').any(|p| p.trim() == "chat") {
+        res.headers_mut()
+            .insert("sec-websocket-protocol", "chat".parse().unwrap());
+    }
+    Ok(res)
+}
+
+//echoes text and binary frames, picks `chat` when it is offered and answers a close
+async fn echo_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_hdr_async(stream, pick)
+                    .await
+                    .unwrap();
+                while let Some(Ok(message)) = ws.next().await {
+                    if message.is_text() || message.is_binary() {
+                        let _ = ws.send(message).await;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+async fn next_frame(session: &mut WsSession) -> WsFrame {
+    let event = tokio::time::timeout(Duration::from_secs(5), session.recv())
+        .await
+        .unwrap();
+    match event {
+        Some(WsEvent::Message(message)) => {
+            assert_eq!(message.direction, WsDirection::Received);
+            message.frame
+        }
+        other => panic!("expected a frame, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn frames_are_echoed_logged_and_closed() {
+    let addr = echo_server().await;
+    let mut request = WsRequest::new(format!("ws://{addr}/socket"));
+    request.subprotocols = vec!["other".into(), "chat".into()];
+    let mut session = Reqx::default().websocket(&request).await.unwrap();
+    assert_eq!(session.protocol.as_deref(), Some("chat"));
+
+    session.send_text("hello").unwrap();
+    assert_eq!(
+        next_frame(&mut session).await,
+        WsFrame::Text("hello".into())
+    );
+    session.send_binary(vec![0, 159, 255]).unwrap();
+    assert_eq!(
+        next_frame(&mut session).await,
+        WsFrame::Binary(vec![0, 159, 255])
+    );
+    session.ping(b"p1".to_vec()).unwrap();
+    assert_eq!(
+        next_frame(&mut session).await,
+        WsFrame::Pong(b"p1".to_vec())
+    );
+    session.close(1000, "bye").unwrap();
+    assert_eq!(
+        next_frame(&mut session).await,
+        WsFrame::Close(Some((1000, "bye".into())))
+    );
+    assert!(matches!(session.recv().await, Some(WsEvent::Closed)));
+
+    let log = session.log();
+    assert_eq!(log.protocol.as_deref(), Some("chat"));
+    assert_eq!(log.url, format!("ws://{addr}/socket"));
+    let frames: Vec<_> = log
+        .messages
+        .iter()
+        .map(|m| (m.direction, m.frame.clone()))
+        .collect();
+    use WsDirection::{Received, Sent};
+    assert_eq!(
+        frames,
+        [
+            (Sent, WsFrame::Text("hello".into())),
+            (Received, WsFrame::Text("hello".into())),
+            (Sent, WsFrame::Binary(vec![0, 159, 255])),
+            (Received, WsFrame::Binary(vec![0, 159, 255])),
+            (Sent, WsFrame::Ping(b"p1".to_vec())),
+            (Received, WsFrame::Pong(b"p1".to_vec())),
+            (Sent, WsFrame::Close(Some((1000, "bye".into())))),
+            (Received, WsFrame::Close(Some((1000, "bye".into())))),
+        ]
+    );
+    assert_eq!(WsLog::from_json(&log.to_json()).unwrap(), log);
+    assert!(log
+        .to_text()
+        .lines()
+        .nth(2)
+        .unwrap()
+        .ends_with("> binary AJ//"));
+}
+
+#[tokio::test]
+async fn a_server_agreeing_to_no_offered_subprotocol_is_refused() {
+    let addr = echo_server().await;
+    let mut request = WsRequest::new(format!("ws://{addr}/"));
+    request.subprotocols = vec!["other".into()];
+    let error = Reqx::default().websocket(&request).await.unwrap_err();
+    assert!(matches!(error, ReqxError::WebSocket(_)), "{error}");
+    //without an offer none is needed
+    let session = Reqx::default()
+        .websocket(&WsRequest::new(format!("ws://{addr}/")))
+        .await
+        .unwrap();
+    assert_eq!(session.protocol, None);
+}
+
+#[tokio::test]
+async fn a_close_the_server_never_answers_times_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        //never reads, so the close is not answered
+        tokio::time::sleep(Duration::from_secs(30)).await;
+    });
+    let reqx = Reqx::new(ReqxConfig {
+        timeout: Some(Duration::from_millis(300)),
+        ..Default::default()
+    })
+    .unwrap();
+    let request = WsRequest::new(format!("ws://{addr}/"));
+    let mut session = reqx.websocket(&request).await.unwrap();
+    session.close(1000, "bye").unwrap();
+    let event = tokio::time::timeout(Duration::from_secs(3), session.recv())
+        .await
+        .expect("close timed out");
+    assert!(matches!(event, Some(WsEvent::Closed)));
+    assert!(matches!(
+        session.log().messages.last().map(|m| &m.frame),
+        Some(WsFrame::Close(_))
+    ));
+}
+
+#[tokio::test]
+async fn a_query_api_key_is_sent_but_not_logged() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (seen, uri) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut seen = Some(seen);
+        #[allow(clippy::result_large_err)]
+        let callback = |req: &Request, res: Response| {
+            let _ = seen.take().unwrap().send(req.uri().to_string());
+            Ok(res)
+        };
+        let mut ws = tokio_tungstenite::accept_hdr_async(stream, callback)
+            .await
+            .unwrap();
+        while ws.next().await.is_some() {}
+    });
+    let mut request = WsRequest::new(format!("ws://{addr}/socket?room=1"));
+    request.request.authentication = ReqxAuth::ApiKey {
+        name: "sig".into(),
+        value: "WSKEYVAL".into(),
+        location: ApiKeyLocation::Query,
+    };
+    let session = Reqx::default().websocket(&request).await.unwrap();
+    assert_eq!(uri.await.unwrap(), "/socket?room=1&sig=WSKEYVAL");
+
+    let log = session.log();
+    assert!(!session.url.contains("WSKEYVAL"), "{}", session.url);
+    assert!(
+        log.url
+            .starts_with(&format!("ws://{addr}/socket?room=1&sig=")),
+        "{}",
+        log.url
+    );
+    let path = std::env::temp_dir().join(format!("argus-ws-{}.json", std::process::id()));
+    log.save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(!saved.contains("WSKEYVAL"));
+}
@@ -1,8 +1,10 @@
 pub(crate) mod bindgroups;
 mod pipeline;
+mod preview;
 mod state;
 mod window;
 pub(crate) use pipeline::pipeline_helper;
-pub use state::WgpuState;
+pub use preview::ImagePreview;
+pub use state::{Texture2D, WgpuState};
 pub use window::{YHandler, YMessage, YWindow, YWindowData};
 pub use winit;
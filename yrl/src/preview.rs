@@ -0,0 +1,119 @@
+use image::ImageError;
+
+use crate::{
+    bindgroups::{BindGroupHelper, BindGroupInfoKind},
+    pipeline_helper,
+    state::{Texture2D, WgpuState},
+};
+
+//a quad generated from the vertex index, scaled by `fit` to keep the image aspect ratio
+const SHADER: &str = r#"
+struct Fit {
+    scale: vec2<f32>,
+    _pad: vec2<f32>,
+};
+@group(0) @binding(0) var<uniform> fit: Fit;
+@group(1) @binding(0) var image: texture_2d<f32>;
+@group(1) @binding(1) var image_sampler: sampler;
+
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+    var out: VertexOut;
+    out.position = vec4<f32>((corner * 2.0 - 1.0) * fit.scale, 0.0, 1.0);
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return textureSample(image, image_sampler, in.uv);
+}
+"#;
+
+//an image drawn centered in the window, as large as fits without upscaling
+pub struct ImagePreview {
+    texture: Texture2D,
+    pipeline: wgpu::RenderPipeline,
+    fit: wgpu::Buffer,
+    bindgroups: [wgpu::BindGroup; 2],
+}
+impl ImagePreview {
+    //decodes any format the image crate reads, fails on bytes it cannot
+    pub fn new(state: &WgpuState, bytes: Vec<u8>) -> Result<Self, ImageError> {
+        let device = state.device();
+        let texture = Texture2D::new(state.queue(), device, bytes)?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("image preview"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = pipeline_helper::create_pipeline(
+            device,
+            &module,
+            None,
+            &vec![BindGroupInfoKind::Uniform(0), BindGroupInfoKind::Texture(0)],
+        );
+        let fit = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("image preview fit"),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (uniform, _) = BindGroupHelper::create_uniform(device, &fit, 0);
+        let (image, _) =
+            BindGroupHelper::create_texture(device, texture.view(), texture.sampler(), 0);
+        let preview = Self {
+            texture,
+            pipeline,
+            fit,
+            bindgroups: [uniform, image],
+        };
+        preview.fit(state);
+        Ok(preview)
+    }
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+    //has to be called again after the window is resized
+    pub fn fit(&self, state: &WgpuState) {
+        let (width, height) = state.size();
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        let (image_width, image_height) = (self.width() as f32, self.height() as f32);
+        let zoom = (width / image_width).min(height / image_height).min(1.0);
+        let scale = [
+            image_width * zoom / width,
+            image_height * zoom / height,
+            0.0,
+            0.0,
+        ];
+        state
+            .queue()
+            .write_buffer(&self.fit, 0, bytemuck::cast_slice(&scale));
+    }
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass<'_>) {
+        rpass.set_pipeline(&self.pipeline);
+        for (idx, group) in self.bindgroups.iter().enumerate() {
+            rpass.set_bind_group(idx as u32, group, &[]);
+        }
+        rpass.draw(0..6, 0..1);
+    }
+    pub fn render(&self, state: &WgpuState) -> Result<(), wgpu::SurfaceError> {
+        state.render_with(wgpu::Color::BLACK, |rpass| self.draw(rpass))
+    }
+}
@@ -239,6 +239,18 @@ impl<'a> WgpuState<'a> {
     pub fn queue(&self) -> &wgpu::Queue {
         &self.queue
     }
+    pub fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+    pub fn resize(&mut self, width: u32, height: u32) {
+        //a minimized window reports 0x0, which the surface does not accept
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.device, &self.config);
+    }
     pub fn current_shader(&self) -> Option<&Shader> {
         self.shaders.get(self.current_shader.as_ref()?)
     }
@@ -267,6 +279,40 @@ impl<'a> WgpuState<'a> {
             self.current_shader = Some(shader.to_string())
         }
     }
+    //one frame cleared to `clear`, with `draw` recording into its pass
+    pub fn render_with(
+        &self,
+        clear: wgpu::Color,
+        draw: impl FnOnce(&mut wgpu::RenderPass<'_>),
+    ) -> Result<(), wgpu::SurfaceError> {
+        let txt = self.surface.get_current_texture()?;
+        let view = txt
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            draw(&mut rpass);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        txt.present();
+        Ok(())
+    }
     pub fn render(
         &self,
         vertices: wgpu::Buffer,
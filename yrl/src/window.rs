@@ -2,7 +2,7 @@ use winit::{
     application::ApplicationHandler,
     dpi::PhysicalSize,
     event::{Event, WindowEvent},
-    event_loop::{ActiveEventLoop, ControlFlow},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
     window::{Icon, Window, WindowAttributes, WindowId},
 };
 
@@ -13,14 +13,19 @@ pub enum YMessage {
 }
 
 pub trait YHandler {
+    //sent from other threads through the proxy given to create
+    type Event: 'static;
     fn handle_event(
         &mut self,
         ev_loop: &ActiveEventLoop,
         window_id: WindowId,
         event: WindowEvent,
     ) -> YMessage;
+    fn user_event(&mut self, _ev_loop: &ActiveEventLoop, _event: Self::Event) -> YMessage {
+        YMessage::None
+    }
     fn window_req(&mut self, _window: &Window) {}
-    fn create(window: &Window) -> Self;
+    fn create(window: &Window, proxy: EventLoopProxy<Self::Event>) -> Self;
 }
 
 pub struct YWindowData {
@@ -31,10 +36,11 @@ pub struct YWindowData {
     pub control_flow: ControlFlow,
 }
 
-pub struct YWindow<T> {
+pub struct YWindow<T: YHandler> {
     data: YWindowData,
     handler: Option<T>,
     window: Option<Window>,
+    proxy: Option<EventLoopProxy<T::Event>>,
 }
 impl<T> YWindow<T>
 where
@@ -45,6 +51,7 @@ where
             data,
             window: None,
             handler: None,
+            proxy: None,
         }
     }
     pub fn handler(&self) -> Option<&T> {
@@ -58,8 +65,9 @@ where
         }
     }
     pub fn run(&mut self) {
-        let evloop = winit::event_loop::EventLoop::new().unwrap();
+        let evloop = EventLoop::<T::Event>::with_user_event().build().unwrap();
         evloop.set_control_flow(self.data.control_flow);
+        self.proxy = Some(evloop.create_proxy());
         evloop.run_app(self).unwrap()
     }
     fn handle_single_message(&mut self, message: YMessage) {
@@ -87,7 +95,7 @@ where
         }
     }
 }
-impl<T> ApplicationHandler for YWindow<T>
+impl<T> ApplicationHandler<T::Event> for YWindow<T>
 where
     T: YHandler,
 {
@@ -102,9 +110,18 @@ where
             )
             .unwrap();
 
-        self.handler = Some(T::create(&window));
+        let proxy = self.proxy.clone().unwrap();
+        self.handler = Some(T::create(&window, proxy));
         self.window = Some(window);
     }
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: T::Event) {
+        //events sent after the window closed have nobody to go to
+        let Some(handler) = self.handler_mut() else {
+            return;
+        };
+        let msg = handler.user_event(event_loop, event);
+        self.handle_message(msg);
+    }
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,